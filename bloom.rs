@@ -0,0 +1,268 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+A bloom filter of the names, ids and classes of a node's ancestors
+
+Matching a descendant selector like `section span` walks every ancestor of
+the span looking for a section. With the ancestors in a bloom filter most of
+those walks are rejected up front: the filter can't say an ancestor exists,
+but it can say one doesn't. libcss only ever asks for an ancestor by name
+(`named_ancestor_node`), so it is only spared the walks for names;
+`SelectCtx::matched_rules_with_bloom` uses the ids and classes as well.
+Ids and classes are compared as `with_node_id` and `with_node_classes` give
+them.
+
+The filter has to describe exactly the ancestors of the node being selected.
+Callers push each node before descending into its children and pop it on the
+way back up, or let `AncestorBloomFilter::traverse` do it.
+*/
+
+use std::ascii::StrAsciiExt;
+use std::vec;
+use select::SelectHandler;
+
+// 2^12 counters, each indexed by 12 of the low 24 bits of a hash
+static KEY_BITS: uint = 12;
+static KEY_MASK: u32 = (1 << KEY_BITS) - 1;
+static COUNTER_MAX: u8 = 0xff;
+
+/**
+A counting bloom filter of 32 bit hashes, so that removal is possible.
+Counters that overflow stick at their maximum, after which they are never
+decremented, keeping the filter free of false negatives.
+*/
+pub struct BloomFilter {
+    counters: ~[u8]
+}
+
+impl BloomFilter {
+    pub fn new() -> BloomFilter {
+        BloomFilter {
+            counters: vec::from_elem(1 << KEY_BITS, 0u8)
+        }
+    }
+
+    pub fn insert_hash(&mut self, hash: u32) {
+        for &index in indices(hash).iter() {
+            if self.counters[index] != COUNTER_MAX {
+                self.counters[index] += 1;
+            }
+        }
+    }
+
+    /** Removes a hash that was inserted before */
+    pub fn remove_hash(&mut self, hash: u32) {
+        for &index in indices(hash).iter() {
+            assert!(self.counters[index] != 0);
+            if self.counters[index] != COUNTER_MAX {
+                self.counters[index] -= 1;
+            }
+        }
+    }
+
+    /** False if the hash was definitely never inserted */
+    pub fn might_contain_hash(&self, hash: u32) -> bool {
+        indices(hash).iter().all(|&index| self.counters[index] != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counters.iter().all(|&counter| counter == 0)
+    }
+
+    pub fn clear(&mut self) {
+        for counter in self.counters.mut_iter() {
+            *counter = 0;
+        }
+    }
+}
+
+fn indices(hash: u32) -> [uint, ..2] {
+    [(hash & KEY_MASK) as uint, ((hash >> KEY_BITS) & KEY_MASK) as uint]
+}
+
+/** FNV-1a, with a prefix keeping names, ids and classes apart */
+fn hash(kind: u8, value: &str) -> u32 {
+    let mut hash = 0x811c9dc5u32;
+    hash = (hash ^ kind as u32) * 0x01000193;
+    for &b in value.as_bytes().iter() {
+        hash = (hash ^ b as u32) * 0x01000193;
+    }
+    hash
+}
+
+// Element names are compared case-insensitively, as in HTML
+fn name_hash(name: &str) -> u32 { hash('n' as u8, name.to_ascii_lower()) }
+fn id_hash(id: &str) -> u32 { hash('#' as u8, id) }
+fn class_hash(class: &str) -> u32 { hash('.' as u8, class) }
+
+/** The ancestors of the node being selected, as a stack of pushed nodes */
+pub struct AncestorBloomFilter {
+    filter: BloomFilter,
+    // The hashes each pushed node added, to remove them again when it's popped
+    pushed: ~[~[u32]]
+}
+
+impl AncestorBloomFilter {
+    pub fn new() -> AncestorBloomFilter {
+        AncestorBloomFilter {
+            filter: BloomFilter::new(),
+            pushed: ~[]
+        }
+    }
+
+    /** The number of pushed nodes */
+    pub fn depth(&self) -> uint {
+        self.pushed.len()
+    }
+
+    /** Adds a node about to become an ancestor, before descending into its children */
+    pub fn push<N, H: SelectHandler<N>>(&mut self, node: &N, handler: &H) {
+        let mut hashes = ~[handler.with_node_name(node, |name| name_hash(name))];
+        do handler.with_node_id(node) |id| {
+            for &id in id.iter() {
+                if !id.is_empty() {
+                    hashes.push(id_hash(id));
+                }
+            }
+        }
+        do handler.with_node_classes(node) |classes| {
+            for &classes in classes.iter() {
+                for class in classes.split_iter(' ').filter(|c| !c.is_empty()) {
+                    hashes.push(class_hash(class));
+                }
+            }
+        }
+        for &hash in hashes.iter() {
+            self.filter.insert_hash(hash);
+        }
+        self.pushed.push(hashes);
+    }
+
+    /** Removes the most recently pushed node */
+    pub fn pop(&mut self) {
+        let hashes = self.pushed.pop();
+        for &hash in hashes.iter() {
+            self.filter.remove_hash(hash);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.filter.clear();
+        self.pushed = ~[];
+    }
+
+    /** Makes the filter hold the ancestors of `node`, e.g. to start a traversal mid-tree */
+    pub fn rebuild_for<N, H: SelectHandler<N>>(&mut self, node: &N, handler: &H) {
+        self.clear();
+        let mut ancestors = ~[];
+        let mut current = handler.parent_node(node);
+        while current.is_some() {
+            let ancestor = current.unwrap();
+            current = handler.parent_node(&ancestor);
+            ancestors.push(ancestor);
+        }
+        while !ancestors.is_empty() {
+            let ancestor = ancestors.pop();
+            self.push(&ancestor, handler);
+        }
+    }
+
+    pub fn might_have_ancestor_named(&self, name: &str) -> bool {
+        self.filter.might_contain_hash(name_hash(name))
+    }
+
+    pub fn might_have_ancestor_with_id(&self, id: &str) -> bool {
+        self.filter.might_contain_hash(id_hash(id))
+    }
+
+    pub fn might_have_ancestor_with_class(&self, class: &str) -> bool {
+        self.filter.might_contain_hash(class_hash(class))
+    }
+
+    /**
+    Visits `root` and its descendants in document order, keeping the filter
+    in sync with the ancestors of the node `f` is called with. `f` usually
+    passes the filter on to `SelectCtx::select_style_with_bloom`.
+    */
+    pub fn traverse<N, H: SelectHandler<N>>(&mut self,
+                                            root: &N,
+                                            handler: &H,
+                                            children: &fn(&N) -> ~[N],
+                                            f: &fn(&N, &AncestorBloomFilter)) {
+        self.rebuild_for(root, handler);
+        self.traverse_subtree(root, handler, children, f);
+    }
+
+    fn traverse_subtree<N, H: SelectHandler<N>>(&mut self,
+                                                 node: &N,
+                                                 handler: &H,
+                                                 children: &fn(&N) -> ~[N],
+                                                 f: &fn(&N, &AncestorBloomFilter)) {
+        f(node, &*self);
+        let child_nodes = children(node);
+        if child_nodes.is_empty() {
+            return;
+        }
+        self.push(node, handler);
+        for child in child_nodes.iter() {
+            self.traverse_subtree(child, handler, |n| children(n), |n, b| f(n, b));
+        }
+        self.pop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BloomFilter, name_hash, id_hash, class_hash};
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new();
+        assert!(filter.is_empty());
+        assert!(!filter.might_contain_hash(name_hash("div")));
+
+        filter.insert_hash(name_hash("div"));
+        filter.insert_hash(name_hash("div"));
+        filter.insert_hash(name_hash("span"));
+        assert!(filter.might_contain_hash(name_hash("div")));
+        assert!(filter.might_contain_hash(name_hash("span")));
+        assert!(!filter.might_contain_hash(name_hash("section")));
+
+        filter.remove_hash(name_hash("div"));
+        assert!(filter.might_contain_hash(name_hash("div")));
+        filter.remove_hash(name_hash("div"));
+        assert!(!filter.might_contain_hash(name_hash("div")));
+        assert!(filter.might_contain_hash(name_hash("span")));
+
+        filter.clear();
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn test_name_hash_case() {
+        assert!(name_hash("DIV") == name_hash("div"));
+        assert!(name_hash("div") != name_hash("span"));
+    }
+
+    #[test]
+    fn test_hash_kinds() {
+        assert!(name_hash("a") != id_hash("a"));
+        assert!(name_hash("a") != class_hash("a"));
+        assert!(id_hash("a") != class_hash("a"));
+    }
+
+    #[test]
+    fn test_saturated_counters() {
+        let mut filter = BloomFilter::new();
+        for _ in range(0, 300) {
+            filter.insert_hash(name_hash("p"));
+        }
+        for _ in range(0, 300) {
+            filter.remove_hash(name_hash("p"));
+        }
+        // A saturated counter can't know how many hashes it holds, so it stays set
+        assert!(filter.might_contain_hash(name_hash("p")));
+    }
+}
@@ -3,8 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use std::libc::types::os::arch::c95::c_double;
-use std::cmp::Eq;
-use std::ascii::AsciiStr;
+use std::cmp::{Eq, Ordering, Less, Equal, Greater};
+use std::ascii::StrAsciiExt;
+use std::f64;
+use serialize::serialize_number;
 
 macro_rules! define_color(
     ($color:ident, $r:expr, $g:expr, $b:expr) => {
@@ -46,24 +48,25 @@ pub fn rgb(r : u8, g : u8, b : u8) -> Color {
 pub fn hsla(h : f64, s : f64, l : f64, a : f64) -> Color {
     // Algorithm for converting hsl to rbg taken from
     // http://www.w3.org/TR/2003/CR-css3-color-20030514/#hsl-color
+    let s = clamp_unit(s);
+    let l = clamp_unit(l);
     let m2 = if l <= 0.5 { l*(s + 1.0) } else { l + s - l*s };
     let m1 = l*2.0 - m2;
-    let h = h / 360.0; 
-    
+    let h = normalize_hue(h) / 360.0;
+
+    // h is in [-1/3, 4/3) here, so a single wrap brings it into [0, 1)
     fn hue_to_rgb(m1 : f64, m2 : f64, h : f64) -> f64 {
-        let h = if h < 0.0 { h + 1.0 } else if h > 1.0 { h - 1.0 } else { h };
+        let h = if h < 0.0 { h + 1.0 } else if h >= 1.0 { h - 1.0 } else { h };
 
         // FIXME (Rust #7222) - Auugh. Patterns would be much better here
-        if 0.0 <= h && h < 1.0/6.0 {
+        if h < 1.0/6.0 {
             m1 + (m2 - m1)*h*6.0
-        } else if 1.0/6.0 <= h && h < 1.0/2.0 {
+        } else if h < 1.0/2.0 {
             m2
-        } else if 1.0/2.0 <= h && h < 2.0/3.0 {
+        } else if h < 2.0/3.0 {
             m1 + (m2 - m1)*(4.0 - 6.0*h)
-        } else if 2.0/3.0 <= h && h <= 1.0 {
-            m1
         } else {
-          fail!(~"unexpected hue value")
+            m1
         }
     }
 
@@ -79,106 +82,1380 @@ pub fn hsl(h : f64, s : f64, l : f64) -> Color {
 }
 
 impl Color {
-    fn print(&self) -> ~str {
-        fmt!("rgba(%u,%u,%u,%f)", self.red as uint, self.green as uint,
-             self.blue as uint, self.alpha as f64)
+    /**
+    Serializes as `rgb(r, g, b)` when opaque and `rgba(r, g, b, a)` otherwise,
+    the canonical form of CSS Color 3 used by CSSOM
+    */
+    pub fn to_css_string(&self) -> ~str {
+        if self.alpha == 1.0 {
+            fmt!("rgb(%u, %u, %u)", self.red as uint, self.green as uint, self.blue as uint)
+        } else {
+            fmt!("rgba(%u, %u, %u, %s)", self.red as uint, self.green as uint,
+                 self.blue as uint, serialize_alpha(self.alpha))
+        }
+    }
+
+    /** Serializes as `#rrggbb`, or `#rrggbbaa` when not opaque */
+    pub fn to_hex(&self) -> ~str {
+        let mut hex = fmt!("#%02x%02x%02x", self.red as uint, self.green as uint, self.blue as uint);
+        if self.alpha != 1.0 {
+            hex.push_str(fmt!("%02x", alpha_to_u8(self.alpha) as uint));
+        }
+        hex
+    }
+
+    /** The name of this color, if it is opaque and has one */
+    pub fn name(&self) -> Option<&'static str> {
+        if self.alpha != 1.0 {
+            return None;
+        }
+        let value = (self.red as u32 << 16) | (self.green as u32 << 8) | self.blue as u32;
+        let mut low = 0;
+        let mut high = NAMED_COLORS_BY_VALUE.len();
+        while low < high {
+            let middle = (low + high) / 2;
+            let (entry, name) = NAMED_COLORS_BY_VALUE[middle];
+            if value < entry {
+                high = middle;
+            } else if value > entry {
+                low = middle + 1;
+            } else {
+                return Some(name);
+            }
+        }
+        None
+    }
+
+    /** The named color closest to this one in RGB space, ignoring alpha */
+    pub fn nearest_name(&self) -> &'static str {
+        let distance = |other: &Color| {
+            let dr = self.red as int - other.red as int;
+            let dg = self.green as int - other.green as int;
+            let db = self.blue as int - other.blue as int;
+            dr * dr + dg * dg + db * db
+        };
+        let mut nearest = NAMED_COLORS[0];
+        for &(name, color) in NAMED_COLORS.iter() {
+            if distance(&color) < distance(&nearest.second()) {
+                nearest = (name, color);
+            }
+        }
+        nearest.first()
+    }
+}
+
+impl ToStr for Color {
+    fn to_str(&self) -> ~str {
+        self.to_css_string()
+    }
+}
+
+fn alpha_to_u8(alpha : f64) -> u8 {
+    channel_to_u8(alpha)
+}
+
+/**
+CSSOM serializes alpha with two decimals if that identifies the same 8-bit
+value, and with three otherwise
+*/
+fn serialize_alpha(alpha : f64) -> ~str {
+    let byte = alpha_to_u8(alpha);
+    let rounded = ((byte as f64) * 100.0 / 255.0 as c_double).round() / 100.0;
+    if alpha_to_u8(rounded) == byte {
+        f64::to_str_digits(rounded, 2)
+    } else {
+        f64::to_str_digits(((byte as f64) * 1000.0 / 255.0 as c_double).round() / 1000.0, 3)
+    }
+}
+
+// Color spaces and color math. Formulas and matrices follow the sample code
+// of CSS Color 4, Section 18, and Björn Ottosson's definition of OKLab.
+
+/** HSL, with the hue in degrees and everything else in [0, 1] */
+#[deriving(Eq, Clone)]
+pub struct Hsla {
+    hue: f64,
+    saturation: f64,
+    lightness: f64,
+    alpha: f64
+}
+
+/** HWB, with the hue in degrees and everything else in [0, 1] */
+#[deriving(Eq, Clone)]
+pub struct Hwba {
+    hue: f64,
+    whiteness: f64,
+    blackness: f64,
+    alpha: f64
+}
+
+/** sRGB with the transfer function removed, in [0, 1] when in gamut */
+#[deriving(Eq, Clone)]
+pub struct LinearRgba {
+    red: f64,
+    green: f64,
+    blue: f64,
+    alpha: f64
+}
+
+/** CIE Lab relative to D50, with lightness in [0, 100] */
+#[deriving(Eq, Clone)]
+pub struct Laba {
+    lightness: f64,
+    a: f64,
+    b: f64,
+    alpha: f64
+}
+
+/** The polar form of CIE Lab, with the hue in degrees */
+#[deriving(Eq, Clone)]
+pub struct Lcha {
+    lightness: f64,
+    chroma: f64,
+    hue: f64,
+    alpha: f64
+}
+
+/** OKLab, with lightness in [0, 1] */
+#[deriving(Eq, Clone)]
+pub struct Oklaba {
+    lightness: f64,
+    a: f64,
+    b: f64,
+    alpha: f64
+}
+
+/** The polar form of OKLab, with the hue in degrees */
+#[deriving(Eq, Clone)]
+pub struct Oklcha {
+    lightness: f64,
+    chroma: f64,
+    hue: f64,
+    alpha: f64
+}
+
+/** The spaces colors can be interpolated in */
+#[deriving(Eq, Clone)]
+pub enum ColorSpace {
+    SpaceSrgb,
+    SpaceLinearSrgb,
+    SpaceHsl,
+    SpaceHwb,
+    SpaceLab,
+    SpaceLch,
+    SpaceOklab,
+    SpaceOklch
+}
+
+impl Color {
+    pub fn to_hsl(&self) -> Hsla {
+        let (r, g, b) = self.unit_rgb();
+        let max = max3(r, g, b);
+        let min = min3(r, g, b);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        let saturation = if delta == 0.0 || lightness == 0.0 || lightness == 1.0 {
+            0.0
+        } else {
+            delta / (1.0 - (2.0 * lightness - 1.0).abs())
+        };
+        Hsla {
+            hue: rgb_hue(r, g, b),
+            saturation: saturation,
+            lightness: lightness,
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_hwb(&self) -> Hwba {
+        let (r, g, b) = self.unit_rgb();
+        Hwba {
+            hue: rgb_hue(r, g, b),
+            whiteness: min3(r, g, b),
+            blackness: 1.0 - max3(r, g, b),
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_linear(&self) -> LinearRgba {
+        let (r, g, b) = self.unit_rgb();
+        LinearRgba {
+            red: srgb_to_linear(r),
+            green: srgb_to_linear(g),
+            blue: srgb_to_linear(b),
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_lab(&self) -> Laba {
+        self.to_linear().to_lab()
+    }
+
+    pub fn to_lch(&self) -> Lcha {
+        self.to_lab().to_lch()
+    }
+
+    pub fn to_oklab(&self) -> Oklaba {
+        self.to_linear().to_oklab()
+    }
+
+    pub fn to_oklch(&self) -> Oklcha {
+        self.to_oklab().to_oklch()
+    }
+
+    /** Composites this color over `backdrop` with the source-over operator */
+    pub fn over(&self, backdrop: &Color) -> Color {
+        let alpha = self.alpha + backdrop.alpha * (1.0 - self.alpha);
+        if alpha == 0.0 {
+            return TRANSPARENT;
+        }
+        let blend = |source: u8, back: u8| {
+            (source as f64 * self.alpha + back as f64 * backdrop.alpha * (1.0 - self.alpha)) / alpha
+        };
+        rgba(channel_to_u8(blend(self.red, backdrop.red) / 255.0),
+             channel_to_u8(blend(self.green, backdrop.green) / 255.0),
+             channel_to_u8(blend(self.blue, backdrop.blue) / 255.0),
+             alpha)
+    }
+
+    /**
+    Interpolates between this color, at `progress` 0, and `other`, at 1, in
+    `space`. As in CSS Color 4, components are premultiplied by alpha, hues
+    take the shorter arc, and the hue of an achromatic color takes the other
+    color's hue.
+    */
+    pub fn interpolate(&self, other: &Color, progress: f64, space: ColorSpace) -> Color {
+        let mut from = to_components(self, space);
+        let mut to = to_components(other, space);
+        let hue = hue_index(space);
+
+        match hue {
+            Some(i) => {
+                let from_powerless = is_achromatic(from, space);
+                let to_powerless = is_achromatic(to, space);
+                if from_powerless && !to_powerless {
+                    from[i] = to[i];
+                } else if to_powerless && !from_powerless {
+                    to[i] = from[i];
+                }
+                let delta = to[i] - from[i];
+                if delta > 180.0 {
+                    from[i] += 360.0;
+                } else if delta < -180.0 {
+                    to[i] += 360.0;
+                }
+            }
+            None => ()
+        }
+
+        let alpha = from[3] + (to[3] - from[3]) * progress;
+        let mut result = [0.0, 0.0, 0.0, alpha];
+        for i in range(0u, 3) {
+            if Some(i) == hue {
+                result[i] = normalize_hue(from[i] + (to[i] - from[i]) * progress);
+            } else {
+                let from_premultiplied = from[i] * from[3];
+                let to_premultiplied = to[i] * to[3];
+                let value = from_premultiplied + (to_premultiplied - from_premultiplied) * progress;
+                result[i] = if alpha == 0.0 { value } else { value / alpha };
+            }
+        }
+        from_components(result, space)
+    }
+
+    /** The relative luminance of WCAG 2.x, ignoring alpha */
+    pub fn relative_luminance(&self) -> f64 {
+        let linear = self.to_linear();
+        0.2126 * linear.red + 0.7152 * linear.green + 0.0722 * linear.blue
+    }
+
+    /** The WCAG 2.x contrast ratio between two opaque colors, in [1, 21] */
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+        if a > b { (a + 0.05) / (b + 0.05) } else { (b + 0.05) / (a + 0.05) }
+    }
+
+    fn unit_rgb(&self) -> (f64, f64, f64) {
+        (self.red as f64 / 255.0, self.green as f64 / 255.0, self.blue as f64 / 255.0)
+    }
+}
+
+/** Builds a color from sRGB channels in [0, 1], clipping anything out of gamut */
+pub fn unit_rgba(r : f64, g : f64, b : f64, a : f64) -> Color {
+    rgba(channel_to_u8(r), channel_to_u8(g), channel_to_u8(b), clamp_unit(a))
+}
+
+impl Hsla {
+    pub fn to_color(&self) -> Color {
+        hsla(normalize_hue(self.hue), clamp_unit(self.saturation), clamp_unit(self.lightness),
+             clamp_unit(self.alpha))
+    }
+}
+
+impl Hwba {
+    pub fn to_color(&self) -> Color {
+        let whiteness = clamp_unit(self.whiteness);
+        let blackness = clamp_unit(self.blackness);
+        if whiteness + blackness >= 1.0 {
+            let gray = whiteness / (whiteness + blackness);
+            return unit_rgba(gray, gray, gray, self.alpha);
+        }
+        let base = hsl(normalize_hue(self.hue), 1.0, 0.5);
+        let (r, g, b) = base.unit_rgb();
+        let scale = 1.0 - whiteness - blackness;
+        unit_rgba(r * scale + whiteness, g * scale + whiteness, b * scale + whiteness, self.alpha)
+    }
+}
+
+impl LinearRgba {
+    pub fn to_color(&self) -> Color {
+        unit_rgba(linear_to_srgb(self.red), linear_to_srgb(self.green), linear_to_srgb(self.blue),
+                  self.alpha)
+    }
+
+    pub fn to_lab(&self) -> Laba {
+        let xyz = multiply(SRGB_TO_XYZ_D65, [self.red, self.green, self.blue]);
+        let xyz = multiply(D65_TO_D50, xyz);
+        let f = |i: uint| {
+            let value = xyz[i] / D50_WHITE[i];
+            if value > LAB_EPSILON { value.cbrt() } else { (LAB_KAPPA * value + 16.0) / 116.0 }
+        };
+        let (fx, fy, fz) = (f(0), f(1), f(2));
+        Laba {
+            lightness: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_oklab(&self) -> Oklaba {
+        let lms = multiply(LINEAR_SRGB_TO_LMS, [self.red, self.green, self.blue]);
+        let lab = multiply(LMS_TO_OKLAB, [lms[0].cbrt(), lms[1].cbrt(), lms[2].cbrt()]);
+        Oklaba {
+            lightness: lab[0],
+            a: lab[1],
+            b: lab[2],
+            alpha: self.alpha
+        }
+    }
+}
+
+impl Laba {
+    pub fn to_linear(&self) -> LinearRgba {
+        let fy = (self.lightness + 16.0) / 116.0;
+        let fx = self.a / 500.0 + fy;
+        let fz = fy - self.b / 200.0;
+        let inverse = |f: f64| {
+            let cubed = f * f * f;
+            if cubed > LAB_EPSILON { cubed } else { (116.0 * f - 16.0) / LAB_KAPPA }
+        };
+        let y = if self.lightness > LAB_KAPPA * LAB_EPSILON {
+            fy * fy * fy
+        } else {
+            self.lightness / LAB_KAPPA
+        };
+        let xyz = [inverse(fx) * D50_WHITE[0], y * D50_WHITE[1], inverse(fz) * D50_WHITE[2]];
+        let rgb = multiply(XYZ_D65_TO_SRGB, multiply(D50_TO_D65, xyz));
+        LinearRgba {
+            red: rgb[0],
+            green: rgb[1],
+            blue: rgb[2],
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_color(&self) -> Color {
+        self.to_linear().to_color()
+    }
+
+    pub fn to_lch(&self) -> Lcha {
+        let (chroma, hue) = to_polar(self.a, self.b);
+        Lcha {
+            lightness: self.lightness,
+            chroma: chroma,
+            hue: hue,
+            alpha: self.alpha
+        }
+    }
+}
+
+impl Lcha {
+    pub fn to_lab(&self) -> Laba {
+        let (a, b) = from_polar(self.chroma, self.hue);
+        Laba {
+            lightness: self.lightness,
+            a: a,
+            b: b,
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_color(&self) -> Color {
+        self.to_lab().to_color()
+    }
+}
+
+impl Oklaba {
+    pub fn to_linear(&self) -> LinearRgba {
+        let lms = multiply(OKLAB_TO_LMS, [self.lightness, self.a, self.b]);
+        let rgb = multiply(LMS_TO_LINEAR_SRGB, [lms[0] * lms[0] * lms[0],
+                                                lms[1] * lms[1] * lms[1],
+                                                lms[2] * lms[2] * lms[2]]);
+        LinearRgba {
+            red: rgb[0],
+            green: rgb[1],
+            blue: rgb[2],
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_color(&self) -> Color {
+        self.to_linear().to_color()
+    }
+
+    pub fn to_oklch(&self) -> Oklcha {
+        let (chroma, hue) = to_polar(self.a, self.b);
+        Oklcha {
+            lightness: self.lightness,
+            chroma: chroma,
+            hue: hue,
+            alpha: self.alpha
+        }
+    }
+}
+
+impl Oklcha {
+    pub fn to_oklab(&self) -> Oklaba {
+        let (a, b) = from_polar(self.chroma, self.hue);
+        Oklaba {
+            lightness: self.lightness,
+            a: a,
+            b: b,
+            alpha: self.alpha
+        }
+    }
+
+    pub fn to_color(&self) -> Color {
+        self.to_oklab().to_color()
+    }
+}
+
+type Matrix = [[f64, ..3], ..3];
+
+static SRGB_TO_XYZ_D65: Matrix = [
+    [0.41239079926595934, 0.357584339383878, 0.1804807884018343],
+    [0.21263900587151027, 0.715168678767756, 0.07219231536073371],
+    [0.01933081871559182, 0.11919477979462598, 0.9505321522496607],
+];
+
+static XYZ_D65_TO_SRGB: Matrix = [
+    [3.2409699419045226, -1.537383177570094, -0.4986107602930034],
+    [-0.9692436362808796, 1.8759675015077202, 0.04155505740717559],
+    [0.05563007969699366, -0.20397695888897652, 1.0569715142428786],
+];
+
+// Bradford chromatic adaptation
+static D65_TO_D50: Matrix = [
+    [1.0479298208405488, 0.022946793341019088, -0.05019222954313557],
+    [0.029627815688159344, 0.990434484573249, -0.01707382502938514],
+    [-0.009243058152591178, 0.015055144896577895, 0.7521316354461029],
+];
+
+static D50_TO_D65: Matrix = [
+    [0.9554734527042182, -0.023098536874261423, 0.0632593086610217],
+    [-0.028369706963208136, 1.0099954580058226, 0.021041398966943008],
+    [0.012314001688319899, -0.020507696433477912, 1.3303659366080753],
+];
+
+static D50_WHITE: [f64, ..3] = [0.9642956764295677, 1.0, 0.8251046025104602];
+
+static LAB_EPSILON: f64 = 216.0 / 24389.0;
+static LAB_KAPPA: f64 = 24389.0 / 27.0;
+
+static LINEAR_SRGB_TO_LMS: Matrix = [
+    [0.4122214708, 0.5363325363, 0.0514459929],
+    [0.2119034982, 0.6806995451, 0.1073969566],
+    [0.0883024619, 0.2817188376, 0.6299787005],
+];
+
+static LMS_TO_OKLAB: Matrix = [
+    [0.2104542553, 0.7936177850, -0.0040720468],
+    [1.9779984951, -2.4285922050, 0.4505937099],
+    [0.0259040371, 0.7827717662, -0.8086757660],
+];
+
+static OKLAB_TO_LMS: Matrix = [
+    [1.0, 0.3963377774, 0.2158037573],
+    [1.0, -0.1055613458, -0.0638541728],
+    [1.0, -0.0894841775, -1.2914855480],
+];
+
+static LMS_TO_LINEAR_SRGB: Matrix = [
+    [4.0767416621, -3.3077115913, 0.2309699292],
+    [-1.2684380046, 2.6097574011, -0.3413193965],
+    [-0.0041960863, -0.7034186147, 1.7076147010],
+];
+
+fn multiply(m: Matrix, v: [f64, ..3]) -> [f64, ..3] {
+    [m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+     m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+     m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2]]
+}
+
+// The sRGB transfer function, extended to negative values for out of gamut colors
+fn srgb_to_linear(c : f64) -> f64 {
+    let abs = c.abs();
+    if abs <= 0.04045 {
+        c / 12.92
+    } else {
+        c.signum() * ((abs + 0.055) / 1.055).pow(&2.4)
+    }
+}
+
+fn linear_to_srgb(c : f64) -> f64 {
+    let abs = c.abs();
+    if abs <= 0.0031308 {
+        c * 12.92
+    } else {
+        c.signum() * (1.055 * abs.pow(&(1.0 / 2.4)) - 0.055)
+    }
+}
+
+fn to_polar(a : f64, b : f64) -> (f64, f64) {
+    ((a * a + b * b).sqrt(), normalize_hue(b.atan2(&a).to_degrees()))
+}
+
+fn from_polar(chroma : f64, hue : f64) -> (f64, f64) {
+    let radians = hue.to_radians();
+    (chroma * radians.cos(), chroma * radians.sin())
+}
+
+/** The hue shared by HSL and HWB, in degrees */
+fn rgb_hue(r : f64, g : f64, b : f64) -> f64 {
+    let max = max3(r, g, b);
+    let delta = max - min3(r, g, b);
+    if delta == 0.0 {
+        0.0
+    } else if max == r {
+        normalize_hue(60.0 * ((g - b) / delta))
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    }
+}
+
+fn normalize_hue(hue : f64) -> f64 {
+    let hue = hue % 360.0;
+    if hue < 0.0 { hue + 360.0 } else { hue }
+}
+
+fn to_components(color : &Color, space : ColorSpace) -> [f64, ..4] {
+    match space {
+        SpaceSrgb => {
+            let (r, g, b) = color.unit_rgb();
+            [r, g, b, color.alpha]
+        }
+        SpaceLinearSrgb => {
+            let c = color.to_linear();
+            [c.red, c.green, c.blue, c.alpha]
+        }
+        SpaceHsl => {
+            let c = color.to_hsl();
+            [c.hue, c.saturation, c.lightness, c.alpha]
+        }
+        SpaceHwb => {
+            let c = color.to_hwb();
+            [c.hue, c.whiteness, c.blackness, c.alpha]
+        }
+        SpaceLab => {
+            let c = color.to_lab();
+            [c.lightness, c.a, c.b, c.alpha]
+        }
+        SpaceLch => {
+            let c = color.to_lch();
+            [c.lightness, c.chroma, c.hue, c.alpha]
+        }
+        SpaceOklab => {
+            let c = color.to_oklab();
+            [c.lightness, c.a, c.b, c.alpha]
+        }
+        SpaceOklch => {
+            let c = color.to_oklch();
+            [c.lightness, c.chroma, c.hue, c.alpha]
+        }
+    }
+}
+
+fn from_components(c : [f64, ..4], space : ColorSpace) -> Color {
+    match space {
+        SpaceSrgb => unit_rgba(c[0], c[1], c[2], c[3]),
+        SpaceLinearSrgb => LinearRgba { red: c[0], green: c[1], blue: c[2], alpha: c[3] }.to_color(),
+        SpaceHsl => Hsla { hue: c[0], saturation: c[1], lightness: c[2], alpha: c[3] }.to_color(),
+        SpaceHwb => Hwba { hue: c[0], whiteness: c[1], blackness: c[2], alpha: c[3] }.to_color(),
+        SpaceLab => Laba { lightness: c[0], a: c[1], b: c[2], alpha: c[3] }.to_color(),
+        SpaceLch => Lcha { lightness: c[0], chroma: c[1], hue: c[2], alpha: c[3] }.to_color(),
+        SpaceOklab => Oklaba { lightness: c[0], a: c[1], b: c[2], alpha: c[3] }.to_color(),
+        SpaceOklch => Oklcha { lightness: c[0], chroma: c[1], hue: c[2], alpha: c[3] }.to_color()
+    }
+}
+
+fn hue_index(space : ColorSpace) -> Option<uint> {
+    match space {
+        SpaceHsl | SpaceHwb => Some(0),
+        SpaceLch | SpaceOklch => Some(2),
+        _ => None
+    }
+}
+
+/** Whether the hue of a color is powerless, so that it shouldn't be interpolated */
+fn is_achromatic(c : [f64, ..4], space : ColorSpace) -> bool {
+    match space {
+        SpaceHsl => c[1] == 0.0,
+        SpaceHwb => c[1] + c[2] >= 1.0,
+        // Conversions leave a little noise in the chroma of grays
+        SpaceLch => c[1] < 0.0015,
+        SpaceOklch => c[1] < 0.000004,
+        _ => false
+    }
+}
+
+fn channel_to_u8(value : f64) -> u8 {
+    (255.0 * clamp_unit(value) as c_double).round() as u8
+}
+
+fn clamp_unit(value : f64) -> f64 {
+    if value < 0.0 { 0.0 } else if value > 1.0 { 1.0 } else { value }
+}
+
+fn max3(a : f64, b : f64, c : f64) -> f64 {
+    let ab = if a > b { a } else { b };
+    if ab > c { ab } else { c }
+}
+
+fn min3(a : f64, b : f64, c : f64) -> f64 {
+    let ab = if a < b { a } else { b };
+    if ab < c { ab } else { c }
+}
+
+/**
+A color as it was specified, in the space of the function that produced it.
+Colors in spaces wider than sRGB are gamut mapped when converted to `Color`.
+*/
+#[deriving(Eq, Clone)]
+pub enum SpecifiedColor {
+    SpecifiedRgb(Color),
+    SpecifiedHwb(Hwba),
+    SpecifiedLab(Laba),
+    SpecifiedLch(Lcha),
+    SpecifiedOklab(Oklaba),
+    SpecifiedOklch(Oklcha)
+}
+
+impl SpecifiedColor {
+    pub fn to_color(&self) -> Color {
+        match *self {
+            SpecifiedRgb(color) => color,
+            SpecifiedHwb(ref hwb) => hwb.to_color(),
+            SpecifiedLab(ref lab) => gamut_map(&lab.to_linear().to_oklab().to_oklch()),
+            SpecifiedLch(ref lch) => gamut_map(&lch.to_lab().to_linear().to_oklab().to_oklch()),
+            SpecifiedOklab(ref oklab) => gamut_map(&oklab.to_oklch()),
+            SpecifiedOklch(ref oklch) => gamut_map(oklch)
+        }
+    }
+
+    /**
+    Serializes in the space the color was specified in, as CSS Color 4 does.
+    HWB colors, like HSL ones, serialize as `rgb()`.
+    */
+    pub fn to_css_string(&self) -> ~str {
+        match *self {
+            SpecifiedRgb(color) => color.to_css_string(),
+            SpecifiedHwb(ref hwb) => hwb.to_color().to_css_string(),
+            SpecifiedLab(ref c) => serialize_function("lab", c.lightness, c.a, c.b, c.alpha),
+            SpecifiedLch(ref c) => serialize_function("lch", c.lightness, c.chroma, c.hue, c.alpha),
+            SpecifiedOklab(ref c) => serialize_function("oklab", c.lightness, c.a, c.b, c.alpha),
+            SpecifiedOklch(ref c) => {
+                serialize_function("oklch", c.lightness, c.chroma, c.hue, c.alpha)
+            }
+        }
+    }
+}
+
+fn serialize_function(name : &str, x : f64, y : f64, z : f64, alpha : f64) -> ~str {
+    let mut result = fmt!("%s(%s %s %s", name, serialize_number(x), serialize_number(y),
+                          serialize_number(z));
+    if alpha != 1.0 {
+        result.push_str(" / ");
+        result.push_str(serialize_alpha(alpha));
+    }
+    result.push_str(")");
+    result
+}
+
+// The just noticeable difference and search precision of the CSS Color 4
+// gamut mapping algorithm
+static GAMUT_JND: f64 = 0.02;
+static GAMUT_EPSILON: f64 = 0.0001;
+
+/**
+Maps a color into the sRGB gamut by reducing its OKLCH chroma until clipping
+it makes no noticeable difference, as in CSS Color 4, Section 13.2.
+*/
+pub fn gamut_map(origin : &Oklcha) -> Color {
+    if origin.lightness >= 1.0 {
+        return rgba(255, 255, 255, clamp_unit(origin.alpha));
+    }
+    if origin.lightness <= 0.0 {
+        return rgba(0, 0, 0, clamp_unit(origin.alpha));
+    }
+
+    let linear = origin.to_oklab().to_linear();
+    if in_gamut(&linear) {
+        return linear.to_color();
+    }
+    if delta_eok(&clip(&linear), &linear) < GAMUT_JND {
+        return linear.to_color();
+    }
+
+    let mut current = origin.clone();
+    let mut min = 0.0;
+    let mut max = origin.chroma;
+    let mut min_in_gamut = true;
+    while max - min > GAMUT_EPSILON {
+        current.chroma = (min + max) / 2.0;
+        let linear = current.to_oklab().to_linear();
+        if min_in_gamut && in_gamut(&linear) {
+            min = current.chroma;
+        } else {
+            let delta = delta_eok(&clip(&linear), &linear);
+            if delta < GAMUT_JND {
+                if GAMUT_JND - delta < GAMUT_EPSILON {
+                    return linear.to_color();
+                }
+                min_in_gamut = false;
+                min = current.chroma;
+            } else {
+                max = current.chroma;
+            }
+        }
+    }
+    current.to_oklab().to_linear().to_color()
+}
+
+fn in_gamut(color : &LinearRgba) -> bool {
+    let within = |c: f64| -0.000001 <= c && c <= 1.000001;
+    within(color.red) && within(color.green) && within(color.blue)
+}
+
+fn clip(color : &LinearRgba) -> LinearRgba {
+    LinearRgba {
+        red: clamp_unit(color.red),
+        green: clamp_unit(color.green),
+        blue: clamp_unit(color.blue),
+        alpha: color.alpha
+    }
+}
+
+fn delta_eok(a : &LinearRgba, b : &LinearRgba) -> f64 {
+    let a = a.to_oklab();
+    let b = b.to_oklab();
+    let (dl, da, db) = (a.lightness - b.lightness, a.a - b.a, a.b - b.b);
+    (dl * dl + da * da + db * db).sqrt()
+}
+
+/**
+The CSS 2.1 system colors (Section 18.2). Their values come from the user's
+environment, so they're looked up in a `SystemColorPalette`.
+*/
+#[deriving(Eq, Clone)]
+pub enum SystemColor {
+    SystemColorActiveBorder,
+    SystemColorActiveCaption,
+    SystemColorAppWorkspace,
+    SystemColorBackground,
+    SystemColorButtonFace,
+    SystemColorButtonHighlight,
+    SystemColorButtonShadow,
+    SystemColorButtonText,
+    SystemColorCaptionText,
+    SystemColorGrayText,
+    SystemColorHighlight,
+    SystemColorHighlightText,
+    SystemColorInactiveBorder,
+    SystemColorInactiveCaption,
+    SystemColorInactiveCaptionText,
+    SystemColorInfoBackground,
+    SystemColorInfoText,
+    SystemColorMenu,
+    SystemColorMenuText,
+    SystemColorScrollbar,
+    SystemColorThreeDDarkShadow,
+    SystemColorThreeDFace,
+    SystemColorThreeDHighlight,
+    SystemColorThreeDLightShadow,
+    SystemColorThreeDShadow,
+    SystemColorWindow,
+    SystemColorWindowFrame,
+    SystemColorWindowText,
+}
+
+static SYSTEM_COLOR_NAMES: [(&'static str, SystemColor), ..28] = [
+    ("activeborder", SystemColorActiveBorder),
+    ("activecaption", SystemColorActiveCaption),
+    ("appworkspace", SystemColorAppWorkspace),
+    ("background", SystemColorBackground),
+    ("buttonface", SystemColorButtonFace),
+    ("buttonhighlight", SystemColorButtonHighlight),
+    ("buttonshadow", SystemColorButtonShadow),
+    ("buttontext", SystemColorButtonText),
+    ("captiontext", SystemColorCaptionText),
+    ("graytext", SystemColorGrayText),
+    ("highlight", SystemColorHighlight),
+    ("highlighttext", SystemColorHighlightText),
+    ("inactiveborder", SystemColorInactiveBorder),
+    ("inactivecaption", SystemColorInactiveCaption),
+    ("inactivecaptiontext", SystemColorInactiveCaptionText),
+    ("infobackground", SystemColorInfoBackground),
+    ("infotext", SystemColorInfoText),
+    ("menu", SystemColorMenu),
+    ("menutext", SystemColorMenuText),
+    ("scrollbar", SystemColorScrollbar),
+    ("threeddarkshadow", SystemColorThreeDDarkShadow),
+    ("threedface", SystemColorThreeDFace),
+    ("threedhighlight", SystemColorThreeDHighlight),
+    ("threedlightshadow", SystemColorThreeDLightShadow),
+    ("threedshadow", SystemColorThreeDShadow),
+    ("window", SystemColorWindow),
+    ("windowframe", SystemColorWindowFrame),
+    ("windowtext", SystemColorWindowText),
+];
+
+/** Looks up a system color by its case-insensitive name */
+pub fn parse_system_color(name : &str) -> Option<SystemColor> {
+    let name = name.trim();
+    for &(system_name, color) in SYSTEM_COLOR_NAMES.iter() {
+        if name.eq_ignore_ascii_case(system_name) {
+            return Some(color);
+        }
+    }
+    None
+}
+
+/** The values of the system colors, indexed by `SystemColor` */
+pub struct SystemColorPalette {
+    colors: [Color, ..28]
+}
+
+impl SystemColorPalette {
+    /** A light palette in the style of classic desktop themes */
+    pub fn default() -> SystemColorPalette {
+        SystemColorPalette {
+            colors: [
+                rgb(212, 208, 200), // ActiveBorder
+                rgb(10, 36, 106),   // ActiveCaption
+                rgb(128, 128, 128), // AppWorkspace
+                rgb(58, 110, 165),  // Background
+                rgb(212, 208, 200), // ButtonFace
+                rgb(255, 255, 255), // ButtonHighlight
+                rgb(128, 128, 128), // ButtonShadow
+                rgb(0, 0, 0),       // ButtonText
+                rgb(255, 255, 255), // CaptionText
+                rgb(128, 128, 128), // GrayText
+                rgb(10, 36, 106),   // Highlight
+                rgb(255, 255, 255), // HighlightText
+                rgb(212, 208, 200), // InactiveBorder
+                rgb(128, 128, 128), // InactiveCaption
+                rgb(212, 208, 200), // InactiveCaptionText
+                rgb(255, 255, 225), // InfoBackground
+                rgb(0, 0, 0),       // InfoText
+                rgb(212, 208, 200), // Menu
+                rgb(0, 0, 0),       // MenuText
+                rgb(212, 208, 200), // Scrollbar
+                rgb(64, 64, 64),    // ThreeDDarkShadow
+                rgb(212, 208, 200), // ThreeDFace
+                rgb(255, 255, 255), // ThreeDHighlight
+                rgb(212, 208, 200), // ThreeDLightShadow
+                rgb(128, 128, 128), // ThreeDShadow
+                rgb(255, 255, 255), // Window
+                rgb(0, 0, 0),       // WindowFrame
+                rgb(0, 0, 0),       // WindowText
+            ]
+        }
+    }
+
+    pub fn get(&self, color : SystemColor) -> Color {
+        self.colors[color as uint]
+    }
+
+    pub fn set(&mut self, color : SystemColor, value : Color) {
+        self.colors[color as uint] = value;
+    }
+}
+
+impl Clone for SystemColorPalette {
+    fn clone(&self) -> SystemColorPalette {
+        SystemColorPalette { colors: self.colors }
+    }
+}
+
+impl Eq for SystemColorPalette {
+    fn eq(&self, other : &SystemColorPalette) -> bool {
+        self.colors.iter().zip(other.colors.iter()).all(|(a, b)| a == b)
     }
 }
 
 pub mod parsing {
+    use std::ascii::StrAsciiExt;
+    use std::char;
+    use std::libc::types::os::arch::c95::c_double;
     use super::*;
-    use super::{Color, rgb, rgba, hsl, hsla};
+    use super::{Color, SystemColorPalette, parse_system_color, rgba, hsla};
+    use super::{SpecifiedColor, SpecifiedRgb, SpecifiedHwb, SpecifiedLab, SpecifiedLch};
+    use super::{SpecifiedOklab, SpecifiedOklch, Hwba, Laba, Lcha, Oklaba, Oklcha};
+    use super::{ColorSpace, SpaceSrgb, SpaceLinearSrgb, SpaceHsl, SpaceHwb};
+    use super::{SpaceLab, SpaceLch, SpaceOklab, SpaceOklch};
+    use units::parsing::parse_angle;
+
+    /** A numeric argument of a color function */
+    enum Component {
+        Number(f64),
+        Percentage(f64),
+        // An angle, in degrees
+        Angle(f64)
+    }
 
-    /** Parses a color specification in the form rgb(foo,bar,baz) */
-    fn parse_rgb(color : &str) -> Option<Color> {
-        // Shave off the rgb( and the )
-        let only_colors = color.slice(4u, color.len() - 1);
+    /** The arguments of a color function, split up but not yet interpreted */
+    struct Arguments {
+        values: ~[Component],
+        // Opaque unless given
+        alpha: f64,
+        // Whether the CSS Color 3 comma-separated syntax was used
+        legacy: bool
+    }
 
-        // split up r, g, and b
-        let mut cols = ~[];
-        for s in only_colors.split_iter(',') {
-            cols.push(s.trim());
+    fn parse_component(value : &str) -> Option<Component> {
+        let value = value.trim();
+        match parse_angle(value) {
+            Some(angle) => return Some(Angle(angle.to_degrees())),
+            None => ()
+        }
+        if value.ends_with("%") {
+            let number: Option<f64> = FromStr::from_str(value.slice_to(value.len() - 1));
+            match number { Some(n) => Some(Percentage(n)), None => None }
+        } else {
+            let number: Option<f64> = FromStr::from_str(value);
+            match number { Some(n) => Some(Number(n)), None => None }
+        }
+    }
+
+    /**
+    Splits the arguments of a color function with three channels. Both the
+    CSS Color 3 syntax `f(a, b, c[, alpha])` and the CSS Color 4 syntax
+    `f(a b c[ / alpha])` are accepted.
+    */
+    fn parse_arguments(args : &str) -> Option<Arguments> {
+        let (values, alpha, legacy) = if args.contains_char(',') {
+            if args.contains_char('/') { return None; }
+            let mut parts = ~[];
+            for s in args.split_iter(',') {
+                parts.push(s.trim());
+            }
+            match parts.len() {
+                3u => (parts, None, true),
+                4u => (parts.slice_to(3).to_owned(), Some(parts[3]), true),
+                _ => return None
+            }
+        } else {
+            let (channels, alpha) = match args.find('/') {
+                Some(i) => (args.slice_to(i), Some(args.slice_from(i + 1).trim())),
+                None => (args, None)
+            };
+            let parts: ~[&str] = channels.word_iter().collect();
+            if parts.len() != 3u { return None; }
+            (parts, alpha, false)
         };
 
-        if cols.len() != 3u { return fail_unrecognized(color); }
+        let mut components = ~[];
+        for value in values.iter() {
+            match parse_component(*value) {
+                Some(c) => components.push(c),
+                None => return None
+            }
+        }
+        let alpha = match alpha {
+            None => 1.0,
+            Some(a) => match parse_alpha(a) {
+                Some(a) => a,
+                None => return None
+            }
+        };
+
+        Some(Arguments { values: components, alpha: alpha, legacy: legacy })
+    }
+
+    fn clamp(value : f64, min : f64, max : f64) -> f64 {
+        if value < min { min } else if value > max { max } else { value }
+    }
 
-        match (FromStr::from_str(cols[0]), FromStr::from_str(cols[1]), 
-               FromStr::from_str(cols[2])) {
-          (Some(r), Some(g), Some(b)) => { Some(rgb(r, g, b)) }
-          _ => { fail_unrecognized(color) }
+    /** Alpha is a number in [0, 1] or a percentage, clamped to range, but not an angle */
+    fn parse_alpha(value : &str) -> Option<f64> {
+        match parse_component(value) {
+            Some(Number(a)) => Some(clamp(a, 0.0, 1.0)),
+            Some(Percentage(a)) => Some(clamp(a / 100.0, 0.0, 1.0)),
+            Some(Angle(*)) | None => None
         }
     }
 
-    /** Parses a color specification in the form rgba(foo,bar,baz,qux) */
-    fn parse_rgba(color : &str) -> Option<Color> {
-        // Shave off the rgba( and the )
-        let only_vals = color.slice(5u, color.len() - 1);
+    /**
+    Parses the arguments of rgb() or rgba(), which are aliases of each other.
+    Channels are integers in [0, 255] or percentages, clamped to range. The
+    legacy syntax doesn't allow mixing the two.
+    */
+    fn parse_rgb(color : &str, args : &str) -> Option<Color> {
+        let args = match parse_arguments(args) {
+            Some(args) => args,
+            None => return fail_unrecognized(color)
+        };
+
+        let mut channels = ~[];
+        for value in args.values.iter() {
+            let channel = match *value {
+                Number(n) => clamp(n, 0.0, 255.0),
+                Percentage(p) => clamp(p, 0.0, 100.0) * 255.0 / 100.0,
+                Angle(*) => return fail_unrecognized(color)
+            };
+            channels.push((channel as c_double).round() as u8);
+        }
+        if args.legacy {
+            let mut percentages = 0;
+            for value in args.values.iter() {
+                match *value { Percentage(*) => percentages += 1, _ => () }
+            }
+            if percentages != 0 && percentages != 3 { return fail_unrecognized(color); }
+        }
+
+        Some(rgba(channels[0], channels[1], channels[2], args.alpha))
+    }
+
+    /**
+    Parses the arguments of hsl() or hsla(), which are aliases of each other.
+    The hue is an angle, in degrees when unitless, and taken modulo 360;
+    saturation and lightness are percentages, clamped to range.
+
+    For compatibility with earlier versions of this module, the legacy syntax
+    also accepts saturation and lightness as plain fractions in [0, 1]. The
+    CSS Color 4 syntax reads plain numbers as percentages, as the spec says.
+    */
+    fn parse_hsl(color : &str, args : &str) -> Option<Color> {
+        let args = match parse_arguments(args) {
+            Some(args) => args,
+            None => return fail_unrecognized(color)
+        };
+
+        let hue = match args.values[0] {
+            Number(h) | Angle(h) => h,
+            Percentage(*) => return fail_unrecognized(color)
+        };
+        let mut fractions = ~[];
+        for value in args.values.slice_from(1).iter() {
+            let fraction = match *value {
+                Percentage(p) => clamp(p / 100.0, 0.0, 1.0),
+                Number(n) if !args.legacy => clamp(n / 100.0, 0.0, 1.0),
+                Number(n) if 0.0 <= n && n <= 1.0 => n,
+                Number(*) | Angle(*) => return fail_unrecognized(color)
+            };
+            fractions.push(fraction);
+        }
+
+        Some(hsla(hue, fractions[0], fractions[1], args.alpha))
+    }
 
-        // split up r, g, and b
-        let mut cols = ~[];
-        for s in only_vals.split_iter(',') {
-            cols.push(s);
+    /**
+    Reads the channels of a CSS Color 4 function that has no legacy syntax.
+    Percentages are scaled so that 100% is the matching entry of `ranges`.
+    Hue channels, given by `hue`, are angles, in degrees when unitless.
+    */
+    fn parse_modern_channels(args : &str, ranges : [f64, ..3], hue : Option<uint>)
+                             -> Option<([f64, ..3], f64)> {
+        let args = match parse_arguments(args) {
+            Some(args) => args,
+            None => return None
         };
+        if args.legacy { return None; }
 
-        if cols.len() != 4u { return fail_unrecognized(color); }
+        let mut channels = [0.0, 0.0, 0.0];
+        for i in range(0u, 3) {
+            let is_hue = Some(i) == hue;
+            channels[i] = match args.values[i] {
+                Number(n) => n,
+                Percentage(p) if !is_hue => p * ranges[i] / 100.0,
+                Angle(a) if is_hue => a,
+                Percentage(*) | Angle(*) => return None
+            };
+        }
+        Some((channels, args.alpha))
+    }
 
-        match (FromStr::from_str(cols[0]), FromStr::from_str(cols[1]), 
-               FromStr::from_str(cols[2]), FromStr::from_str(cols[3])) {
-          (Some(r), Some(g), Some(b), Some(a)) => { Some(rgba(r, g, b, a)) }
-          _ => { fail_unrecognized(color) }
+    /** Parses the arguments of hwb(); whiteness and blackness are percentages */
+    fn parse_hwb(args : &str) -> Option<SpecifiedColor> {
+        do parse_modern_channels(args, [0.0, 100.0, 100.0], Some(0)).map |&(c, alpha)| {
+            SpecifiedHwb(Hwba {
+                hue: c[0],
+                whiteness: clamp(c[1], 0.0, 100.0) / 100.0,
+                blackness: clamp(c[2], 0.0, 100.0) / 100.0,
+                alpha: alpha
+            })
         }
     }
 
-    /** Parses a color specification in the form hsl(foo,bar,baz) */
-    fn parse_hsl(color : &str) -> Option<Color> {
-        // Shave off the hsl( and the )
-        let only_vals = color.slice(4u, color.len() - 1);
+    /** Parses the arguments of lab(); 100% is a lightness of 100 and a or b of 125 */
+    fn parse_lab(args : &str) -> Option<SpecifiedColor> {
+        do parse_modern_channels(args, [100.0, 125.0, 125.0], None).map |&(c, alpha)| {
+            SpecifiedLab(Laba {
+                lightness: clamp(c[0], 0.0, 100.0),
+                a: c[1],
+                b: c[2],
+                alpha: alpha
+            })
+        }
+    }
 
-        // split up h, s, and l
-        let mut vals = ~[];
-        for s in only_vals.split_iter(',') {
-            vals.push(s);
+    /** Parses the arguments of lch(); 100% is a lightness of 100 and a chroma of 150 */
+    fn parse_lch(args : &str) -> Option<SpecifiedColor> {
+        do parse_modern_channels(args, [100.0, 150.0, 0.0], Some(2)).map |&(c, alpha)| {
+            SpecifiedLch(Lcha {
+                lightness: clamp(c[0], 0.0, 100.0),
+                chroma: if c[1] < 0.0 { 0.0 } else { c[1] },
+                hue: c[2],
+                alpha: alpha
+            })
+        }
+    }
+
+    /** Parses the arguments of oklab(); 100% is a lightness of 1 and a or b of 0.4 */
+    fn parse_oklab(args : &str) -> Option<SpecifiedColor> {
+        do parse_modern_channels(args, [1.0, 0.4, 0.4], None).map |&(c, alpha)| {
+            SpecifiedOklab(Oklaba {
+                lightness: clamp(c[0], 0.0, 1.0),
+                a: c[1],
+                b: c[2],
+                alpha: alpha
+            })
+        }
+    }
+
+    /** Parses the arguments of oklch(); 100% is a lightness of 1 and a chroma of 0.4 */
+    fn parse_oklch(args : &str) -> Option<SpecifiedColor> {
+        do parse_modern_channels(args, [1.0, 0.4, 0.0], Some(2)).map |&(c, alpha)| {
+            SpecifiedOklch(Oklcha {
+                lightness: clamp(c[0], 0.0, 1.0),
+                chroma: if c[1] < 0.0 { 0.0 } else { c[1] },
+                hue: c[2],
+                alpha: alpha
+            })
+        }
+    }
+
+    /** Splits on the commas that aren't nested inside parentheses */
+    fn split_top_level<'a>(args : &'a str) -> ~[&'a str] {
+        let mut parts = ~[];
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in args.char_offset_iter() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(args.slice(start, i).trim());
+                    start = i + 1;
+                }
+                _ => ()
+            }
+        }
+        parts.push(args.slice_from(start).trim());
+        parts
+    }
+
+    fn parse_color_space(name : &str) -> Option<ColorSpace> {
+        match name.to_ascii_lower().as_slice() {
+            "srgb" => Some(SpaceSrgb),
+            "srgb-linear" => Some(SpaceLinearSrgb),
+            "hsl" => Some(SpaceHsl),
+            "hwb" => Some(SpaceHwb),
+            "lab" => Some(SpaceLab),
+            "lch" => Some(SpaceLch),
+            "oklab" => Some(SpaceOklab),
+            "oklch" => Some(SpaceOklch),
+            _ => None
+        }
+    }
+
+    /** Parses a color-mix() argument, a color with an optional leading or trailing percentage */
+    fn parse_mix_color(arg : &str, palette : &SystemColorPalette) -> Option<(Color, Option<f64>)> {
+        let (color, percentage) = if arg.ends_with("%") {
+            match arg.rfind(char::is_whitespace) {
+                Some(i) => (arg.slice_to(i), Some(arg.slice_from(i + 1))),
+                None => return None
+            }
+        } else {
+            match arg.find(char::is_whitespace) {
+                Some(i) if arg.slice_to(i).ends_with("%") => {
+                    (arg.slice_from(i + 1), Some(arg.slice_to(i)))
+                }
+                _ => (arg, None)
+            }
         };
+        let percentage = match percentage {
+            None => None,
+            Some(p) => match parse_component(p) {
+                Some(Percentage(p)) if 0.0 <= p && p <= 100.0 => Some(p),
+                _ => return None
+            }
+        };
+        do parse_specified_color_with_palette(color, palette).map |specified| {
+            (specified.to_color(), percentage)
+        }
+    }
 
-        if vals.len() != 3u { return fail_unrecognized(color); }
+    /**
+    Parses the arguments of color-mix(), as in CSS Color 5. Only the default
+    shorter hue interpolation is supported.
+    */
+    fn parse_color_mix(args : &str, palette : &SystemColorPalette) -> Option<SpecifiedColor> {
+        let parts = split_top_level(args);
+        if parts.len() != 3u { return None; }
 
-        match (FromStr::from_str(vals[0]), FromStr::from_str(vals[1]), 
-               FromStr::from_str(vals[2])) {
-          (Some(h), Some(s), Some(l)) => { Some(hsl(h, s, l)) }
-          _ => { fail_unrecognized(color) }
+        let method: ~[&str] = parts[0].word_iter().collect();
+        if method.len() < 2u || !method[0].eq_ignore_ascii_case("in") { return None; }
+        let space = match parse_color_space(method[1]) {
+            Some(space) => space,
+            None => return None
+        };
+        match method.len() {
+            2u => (),
+            4u if method[2].eq_ignore_ascii_case("shorter")
+                  && method[3].eq_ignore_ascii_case("hue") => match space {
+                SpaceHsl | SpaceHwb | SpaceLch | SpaceOklch => (),
+                _ => return None
+            },
+            _ => return None
         }
+
+        let (first, first_percentage) = match parse_mix_color(parts[1], palette) {
+            Some(mix) => mix,
+            None => return None
+        };
+        let (second, second_percentage) = match parse_mix_color(parts[2], palette) {
+            Some(mix) => mix,
+            None => return None
+        };
+        let (p1, p2) = match (first_percentage, second_percentage) {
+            (None, None) => (50.0, 50.0),
+            (Some(p1), None) => (p1, 100.0 - p1),
+            (None, Some(p2)) => (100.0 - p2, p2),
+            (Some(p1), Some(p2)) => (p1, p2)
+        };
+        let sum = p1 + p2;
+        if sum == 0.0 { return None; }
+
+        // Percentages that add up to less than 100% make the result partly transparent
+        let mixed = first.interpolate(&second, p2 / sum, space);
+        let alpha = if sum < 100.0 { mixed.alpha * sum / 100.0 } else { mixed.alpha };
+        Some(SpecifiedRgb(rgba(mixed.red, mixed.green, mixed.blue, alpha)))
     }
 
-    /** Parses a color specification in the form hsla(foo,bar,baz,qux) */
-    fn parse_hsla(color : &str) -> Option<Color> {
-        // Shave off the hsla( and the )
-        let only_vals = color.slice(5u, color.len() - 1);
+    /** Parses a color specification in the form #rgb, #rgba, #rrggbb or #rrggbbaa */
+    fn parse_hex(color : &str) -> Option<Color> {
+        let mut digits = ~[];
+        for c in color.slice_from(1).iter() {
+            match c.to_digit(16) {
+                Some(d) => digits.push(d as u8),
+                None => return fail_unrecognized(color)
+            }
+        }
 
-        let mut vals = ~[];
-        for s in only_vals.split_iter(',') {
-            vals.push(s);
+        // Expand to one value per channel, with an opaque alpha if none was given
+        let mut channels = match digits.len() {
+            3u | 4u => digits.map(|&d| d * 17),
+            6u | 8u => {
+                let mut channels = ~[];
+                let mut i = 0;
+                while i < digits.len() {
+                    channels.push(digits[i] * 16 + digits[i + 1]);
+                    i += 2;
+                }
+                channels
+            }
+            _ => return fail_unrecognized(color)
         };
+        if channels.len() == 3u { channels.push(255); }
 
-        if vals.len() != 4u { return fail_unrecognized(color); }
+        Some(rgba(channels[0], channels[1], channels[2], (channels[3] as f64) / 255.0))
+    }
 
-        match (FromStr::from_str(vals[0]), FromStr::from_str(vals[1]), 
-               FromStr::from_str(vals[2]), FromStr::from_str(vals[3])) {
-          (Some(h), Some(s), Some(l), Some(a)) => { Some(hsla(h, s, l, a)) }
-          _ => { fail_unrecognized(color) }
+    /** Splits `name(args)` into the lowercased function name and its arguments */
+    fn split_function<'a>(color : &'a str) -> Option<(~str, &'a str)> {
+        match color.find('(') {
+            Some(open) if color.ends_with(")") => {
+                Some((color.slice_to(open).trim().to_ascii_lower(),
+                      color.slice(open + 1, color.len() - 1)))
+            }
+            _ => None
         }
     }
 
-    // Currently colors are supported in rgb(a,b,c) form and also by
-    // keywords for several common colors.
-    // TODO: extend this
+    /**
+    Parses a color in hex, rgb(), rgba(), hsl() or hsla() form, or by
+    keyword, following CSS Color 3 and the CSS Color 4 syntax for these
+    functions. The CSS Color 4 hwb(), lab(), lch(), oklab() and oklch()
+    functions and the CSS Color 5 color-mix() function are accepted too,
+    and gamut mapped into sRGB. System colors take their values from the
+    default palette.
+    */
     pub fn parse_color(color : &str) -> Option<Color> {
-        match color {
-          c if c.starts_with("rgb(") => parse_rgb(c),
-          c if c.starts_with("rgba(") => parse_rgba(c),
-          c if c.starts_with("hsl(") => parse_hsl(c),
-          c if c.starts_with("hsla(") => parse_hsla(c),
-          c => parse_by_name(c)
+        parse_color_with_palette(color, &SystemColorPalette::default())
+    }
+
+    /** Like `parse_color`, taking the values of system colors from `palette` */
+    pub fn parse_color_with_palette(color : &str, palette : &SystemColorPalette) -> Option<Color> {
+        parse_specified_color_with_palette(color, palette).map(|specified| specified.to_color())
+    }
+
+    /** Like `parse_color`, keeping the space the color was specified in */
+    pub fn parse_specified_color(color : &str) -> Option<SpecifiedColor> {
+        parse_specified_color_with_palette(color, &SystemColorPalette::default())
+    }
+
+    pub fn parse_specified_color_with_palette(color : &str, palette : &SystemColorPalette)
+                                              -> Option<SpecifiedColor> {
+        let color = color.trim();
+        if color.starts_with("#") {
+            return parse_hex(color).map(|&c| SpecifiedRgb(c));
         }
+        let specified = match split_function(color) {
+            Some((name, args)) => match name.as_slice() {
+                "rgb" | "rgba" => return parse_rgb(color, args).map(|&c| SpecifiedRgb(c)),
+                "hsl" | "hsla" => return parse_hsl(color, args).map(|&c| SpecifiedRgb(c)),
+                "hwb" => parse_hwb(args),
+                "lab" => parse_lab(args),
+                "lch" => parse_lch(args),
+                "oklab" => parse_oklab(args),
+                "oklch" => parse_oklch(args),
+                "color-mix" => parse_color_mix(args, palette),
+                _ => None
+            },
+            None => return match parse_system_color(color) {
+                Some(system_color) => Some(SpecifiedRgb(palette.get(system_color))),
+                None => parse_by_name(color).map(|&c| SpecifiedRgb(c))
+            }
+        };
+        if specified.is_none() {
+            fail_unrecognized(color);
+        }
+        specified
     }
 }
 
@@ -187,11 +1464,39 @@ pub fn fail_unrecognized(col : &str) -> Option<Color> {
     return None;
 }
 
+/** Looks up a named color, ignoring ASCII case */
 pub fn parse_by_name(name : &str) -> Option<Color> {
+    let name = name.trim();
+    if name.eq_ignore_ascii_case("transparent") {
+        return Some(TRANSPARENT);
+    }
+
+    let mut low = 0;
+    let mut high = NAMED_COLORS.len();
+    while low < high {
+        let middle = (low + high) / 2;
+        let (entry, color) = NAMED_COLORS[middle];
+        match compare_ignore_ascii_case(name, entry) {
+            Less => high = middle,
+            Greater => low = middle + 1,
+            Equal => return Some(color)
+        }
+    }
+    fail_unrecognized(name)
+}
+
+/**
+The lookup `parse_by_name` used to do, uppercasing the name and comparing it
+against every color, as the baseline of its benchmark
+*/
+#[cfg(test)]
+fn parse_by_name_static(name : &str) -> Option<Color> {
+    use std::ascii::AsciiStr;
+
     let color = parse_static_color!(name,
             ALICEBLUE, ANTIQUEWHITE, AQUA, AQUAMARINE, AZURE,
             BEIGE, BISQUE, BLACK, BLANCHEDALMOND, BLUE,
-            BLUEVIOLET, BROWN, BURLYWOOD, CADETBLUE, CHARTREUSE, 
+            BLUEVIOLET, BROWN, BURLYWOOD, CADETBLUE, CHARTREUSE,
             CHOCOLATE, CORAL, CORNFLOWERBLUE, CORNSILK, CRIMSON,
             CYAN, DARKBLUE, DARKCYAN, DARKGOLDENROD, DARKGRAY,
             DARKGREEN, DARKGREY, DARKKHAKI, DARKMAGENTA, DARKOLIVEGREEN,
@@ -227,6 +1532,321 @@ pub fn parse_by_name(name : &str) -> Option<Color> {
     }
 }
 
+/** Compares `name` case-insensitively against the lowercase `lower`, without allocating */
+fn compare_ignore_ascii_case(name : &str, lower : &str) -> Ordering {
+    let name = name.as_bytes();
+    let lower = lower.as_bytes();
+    let mut i = 0;
+    while i < name.len() && i < lower.len() {
+        let c = name[i];
+        let c = if 'A' as u8 <= c && c <= 'Z' as u8 { c + 32 } else { c };
+        if c != lower[i] {
+            return if c < lower[i] { Less } else { Greater };
+        }
+        i += 1;
+    }
+    name.len().cmp(&lower.len())
+}
+
+/** The named colors of CSS Color 3, sorted by name for binary search */
+static NAMED_COLORS: &'static [(&'static str, Color)] = &[
+    ("aliceblue", ALICEBLUE),
+    ("antiquewhite", ANTIQUEWHITE),
+    ("aqua", AQUA),
+    ("aquamarine", AQUAMARINE),
+    ("azure", AZURE),
+    ("beige", BEIGE),
+    ("bisque", BISQUE),
+    ("black", BLACK),
+    ("blanchedalmond", BLANCHEDALMOND),
+    ("blue", BLUE),
+    ("blueviolet", BLUEVIOLET),
+    ("brown", BROWN),
+    ("burlywood", BURLYWOOD),
+    ("cadetblue", CADETBLUE),
+    ("chartreuse", CHARTREUSE),
+    ("chocolate", CHOCOLATE),
+    ("coral", CORAL),
+    ("cornflowerblue", CORNFLOWERBLUE),
+    ("cornsilk", CORNSILK),
+    ("crimson", CRIMSON),
+    ("cyan", CYAN),
+    ("darkblue", DARKBLUE),
+    ("darkcyan", DARKCYAN),
+    ("darkgoldenrod", DARKGOLDENROD),
+    ("darkgray", DARKGRAY),
+    ("darkgreen", DARKGREEN),
+    ("darkgrey", DARKGREY),
+    ("darkkhaki", DARKKHAKI),
+    ("darkmagenta", DARKMAGENTA),
+    ("darkolivegreen", DARKOLIVEGREEN),
+    ("darkorange", DARKORANGE),
+    ("darkorchid", DARKORCHID),
+    ("darkred", DARKRED),
+    ("darksalmon", DARKSALMON),
+    ("darkseagreen", DARKSEAGREEN),
+    ("darkslateblue", DARKSLATEBLUE),
+    ("darkslategray", DARKSLATEGRAY),
+    ("darkslategrey", DARKSLATEGREY),
+    ("darkturquoise", DARKTURQUOISE),
+    ("darkviolet", DARKVIOLET),
+    ("deeppink", DEEPPINK),
+    ("deepskyblue", DEEPSKYBLUE),
+    ("dimgray", DIMGRAY),
+    ("dimgrey", DIMGREY),
+    ("dodgerblue", DODGERBLUE),
+    ("firebrick", FIREBRICK),
+    ("floralwhite", FLORALWHITE),
+    ("forestgreen", FORESTGREEN),
+    ("fuchsia", FUCHSIA),
+    ("gainsboro", GAINSBORO),
+    ("ghostwhite", GHOSTWHITE),
+    ("gold", GOLD),
+    ("goldenrod", GOLDENROD),
+    ("gray", GRAY),
+    ("green", GREEN),
+    ("greenyellow", GREENYELLOW),
+    ("grey", GREY),
+    ("honeydew", HONEYDEW),
+    ("hotpink", HOTPINK),
+    ("indianred", INDIANRED),
+    ("indigo", INDIGO),
+    ("ivory", IVORY),
+    ("khaki", KHAKI),
+    ("lavender", LAVENDER),
+    ("lavenderblush", LAVENDERBLUSH),
+    ("lawngreen", LAWNGREEN),
+    ("lemonchiffon", LEMONCHIFFON),
+    ("lightblue", LIGHTBLUE),
+    ("lightcoral", LIGHTCORAL),
+    ("lightcyan", LIGHTCYAN),
+    ("lightgoldenrodyellow", LIGHTGOLDENRODYELLOW),
+    ("lightgray", LIGHTGRAY),
+    ("lightgreen", LIGHTGREEN),
+    ("lightgrey", LIGHTGREY),
+    ("lightpink", LIGHTPINK),
+    ("lightsalmon", LIGHTSALMON),
+    ("lightseagreen", LIGHTSEAGREEN),
+    ("lightskyblue", LIGHTSKYBLUE),
+    ("lightslategray", LIGHTSLATEGRAY),
+    ("lightslategrey", LIGHTSLATEGREY),
+    ("lightsteelblue", LIGHTSTEELBLUE),
+    ("lightyellow", LIGHTYELLOW),
+    ("lime", LIME),
+    ("limegreen", LIMEGREEN),
+    ("linen", LINEN),
+    ("magenta", MAGENTA),
+    ("maroon", MAROON),
+    ("mediumaquamarine", MEDIUMAQUAMARINE),
+    ("mediumblue", MEDIUMBLUE),
+    ("mediumorchid", MEDIUMORCHID),
+    ("mediumpurple", MEDIUMPURPLE),
+    ("mediumseagreen", MEDIUMSEAGREEN),
+    ("mediumslateblue", MEDIUMSLATEBLUE),
+    ("mediumspringgreen", MEDIUMSPRINGGREEN),
+    ("mediumturquoise", MEDIUMTURQUOISE),
+    ("mediumvioletred", MEDIUMVIOLETRED),
+    ("midnightblue", MIDNIGHTBLUE),
+    ("mintcream", MINTCREAM),
+    ("mistyrose", MISTYROSE),
+    ("moccasin", MOCCASIN),
+    ("navajowhite", NAVAJOWHITE),
+    ("navy", NAVY),
+    ("oldlace", OLDLACE),
+    ("olive", OLIVE),
+    ("olivedrab", OLIVEDRAB),
+    ("orange", ORANGE),
+    ("orangered", ORANGERED),
+    ("orchid", ORCHID),
+    ("palegoldenrod", PALEGOLDENROD),
+    ("palegreen", PALEGREEN),
+    ("paleturquoise", PALETURQUOISE),
+    ("palevioletred", PALEVIOLETRED),
+    ("papayawhip", PAPAYAWHIP),
+    ("peachpuff", PEACHPUFF),
+    ("peru", PERU),
+    ("pink", PINK),
+    ("plum", PLUM),
+    ("powderblue", POWDERBLUE),
+    ("purple", PURPLE),
+    ("red", RED),
+    ("rosybrown", ROSYBROWN),
+    ("royalblue", ROYALBLUE),
+    ("saddlebrown", SADDLEBROWN),
+    ("salmon", SALMON),
+    ("sandybrown", SANDYBROWN),
+    ("seagreen", SEAGREEN),
+    ("seashell", SEASHELL),
+    ("sienna", SIENNA),
+    ("silver", SILVER),
+    ("skyblue", SKYBLUE),
+    ("slateblue", SLATEBLUE),
+    ("slategray", SLATEGRAY),
+    ("slategrey", SLATEGREY),
+    ("snow", SNOW),
+    ("springgreen", SPRINGGREEN),
+    ("steelblue", STEELBLUE),
+    ("tan", TAN),
+    ("teal", TEAL),
+    ("thistle", THISTLE),
+    ("tomato", TOMATO),
+    ("turquoise", TURQUOISE),
+    ("violet", VIOLET),
+    ("wheat", WHEAT),
+    ("white", WHITE),
+    ("whitesmoke", WHITESMOKE),
+    ("yellow", YELLOW),
+    ("yellowgreen", YELLOWGREEN),
+];
+
+/**
+The named colors sorted by their packed 0xRRGGBB value. Where several names
+share a value only the first in alphabetical order is listed.
+*/
+static NAMED_COLORS_BY_VALUE: &'static [(u32, &'static str)] = &[
+    (0x000000, "black"),
+    (0x000080, "navy"),
+    (0x00008b, "darkblue"),
+    (0x0000cd, "mediumblue"),
+    (0x0000ff, "blue"),
+    (0x006400, "darkgreen"),
+    (0x008000, "green"),
+    (0x008080, "teal"),
+    (0x008b8b, "darkcyan"),
+    (0x00bfff, "deepskyblue"),
+    (0x00ced1, "darkturquoise"),
+    (0x00fa9a, "mediumspringgreen"),
+    (0x00ff00, "lime"),
+    (0x00ff7f, "springgreen"),
+    (0x00ffff, "aqua"),
+    (0x191970, "midnightblue"),
+    (0x1e90ff, "dodgerblue"),
+    (0x20b2aa, "lightseagreen"),
+    (0x228b22, "forestgreen"),
+    (0x2e8b57, "seagreen"),
+    (0x2f4f4f, "darkslategray"),
+    (0x32cd32, "limegreen"),
+    (0x3cb371, "mediumseagreen"),
+    (0x40e0d0, "turquoise"),
+    (0x4169e1, "royalblue"),
+    (0x4682b4, "steelblue"),
+    (0x483d8b, "darkslateblue"),
+    (0x48d1cc, "mediumturquoise"),
+    (0x4b0082, "indigo"),
+    (0x556b2f, "darkolivegreen"),
+    (0x5f9ea0, "cadetblue"),
+    (0x6495ed, "cornflowerblue"),
+    (0x66cdaa, "mediumaquamarine"),
+    (0x696969, "dimgray"),
+    (0x6a5acd, "slateblue"),
+    (0x6b8e23, "olivedrab"),
+    (0x708090, "slategray"),
+    (0x778899, "lightslategray"),
+    (0x7b68ee, "mediumslateblue"),
+    (0x7cfc00, "lawngreen"),
+    (0x7fff00, "chartreuse"),
+    (0x7fffd4, "aquamarine"),
+    (0x800000, "maroon"),
+    (0x800080, "purple"),
+    (0x808000, "olive"),
+    (0x808080, "gray"),
+    (0x87ceeb, "skyblue"),
+    (0x87cefa, "lightskyblue"),
+    (0x8a2be2, "blueviolet"),
+    (0x8b0000, "darkred"),
+    (0x8b008b, "darkmagenta"),
+    (0x8b4513, "saddlebrown"),
+    (0x8fbc8f, "darkseagreen"),
+    (0x90ee90, "lightgreen"),
+    (0x9370db, "mediumpurple"),
+    (0x9400d3, "darkviolet"),
+    (0x98fb98, "palegreen"),
+    (0x9932cc, "darkorchid"),
+    (0x9acd32, "yellowgreen"),
+    (0xa0522d, "sienna"),
+    (0xa52a2a, "brown"),
+    (0xa9a9a9, "darkgray"),
+    (0xadd8e6, "lightblue"),
+    (0xadff2f, "greenyellow"),
+    (0xafeeee, "paleturquoise"),
+    (0xb0c4de, "lightsteelblue"),
+    (0xb0e0e6, "powderblue"),
+    (0xb22222, "firebrick"),
+    (0xb8860b, "darkgoldenrod"),
+    (0xba55d3, "mediumorchid"),
+    (0xbc8f8f, "rosybrown"),
+    (0xbdb76b, "darkkhaki"),
+    (0xc0c0c0, "silver"),
+    (0xc71585, "mediumvioletred"),
+    (0xcd5c5c, "indianred"),
+    (0xcd853f, "peru"),
+    (0xd2691e, "chocolate"),
+    (0xd2b48c, "tan"),
+    (0xd3d3d3, "lightgray"),
+    (0xd8bfd8, "thistle"),
+    (0xda70d6, "orchid"),
+    (0xdaa520, "goldenrod"),
+    (0xdb7093, "palevioletred"),
+    (0xdc143c, "crimson"),
+    (0xdcdcdc, "gainsboro"),
+    (0xdda0dd, "plum"),
+    (0xdeb887, "burlywood"),
+    (0xe0ffff, "lightcyan"),
+    (0xe6e6fa, "lavender"),
+    (0xe9967a, "darksalmon"),
+    (0xee82ee, "violet"),
+    (0xeee8aa, "palegoldenrod"),
+    (0xf08080, "lightcoral"),
+    (0xf0e68c, "khaki"),
+    (0xf0f8ff, "aliceblue"),
+    (0xf0fff0, "honeydew"),
+    (0xf0ffff, "azure"),
+    (0xf4a460, "sandybrown"),
+    (0xf5deb3, "wheat"),
+    (0xf5f5dc, "beige"),
+    (0xf5f5f5, "whitesmoke"),
+    (0xf5fffa, "mintcream"),
+    (0xf8f8ff, "ghostwhite"),
+    (0xfa8072, "salmon"),
+    (0xfaebd7, "antiquewhite"),
+    (0xfaf0e6, "linen"),
+    (0xfafad2, "lightgoldenrodyellow"),
+    (0xfdf5e6, "oldlace"),
+    (0xff0000, "red"),
+    (0xff00ff, "fuchsia"),
+    (0xff1493, "deeppink"),
+    (0xff4500, "orangered"),
+    (0xff6347, "tomato"),
+    (0xff69b4, "hotpink"),
+    (0xff7f50, "coral"),
+    (0xff8c00, "darkorange"),
+    (0xffa07a, "lightsalmon"),
+    (0xffa500, "orange"),
+    (0xffb6c1, "lightpink"),
+    (0xffc0cb, "pink"),
+    (0xffd700, "gold"),
+    (0xffdab9, "peachpuff"),
+    (0xffdead, "navajowhite"),
+    (0xffe4b5, "moccasin"),
+    (0xffe4c4, "bisque"),
+    (0xffe4e1, "mistyrose"),
+    (0xffebcd, "blanchedalmond"),
+    (0xffefd5, "papayawhip"),
+    (0xfff0f5, "lavenderblush"),
+    (0xfff5ee, "seashell"),
+    (0xfff8dc, "cornsilk"),
+    (0xfffacd, "lemonchiffon"),
+    (0xfffaf0, "floralwhite"),
+    (0xfffafa, "snow"),
+    (0xffff00, "yellow"),
+    (0xffffe0, "lightyellow"),
+    (0xfffff0, "ivory"),
+    (0xffffff, "white"),
+];
+
+/** CSS Color 3, 4.2.3: 'transparent' is transparent black */
+pub static TRANSPARENT: Color = Color { red: 0, green: 0, blue: 0, alpha: 0.0 };
+
 // Define the colors specified by css
 define_color!(ALICEBLUE, 240, 248, 255)
 define_color!(ANTIQUEWHITE, 250, 235, 215)
@@ -378,8 +1998,16 @@ define_color!(YELLOWGREEN, 154, 205, 50)
 
 #[cfg(test)]
 mod test {
-    use super::{rgb, rgba};
-    use super::parsing::parse_color;
+    use std::ascii::StrAsciiExt;
+    use std::rand;
+    use std::rand::RngUtil;
+    use extra::test::BenchHarness;
+    use super::{rgb, rgba, hsl, hsla, SystemColorPalette, SystemColorWindow, SystemColorGrayText};
+    use super::{SpaceSrgb, SpaceLinearSrgb, SpaceHsl, SpaceHwb};
+    use super::{SpaceLab, SpaceLch, SpaceOklab, SpaceOklch};
+    use super::{NAMED_COLORS, NAMED_COLORS_BY_VALUE, parse_by_name, parse_by_name_static};
+    use super::{SpecifiedRgb, SpecifiedLab, Laba};
+    use super::parsing::{parse_color, parse_color_with_palette, parse_specified_color};
 
     #[test]
     fn test_parsing_rgb() {
@@ -393,6 +2021,412 @@ mod test {
         assert!(None == parse_color("rbga(1,2,3)"));
     }
 
+    #[test]
+    fn test_parsing_conformance() {
+        let cases = ~[
+            // CSS Color 3 percentages, clamping and whitespace
+            ("rgb(100%, 0%, 0%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("rgb(50%,50%,50%)", Some(rgb(128u8, 128u8, 128u8))),
+            ("rgb(300,0,0)", Some(rgb(255u8, 0u8, 0u8))),
+            ("rgb(-10,0,0)", Some(rgb(0u8, 0u8, 0u8))),
+            ("rgb(110%, -5%, 0%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("  rgb( 1 , 2 , 3 )  ", Some(rgb(1u8, 2u8, 3u8))),
+            ("RGB(1,2,3)", Some(rgb(1u8, 2u8, 3u8))),
+            ("rgba(1,2,3,2)", Some(rgba(1u8, 2u8, 3u8, 1.0))),
+            ("rgba(1,2,3,-1)", Some(rgba(1u8, 2u8, 3u8, 0.0))),
+            ("rgb(100%, 0, 0)", None),
+            ("rgb(1,2)", None),
+            ("rgb(1,2,3", None),
+            ("rgb(1,,3)", None),
+            ("rgb(a,b,c)", None),
+            ("hsl(120, 100%, 50%)", Some(rgb(0u8, 255u8, 0u8))),
+            ("hsl( 120 , 100% , 25% )", Some(rgb(0u8, 128u8, 0u8))),
+            ("hsl(0, 200%, 50%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(0, 100%, 150%)", Some(rgb(255u8, 255u8, 255u8))),
+            ("hsla(240, 100%, 50%, 0.5)", Some(rgba(0u8, 0u8, 255u8, 0.5))),
+            ("hsl(120%, 100%, 50%)", None),
+            // Hues wrap around and may have angle units
+            ("hsl(-480, 1, .5)", Some(rgb(0u8, 0u8, 255u8))),
+            ("hsl(720, 100%, 50%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(-120, 100%, 50%)", Some(rgb(0u8, 0u8, 255u8))),
+            ("hsl(360, 100%, 50%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(120deg, 100%, 50%)", Some(rgb(0u8, 255u8, 0u8))),
+            ("hsl(3.14159265358979rad 100% 50%)", Some(rgb(0u8, 255u8, 255u8))),
+            ("hsl(400grad 100% 50%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(200GRAD 100% 50%)", Some(rgb(0u8, 255u8, 255u8))),
+            ("hsl(0.5turn 100% 50%)", Some(rgb(0u8, 255u8, 255u8))),
+            ("hsl(-0.25turn 100% 50%)", Some(rgb(128u8, 0u8, 255u8))),
+            ("hwb(1turn 0% 0%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(120, 100deg, 50%)", None),
+            ("rgb(10deg 0 0)", None),
+            ("hsl(120 100% 50% / 1deg)", None),
+            ("rgba(1, 2, 3, 0.5turn)", None),
+            ("hsl(120px, 100%, 50%)", None),
+            // CSS Color 4 aliases and space-separated syntax
+            ("rgb(0, 0, 0, 0.5)", Some(rgba(0u8, 0u8, 0u8, 0.5))),
+            ("rgba(0, 0, 0)", Some(rgb(0u8, 0u8, 0u8))),
+            ("rgb(1 2 3)", Some(rgb(1u8, 2u8, 3u8))),
+            ("rgb(1 2 3 / 50%)", Some(rgba(1u8, 2u8, 3u8, 0.5))),
+            ("rgb(1 2 3 / 0.25)", Some(rgba(1u8, 2u8, 3u8, 0.25))),
+            ("rgb(100% 0 0)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hsl(240 100 50)", Some(rgb(0u8, 0u8, 255u8))),
+            ("hsl(240 100% 50% / 0)", Some(rgba(0u8, 0u8, 255u8, 0.0))),
+            ("rgb(1 2, 3)", None),
+            ("rgb(1, 2, 3 / 1)", None),
+            ("rgb(1 2 3 4)", None),
+            ("rgb(1 2 3 /)", None),
+        ];
+        for &(input, expected) in cases.iter() {
+            let actual = parse_color(input);
+            assert!(actual == expected, fmt!("%s: expected %?, got %?", input, expected, actual));
+        }
+    }
+
+    #[test]
+    fn test_hsla_never_fails() {
+        let mut rng = rand::rng();
+        for _ in range(0, 10000) {
+            let h = (rng.gen::<f64>() - 0.5) * 1e6;
+            let s = rng.gen::<f64>() * 4.0 - 1.5;
+            let l = rng.gen::<f64>() * 4.0 - 1.5;
+            let a = rng.gen::<f64>();
+            let color = hsla(h, s, l, a);
+            // Out of range saturation and lightness act like the nearest bound
+            let clamp = |v: f64| if v < 0.0 { 0.0 } else if v > 1.0 { 1.0 } else { v };
+            assert!(color == hsla(h, clamp(s), clamp(l), a));
+
+            let input = fmt!("hsl(%fdeg %f%% %f%% / %f)", h, s * 100.0, l * 100.0, a);
+            assert!(parse_color(input).is_some());
+            let input = fmt!("hsla(%f, %f%%, %f%%, %f)", h, s * 100.0, l * 100.0, a);
+            assert!(parse_color(input).is_some());
+        }
+        for &h in [-480.0, -360.0, -1e-20, 0.0, 359.99999999, 360.0, 720.0, 1e20].iter() {
+            hsla(h, 1.0, 0.5, 1.0);
+        }
+    }
+
+    #[test]
+    fn test_parsing_color_4_functions() {
+        let cases = ~[
+            ("hwb(0 0% 0%)", Some(rgb(255u8, 0u8, 0u8))),
+            ("hwb(120 0% 50%)", Some(rgb(0u8, 128u8, 0u8))),
+            ("hwb(0 60% 60%)", Some(rgb(128u8, 128u8, 128u8))),
+            ("hwb(240 0% 0% / 0.5)", Some(rgba(0u8, 0u8, 255u8, 0.5))),
+            ("hwb(0, 0%, 0%)", None),
+            // Examples from CSS Color 4, Sections 9 and 10
+            ("lab(29.2345% 39.3825 20.0664)", Some(rgb(125u8, 35u8, 41u8))),
+            ("lab(52.2345% 40.1645 59.9971)", Some(rgb(198u8, 93u8, 6u8))),
+            ("lab(60.2345 -5.3654 58.956)", Some(rgb(157u8, 147u8, 24u8))),
+            ("lab(67.5345% -8.6911 -41.6019)", Some(rgb(98u8, 172u8, 239u8))),
+            ("lch(29.2345% 44.2 27)", Some(rgb(125u8, 35u8, 41u8))),
+            ("lch(62.2345% 59.2 126.2)", Some(rgb(104u8, 166u8, 57u8))),
+            ("lch(67.5345% 42.5 258.2 / 50%)", Some(rgba(98u8, 172u8, 239u8, 0.5))),
+            ("oklab(40.101% 0.1147 0.0453)", Some(rgb(125u8, 35u8, 41u8))),
+            ("oklab(0.65125 -0.0320 0.1274)", Some(rgb(157u8, 147u8, 24u8))),
+            ("oklab(72.322% -0.0465 -0.1150)", Some(rgb(98u8, 172u8, 239u8))),
+            ("oklch(40.101% 0.12332 21.555)", Some(rgb(125u8, 35u8, 41u8))),
+            ("oklch(59.686% 0.15619 49.7694)", Some(rgb(198u8, 93u8, 6u8))),
+            ("oklch(0.66016 0.15546 134.231)", Some(rgb(104u8, 166u8, 57u8))),
+            ("OKLCH(62.796% 0.25768 29.23)", Some(rgb(255u8, 0u8, 0u8))),
+            // Lightness out of range is clamped, and white and black are fixed points
+            ("lab(150 0 0)", Some(rgb(255u8, 255u8, 255u8))),
+            ("lch(-10% 0 0)", Some(rgb(0u8, 0u8, 0u8))),
+            ("lab(50, 0, 0)", None),
+            ("lch(50% 50 20%)", None),
+            ("oklab(0.5 0.1)", None),
+        ];
+        for &(input, expected) in cases.iter() {
+            let actual = parse_color(input);
+            if actual != expected {
+                fail!(fmt!("parse_color(%?) = %?, expected %?", input, actual, expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_gamut_mapping() {
+        // Out of gamut colors have their chroma reduced, not their channels clipped
+        assert!(parse_color("oklch(70% 0.4 150)") == Some(rgb(0u8, 194u8, 72u8)));
+        assert!(parse_color("oklch(50% 0.5 270)") == Some(rgb(52u8, 37u8, 255u8)));
+        assert!(parse_color("lch(50% 150 0)") == Some(rgb(248u8, 0u8, 131u8)));
+    }
+
+    #[test]
+    fn test_parsing_color_mix() {
+        let cases = ~[
+            ("color-mix(in srgb, red, blue)", Some(rgb(128u8, 0u8, 128u8))),
+            ("color-mix(in srgb, red 40%, blue)", Some(rgb(102u8, 0u8, 153u8))),
+            ("color-mix(in srgb, 40% red, blue 60%)", Some(rgb(102u8, 0u8, 153u8))),
+            ("color-mix(in srgb, red 80%, blue 120%)", None),
+            ("color-mix(in srgb, red 20%, blue 20%)", Some(rgba(128u8, 0u8, 128u8, 0.4))),
+            ("color-mix(in srgb, red 0%, blue 0%)", None),
+            ("color-mix(in hsl, red, blue)", Some(rgb(255u8, 0u8, 255u8))),
+            ("color-mix(in hsl shorter hue, red, blue)", Some(rgb(255u8, 0u8, 255u8))),
+            ("color-mix(in hsl longer hue, red, blue)", None),
+            ("color-mix(in srgb shorter hue, red, blue)", None),
+            ("color-mix(in oklab, white, black)", Some(rgb(99u8, 99u8, 99u8))),
+            ("color-mix(in lab, white, black)", Some(rgb(119u8, 119u8, 119u8))),
+            ("color-mix(in srgb, rgb(255 0 0), hsl(240, 100%, 50%))", Some(rgb(128u8, 0u8, 128u8))),
+            ("color-mix(in srgb, red, color-mix(in srgb, blue, blue))", Some(rgb(128u8, 0u8, 128u8))),
+            ("color-mix(in xyz, red, blue)", None),
+            ("color-mix(red, blue)", None),
+            ("color-mix(in srgb, red)", None),
+        ];
+        for &(input, expected) in cases.iter() {
+            let actual = parse_color(input);
+            if actual != expected {
+                fail!(fmt!("parse_color(%?) = %?, expected %?", input, actual, expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_specified_color_space() {
+        assert!(parse_specified_color("red") == Some(SpecifiedRgb(rgb(255u8, 0u8, 0u8))));
+        let lab = parse_specified_color("lab(29.2345% 39.3825 20.0664)");
+        assert!(lab == Some(SpecifiedLab(Laba {
+            lightness: 29.2345, a: 39.3825, b: 20.0664, alpha: 1.0
+        })));
+        let serialize = |input: &str| parse_specified_color(input).unwrap().to_css_string();
+        assert_eq!(serialize("lab(29.2345% 39.3825 20.0664)"), ~"lab(29.2345 39.3825 20.0664)");
+        assert_eq!(serialize("lch(50% 20 30 / 0.5)"), ~"lch(50 20 30 / 0.5)");
+        assert_eq!(serialize("oklab(0.5 -0.1 0)"), ~"oklab(0.5 -0.1 0)");
+        assert_eq!(serialize("oklch(100% 0.4 90)"), ~"oklch(1 0.4 90)");
+        assert_eq!(serialize("hwb(120 0% 50%)"), ~"rgb(0, 128, 0)");
+        assert_eq!(serialize("#ff000080"), ~"rgba(255, 0, 0, 0.5)");
+    }
+
+    #[test]
+    fn test_parsing_hex() {
+        assert!(parse_color("red").unwrap().eq(&parse_color("#f00").unwrap()));
+        assert!(parse_color("red").unwrap().eq(&parse_color("#FF0000").unwrap()));
+        assert!(parse_color("red").unwrap().eq(&parse_color("#ff0000ff").unwrap()));
+        assert!(rgb(0x12u8,0x34u8,0x56u8).eq(&parse_color("#123456").unwrap()));
+        assert!(rgb(0x11u8,0x22u8,0x33u8).eq(&parse_color("#123").unwrap()));
+        assert!(rgba(0x11u8,0x22u8,0x33u8,0.0).eq(&parse_color("#1230").unwrap()));
+        assert!(rgba(0xaau8,0xbbu8,0xccu8,1.0).eq(&parse_color("#abcf").unwrap()));
+        assert!(rgba(0x12u8,0x34u8,0x56u8,0x80 as f64 / 255.0).eq(&parse_color("#12345680").unwrap()));
+        assert!(None == parse_color("#"));
+        assert!(None == parse_color("#12"));
+        assert!(None == parse_color("#12345"));
+        assert!(None == parse_color("#1234567"));
+        assert!(None == parse_color("#123456789"));
+        assert!(None == parse_color("#ggg"));
+        assert!(None == parse_color("#12 345"));
+    }
+
+    fn approx_eq(a : f64, b : f64, epsilon : f64) -> bool {
+        (a - b).abs() < epsilon
+    }
+
+    #[test]
+    fn test_color_space_conversions() {
+        let red = rgb(255u8, 0u8, 0u8);
+
+        let hsl = red.to_hsl();
+        assert!(hsl.hue == 0.0 && hsl.saturation == 1.0 && hsl.lightness == 0.5);
+        let hsl = rgb(0u8, 128u8, 0u8).to_hsl();
+        assert!(approx_eq(hsl.hue, 120.0, 1e-9) && approx_eq(hsl.lightness, 0.251, 1e-3));
+
+        let hwb = rgb(128u8, 128u8, 128u8).to_hwb();
+        assert!(approx_eq(hwb.whiteness, 0.502, 1e-3) && approx_eq(hwb.blackness, 0.498, 1e-3));
+
+        let linear = rgb(128u8, 128u8, 128u8).to_linear();
+        assert!(approx_eq(linear.red, 0.2159, 1e-4));
+
+        // Reference values from CSS Color 4
+        let lab = red.to_lab();
+        assert!(approx_eq(lab.lightness, 54.29, 0.01));
+        assert!(approx_eq(lab.a, 80.80, 0.05));
+        assert!(approx_eq(lab.b, 69.89, 0.05));
+        let lch = red.to_lch();
+        assert!(approx_eq(lch.chroma, 106.84, 0.05) && approx_eq(lch.hue, 40.85, 0.05));
+        let oklab = red.to_oklab();
+        assert!(approx_eq(oklab.lightness, 0.62796, 1e-4));
+        assert!(approx_eq(oklab.a, 0.22486, 1e-4));
+        assert!(approx_eq(oklab.b, 0.12585, 1e-4));
+        let oklch = red.to_oklch();
+        assert!(approx_eq(oklch.chroma, 0.25768, 1e-4) && approx_eq(oklch.hue, 29.23, 0.01));
+
+        let white = rgb(255u8, 255u8, 255u8).to_lab();
+        assert!(approx_eq(white.lightness, 100.0, 1e-3) && approx_eq(white.a, 0.0, 1e-3));
+    }
+
+    #[test]
+    fn test_color_space_round_trips() {
+        let colors = ~[
+            rgb(255u8, 0u8, 0u8), rgb(0u8, 0u8, 0u8), rgb(255u8, 255u8, 255u8),
+            rgb(18u8, 52u8, 86u8), rgb(250u8, 128u8, 114u8), rgba(1u8, 200u8, 3u8, 0.5),
+        ];
+        for color in colors.iter() {
+            assert!(color.to_hsl().to_color() == *color);
+            assert!(color.to_hwb().to_color() == *color);
+            assert!(color.to_linear().to_color() == *color);
+            assert!(color.to_lab().to_color() == *color);
+            assert!(color.to_lch().to_color() == *color);
+            assert!(color.to_oklab().to_color() == *color);
+            assert!(color.to_oklch().to_color() == *color);
+        }
+    }
+
+    #[test]
+    fn test_compositing() {
+        let half_red = rgba(255u8, 0u8, 0u8, 0.5);
+        assert!(half_red.over(&rgb(255u8, 255u8, 255u8)) == rgb(255u8, 128u8, 128u8));
+        assert!(half_red.over(&rgba(0u8, 0u8, 0u8, 0.0)) == half_red);
+        assert!(rgb(1u8, 2u8, 3u8).over(&rgb(200u8, 200u8, 200u8)) == rgb(1u8, 2u8, 3u8));
+        assert!(rgba(1u8, 2u8, 3u8, 0.0).over(&rgba(4u8, 5u8, 6u8, 0.0)).alpha == 0.0);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let red = rgb(255u8, 0u8, 0u8);
+        let blue = rgb(0u8, 0u8, 255u8);
+        let spaces = ~[SpaceSrgb, SpaceLinearSrgb, SpaceHsl, SpaceHwb,
+                       SpaceLab, SpaceLch, SpaceOklab, SpaceOklch];
+        for &space in spaces.iter() {
+            assert!(red.interpolate(&blue, 0.0, space) == red);
+            assert!(red.interpolate(&blue, 1.0, space) == blue);
+        }
+        assert!(red.interpolate(&blue, 0.5, SpaceSrgb) == rgb(128u8, 0u8, 128u8));
+        // Shorter hue arc: from 0 to 240 goes through 300 (magenta)
+        assert!(red.interpolate(&blue, 0.5, SpaceHsl) == rgb(255u8, 0u8, 255u8));
+        // Achromatic endpoints take the other color's hue
+        let white = rgb(255u8, 255u8, 255u8);
+        let mid = white.interpolate(&red, 0.5, SpaceHsl);
+        assert!(mid == rgb(255u8, 128u8, 128u8));
+        // Premultiplied alpha: a transparent endpoint doesn't pull in its color
+        let clear = rgba(0u8, 0u8, 255u8, 0.0);
+        let mid = red.interpolate(&clear, 0.5, SpaceSrgb);
+        assert!(mid == rgba(255u8, 0u8, 0u8, 0.5));
+    }
+
+    #[test]
+    fn test_contrast() {
+        let black = rgb(0u8, 0u8, 0u8);
+        let white = rgb(255u8, 255u8, 255u8);
+        assert!(approx_eq(white.relative_luminance(), 1.0, 1e-9));
+        assert!(approx_eq(black.relative_luminance(), 0.0, 1e-9));
+        assert!(approx_eq(black.contrast_ratio(&white), 21.0, 1e-9));
+        assert!(approx_eq(white.contrast_ratio(&black), 21.0, 1e-9));
+        assert!(approx_eq(white.contrast_ratio(&white), 1.0, 1e-9));
+        assert!(approx_eq(rgb(118u8, 118u8, 118u8).contrast_ratio(&white), 4.54, 0.01));
+    }
+
+    #[test]
+    fn test_named_color_tables_sorted() {
+        for i in range(1, NAMED_COLORS.len()) {
+            assert!(NAMED_COLORS[i - 1].first() < NAMED_COLORS[i].first());
+        }
+        for i in range(1, NAMED_COLORS_BY_VALUE.len()) {
+            assert!(NAMED_COLORS_BY_VALUE[i - 1].first() < NAMED_COLORS_BY_VALUE[i].first());
+        }
+    }
+
+    #[test]
+    fn test_parsing_by_name() {
+        for &(name, color) in NAMED_COLORS.iter() {
+            assert!(parse_by_name(name) == Some(color));
+            assert!(parse_by_name(name.to_ascii_upper()) == Some(color));
+        }
+        assert!(parse_by_name("LightGoldenRodYellow") == Some(rgb(250u8, 250u8, 210u8)));
+        assert!(parse_by_name(" navy ") == Some(rgb(0u8, 0u8, 128u8)));
+        assert!(parse_by_name("") == None);
+        assert!(parse_by_name("re") == None);
+        assert!(parse_by_name("redd") == None);
+        assert!(parse_by_name("zzz") == None);
+    }
+
+    #[test]
+    fn test_color_name() {
+        for &(name, color) in NAMED_COLORS.iter() {
+            assert!(parse_by_name(color.name().unwrap()) == Some(color));
+            assert!(color.name() == Some(name) || color.name().unwrap() < name);
+        }
+        assert!(rgb(0u8, 255u8, 255u8).name() == Some("aqua"));
+        assert!(rgb(128u8, 128u8, 128u8).name() == Some("gray"));
+        assert!(rgb(1u8, 2u8, 3u8).name() == None);
+        assert!(rgba(255u8, 0u8, 0u8, 0.5).name() == None);
+    }
+
+    static BENCH_NAMES: &'static [&'static str] = &[
+        "red", "White", "BLACK", "aliceblue", "yellowgreen", "LightGoldenRodYellow", "gray", "teal"
+    ];
+
+    #[bench]
+    fn bench_parse_by_name(bh: &mut BenchHarness) {
+        do bh.iter {
+            for name in BENCH_NAMES.iter() {
+                parse_by_name(*name);
+            }
+        }
+    }
+
+    #[bench]
+    fn bench_parse_by_name_static(bh: &mut BenchHarness) {
+        do bh.iter {
+            for name in BENCH_NAMES.iter() {
+                parse_by_name_static(*name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parsing_keywords() {
+        assert!(parse_color("transparent") == Some(rgba(0u8, 0u8, 0u8, 0.0)));
+        assert!(parse_color(" Transparent ") == Some(rgba(0u8, 0u8, 0u8, 0.0)));
+        assert!(parse_color("Window") == Some(rgb(255u8, 255u8, 255u8)));
+        assert!(parse_color("graytext") == Some(rgb(128u8, 128u8, 128u8)));
+        assert!(None == parse_color("currentColor"));
+
+        let mut palette = SystemColorPalette::default();
+        palette.set(SystemColorWindow, rgb(1u8, 2u8, 3u8));
+        assert!(palette.get(SystemColorWindow) == rgb(1u8, 2u8, 3u8));
+        assert!(parse_color_with_palette("window", &palette) == Some(rgb(1u8, 2u8, 3u8)));
+        assert!(parse_color_with_palette("GrayText", &palette)
+                == Some(SystemColorPalette::default().get(SystemColorGrayText)));
+        assert!(parse_color_with_palette("red", &palette) == Some(rgb(255u8, 0u8, 0u8)));
+    }
+
+    #[test]
+    fn test_serialization() {
+        assert!(rgb(255u8, 0u8, 0u8).to_css_string() == ~"rgb(255, 0, 0)");
+        assert!(rgba(1u8, 2u8, 3u8, 0.5).to_css_string() == ~"rgba(1, 2, 3, 0.5)");
+        assert!(rgba(1u8, 2u8, 3u8, 0.0).to_css_string() == ~"rgba(1, 2, 3, 0)");
+        assert!(rgba(1u8, 2u8, 3u8, 0.2).to_css_string() == ~"rgba(1, 2, 3, 0.2)");
+        assert!(rgba(1u8, 2u8, 3u8, 0.333).to_css_string() == ~"rgba(1, 2, 3, 0.333)");
+        assert!(rgba(1u8, 2u8, 3u8, 0.125).to_css_string() == ~"rgba(1, 2, 3, 0.125)");
+        assert!(rgb(0x12u8, 0xabu8, 0u8).to_hex() == ~"#12ab00");
+        assert!(rgba(0x12u8, 0xabu8, 0u8, 0.0).to_hex() == ~"#12ab0000");
+        assert!(rgb(254u8, 1u8, 0u8).nearest_name() == "red");
+        assert!(rgb(0u8, 0u8, 0u8).nearest_name() == "black");
+        assert!(hsl(120.0, 1.0, 0.25).nearest_name() == "green");
+        assert!(rgb(1u8, 2u8, 3u8).to_str() == rgb(1u8, 2u8, 3u8).to_css_string());
+    }
+
+    #[test]
+    fn test_serialization_round_trip() {
+        let colors = ~[
+            rgb(0u8, 0u8, 0u8),
+            rgb(255u8, 255u8, 255u8),
+            rgb(18u8, 52u8, 86u8),
+            rgba(18u8, 52u8, 86u8, 0.0),
+            rgba(18u8, 52u8, 86u8, 0.5),
+            rgba(200u8, 100u8, 50u8, 0.3),
+            rgba(200u8, 100u8, 50u8, 0x80 as f64 / 255.0),
+        ];
+        for color in colors.iter() {
+            let hex = parse_color(color.to_hex()).unwrap();
+            assert!(hex.red == color.red && hex.green == color.green && hex.blue == color.blue);
+            assert!(parse_color(color.to_css_string()).unwrap().to_css_string() == color.to_css_string());
+            assert!(parse_color(color.nearest_name()).is_some());
+        }
+        assert!(parse_color(rgb(18u8, 52u8, 86u8).to_css_string()) == Some(rgb(18u8, 52u8, 86u8)));
+        assert!(parse_color(rgba(18u8, 52u8, 86u8, 0.5).to_css_string()) == Some(rgba(18u8, 52u8, 86u8, 0.5)));
+        assert!(parse_color(rgba(1u8, 2u8, 3u8, 0x80 as f64 / 255.0).to_hex())
+                == Some(rgba(1u8, 2u8, 3u8, 0x80 as f64 / 255.0)));
+    }
+
     #[test]
     fn test_parsing_hsl() {
         assert!(parse_color("red").unwrap().eq(&parse_color("hsl(0,1,.5)").unwrap()));
@@ -3,12 +3,17 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use std::cast;
-use color::Color;
+use std::either::{Left, Right};
+use color::{Color, rgba};
 use select::SelectResults;
-use computed::ComputedStyle;
+use computed::{ComputedStyle, convert_net_unit_to_length_or_percent};
+use units::{Px, Viewport, LengthOrPercentageOrCalc, LPCLength, LPCPercentage};
 use n::h::CssHintLength;
 use n::u::float_to_css_fixed;
 use values::*;
+use property::*;
+use damage::{RestyleDamage, NO_DAMAGE};
+use serialize::ToCss;
 use n;
 
 struct CompleteFontSizeComputer {
@@ -16,51 +21,66 @@ struct CompleteFontSizeComputer {
     child_computed: CompleteStyle<'static>,
 }
 
+impl CompleteFontSizeComputer {
+    /**
+    Resolves a font size to px. Percentages and font-relative lengths refer to
+    the parent's font size (CSS 2.1, Section 15.7).
+    */
+    fn font_size_px(&self, unit: &n::t::CssUnit, parent_size: f64) -> f64 {
+        let size = match convert_net_unit_to_length_or_percent(unit.clone()) {
+            Left(length) => LPCLength(length),
+            Right(percent) => LPCPercentage(percent)
+        };
+        size.to_px(parent_size, parent_size, self.parent_computed.root_font_size,
+                   &self.parent_computed.viewport)
+    }
+}
+
 impl n::c::FontSizeComputer for CompleteFontSizeComputer {
     fn compute_font_size(&self, parent: &Option<n::h::CssHint>, child: &n::h::CssHint)
                          -> n::h::CssHint {
-        match *child {
-            // Handle relative units
-            CssHintLength(n::t::CssUnitEm(child_em)) => {
-                match *parent {
-                    Some(CssHintLength(parent_unit)) => {
-                        // CSS3 Values 5.1.1: Multiply parent unit by child unit.
-                        let mut new_value = n::u::css_fixed_to_float(parent_unit.to_css_fixed());
-                        new_value *= n::u::css_fixed_to_float(child_em);
-                        let unit = parent_unit.modify(n::u::float_to_css_fixed(new_value));
-                        CssHintLength(unit)
-                    }
-                    _ => n::h::CssHintLength(n::t::CssUnitPx(float_to_css_fixed(16.0))),
-                }
+        // The parent's size was computed to px here too, unless it's the root
+        let parent_size = match *parent {
+            Some(CssHintLength(ref parent_unit)) => {
+                self.font_size_px(parent_unit, DEFAULT_FONT_SIZE)
             }
-            CssHintLength(n::t::CssUnitPct(child_pct)) => {
-                match *parent {
-                    Some(CssHintLength(parent_unit)) => {
-                        // CSS3 Values 5.1.1: Multiply parent unit by child unit.
-                        let mut new_value = n::u::css_fixed_to_float(parent_unit.to_css_fixed());
-                        new_value *= n::u::css_fixed_to_float(child_pct) / 100.0;
-                        let unit = parent_unit.modify(n::u::float_to_css_fixed(new_value));
-                        CssHintLength(unit)
-                    }
-                    _ => n::h::CssHintLength(n::t::CssUnitPx(float_to_css_fixed(16.0))),
-                }
-            }
-            // Pass through absolute units
-            CssHintLength(unit) => CssHintLength(unit),
-            _ => n::h::CssHintLength(n::t::CssUnitPx(float_to_css_fixed(16.0))),
-        }
+            _ => DEFAULT_FONT_SIZE
+        };
+        let size = match *child {
+            CssHintLength(ref unit) => self.font_size_px(unit, parent_size),
+            _ => DEFAULT_FONT_SIZE
+        };
+        CssHintLength(n::t::CssUnitPx(float_to_css_fixed(size)))
     }
 }
 
+// The initial font-size, medium
+static DEFAULT_FONT_SIZE: f64 = 16.0;
+
+// The viewport used when none is given
+static DEFAULT_VIEWPORT: Viewport = Viewport { width: 800.0, height: 600.0 };
+
 pub struct CompleteSelectResults {
-    inner: SelectResults
+    inner: SelectResults,
+    // What 'rem' and viewport-relative lengths resolve against
+    root_font_size: f64,
+    viewport: Viewport
 }
 
 impl<'self> CompleteSelectResults {
     pub fn new_root(root: SelectResults) -> CompleteSelectResults {
-        CompleteSelectResults {
-            inner: root
-        }
+        CompleteSelectResults::new_root_in_viewport(root, DEFAULT_VIEWPORT)
+    }
+
+    pub fn new_root_in_viewport(root: SelectResults, viewport: Viewport) -> CompleteSelectResults {
+        let mut results = CompleteSelectResults {
+            inner: root,
+            root_font_size: DEFAULT_FONT_SIZE,
+            viewport: viewport
+        };
+        let root_font_size = results.computed_style().font_size_px();
+        results.root_font_size = root_font_size;
+        results
     }
 
     pub fn new_from_parent(parent: &CompleteSelectResults,
@@ -87,20 +107,26 @@ impl<'self> CompleteSelectResults {
         }
 
         CompleteSelectResults {
-            inner: child
+            inner: child,
+            root_font_size: parent.root_font_size,
+            viewport: parent.viewport.clone()
         }
     }
 
     #[inline(always)]
     pub fn computed_style(&'self self) -> CompleteStyle<'self> {
         CompleteStyle {
-            inner: self.inner.computed_style()
+            inner: self.inner.computed_style(),
+            root_font_size: self.root_font_size,
+            viewport: self.viewport.clone()
         }
     }
 }
 
 pub struct CompleteStyle<'self> {
-    inner: ComputedStyle<'self>
+    inner: ComputedStyle<'self>,
+    root_font_size: f64,
+    viewport: Viewport
 }
 
 impl<'self> CompleteStyle<'self> {
@@ -189,22 +215,31 @@ impl<'self> CompleteStyle<'self> {
 
     #[inline(always)]
     pub fn border_top_color(&self) -> Color {
-        strip(self.inner.border_top_color())
+        self.resolve_border_color(strip(self.inner.border_top_color()))
     }
 
     #[inline(always)]
     pub fn border_right_color(&self) -> Color {
-        strip(self.inner.border_right_color())
+        self.resolve_border_color(strip(self.inner.border_right_color()))
     }
 
     #[inline(always)]
     pub fn border_bottom_color(&self) -> Color {
-        strip(self.inner.border_bottom_color())
+        self.resolve_border_color(strip(self.inner.border_bottom_color()))
     }
 
     #[inline(always)]
     pub fn border_left_color(&self) -> Color {
-        strip(self.inner.border_left_color())
+        self.resolve_border_color(strip(self.inner.border_left_color()))
+    }
+
+    #[inline(always)]
+    fn resolve_border_color(&self, color: CSSBorderColor) -> Color {
+        match color {
+            CSSBorderColorColor(color) => color,
+            CSSBorderColorTransparent => rgba(0, 0, 0, 0.0),
+            CSSBorderColorCurrentColor => self.color()
+        }
     }
 
     // CSS 2.1, Section 9 - Visual formatting model
@@ -229,6 +264,26 @@ impl<'self> CompleteStyle<'self> {
         strip(self.inner.clear())
     }
 
+    #[inline(always)]
+    pub fn top(&self) -> CSSTop {
+        strip(self.inner.top())
+    }
+
+    #[inline(always)]
+    pub fn right(&self) -> CSSRight {
+        strip(self.inner.right())
+    }
+
+    #[inline(always)]
+    pub fn bottom(&self) -> CSSBottom {
+        strip(self.inner.bottom())
+    }
+
+    #[inline(always)]
+    pub fn left(&self) -> CSSLeft {
+        strip(self.inner.left())
+    }
+
     // CSS 2.1, Section 10 - Visual formatting model details
 
     #[inline(always)]
@@ -253,15 +308,96 @@ impl<'self> CompleteStyle<'self> {
 
     // CSS 2.1, Section 11 - Visual effects
 
+    #[inline(always)]
+    pub fn z_index(&self) -> CSSZIndex {
+        strip(self.inner.z_index())
+    }
+
+    #[inline(always)]
+    pub fn clip(&self) -> CSSClip {
+        strip(self.inner.clip())
+    }
+
+    // CSS Color Module Level 3, Section 3.2 - Transparency
+
+    #[inline(always)]
+    pub fn opacity(&self) -> f64 {
+        strip(self.inner.opacity())
+    }
+
     // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
 
+    #[inline(always)]
+    pub fn content(&self) -> CSSContent {
+        strip(self.inner.content())
+    }
+
+    #[inline(always)]
+    pub fn counter_reset(&self) -> CSSCounterReset {
+        strip(self.inner.counter_reset())
+    }
+
+    #[inline(always)]
+    pub fn counter_increment(&self) -> CSSCounterIncrement {
+        strip(self.inner.counter_increment())
+    }
+
+    #[inline(always)]
+    pub fn quotes(&self) -> CSSQuotes {
+        strip(self.inner.quotes())
+    }
+
+    #[inline(always)]
+    pub fn list_style_type(&self) -> CSSListStyleType {
+        strip(self.inner.list_style_type())
+    }
+
+    #[inline(always)]
+    pub fn list_style_position(&self) -> CSSListStylePosition {
+        strip(self.inner.list_style_position())
+    }
+
+    #[inline(always)]
+    pub fn list_style_image(&self) -> CSSListStyleImage {
+        strip(self.inner.list_style_image())
+    }
+
     // CSS 2.1, Section 13 - Paged media
 
+    #[inline(always)]
+    pub fn page_break_before(&self) -> CSSPageBreakBefore {
+        strip(self.inner.page_break_before())
+    }
+
+    #[inline(always)]
+    pub fn page_break_after(&self) -> CSSPageBreakAfter {
+        strip(self.inner.page_break_after())
+    }
+
+    #[inline(always)]
+    pub fn page_break_inside(&self) -> CSSPageBreakInside {
+        strip(self.inner.page_break_inside())
+    }
+
+    #[inline(always)]
+    pub fn orphans(&self) -> int {
+        strip(self.inner.orphans())
+    }
+
+    #[inline(always)]
+    pub fn widows(&self) -> int {
+        strip(self.inner.widows())
+    }
+
     // CSS 2.1, Section 14 - Colors and Backgrounds
 
     #[inline(always)]
     pub fn background_color(&self) -> Color {
-        strip(self.inner.background_color())
+        match strip(self.inner.background_color()) {
+            CSSBackgroundColorColor(color) => color,
+            CSSBackgroundColorTransparent => rgba(0, 0, 0, 0.0),
+            CSSBackgroundColorCurrentColor => self.color()
+        }
     }
 
     #[inline(always)]
@@ -288,7 +424,26 @@ impl<'self> CompleteStyle<'self> {
 
     #[inline(always)]
     pub fn font_size(&self) -> CSSFontSize {
-        strip(self.inner.font_size())
+        // Only the root is left with relative sizes, relative to the initial font size
+        let to_px = |size: LengthOrPercentageOrCalc| {
+            let px = size.to_px(DEFAULT_FONT_SIZE, DEFAULT_FONT_SIZE, DEFAULT_FONT_SIZE,
+                                &self.viewport);
+            CSSFontSizeLength(Px(px))
+        };
+        match strip(self.inner.font_size()) {
+            CSSFontSizeLength(Px(px)) => CSSFontSizeLength(Px(px)),
+            CSSFontSizeLength(length) => to_px(LPCLength(length)),
+            CSSFontSizePercentage(percentage) => to_px(LPCPercentage(percentage)),
+            font_size => font_size
+        }
+    }
+
+    /** The font size in px, or the initial font size if it isn't a px length */
+    pub fn font_size_px(&self) -> f64 {
+        match self.font_size() {
+            CSSFontSizeLength(Px(px)) => px,
+            _ => DEFAULT_FONT_SIZE
+        }
     }
 
     #[inline(always)]
@@ -307,6 +462,134 @@ impl<'self> CompleteStyle<'self> {
 
     // CSS 2.1, Section 18 - User interface
 
+    #[inline(always)]
+    pub fn cursor(&self) -> CSSCursor {
+        strip(self.inner.cursor())
+    }
+
+    #[inline(always)]
+    pub fn outline_width(&self) -> CSSBorderWidth {
+        strip(self.inner.outline_width())
+    }
+
+    #[inline(always)]
+    pub fn outline_style(&self) -> CSSBorderStyle {
+        strip(self.inner.outline_style())
+    }
+
+    #[inline(always)]
+    pub fn outline_color(&self) -> CSSOutlineColor {
+        strip(self.inner.outline_color())
+    }
+
+    // Generic access
+
+    /**
+    Fetches any property by its `Property` name, as `ComputedStyle::get` does.
+    Border and background colors come resolved, as `Color` values.
+    */
+    pub fn get(&self, property: Property) -> PropertyValue {
+        match property {
+            PropMarginTop => PropertyValueMargin(self.margin_top()),
+            PropMarginRight => PropertyValueMargin(self.margin_right()),
+            PropMarginBottom => PropertyValueMargin(self.margin_bottom()),
+            PropMarginLeft => PropertyValueMargin(self.margin_left()),
+            PropPaddingTop => PropertyValuePadding(self.padding_top()),
+            PropPaddingRight => PropertyValuePadding(self.padding_right()),
+            PropPaddingBottom => PropertyValuePadding(self.padding_bottom()),
+            PropPaddingLeft => PropertyValuePadding(self.padding_left()),
+            PropBorderTopStyle => PropertyValueBorderStyle(self.border_top_style()),
+            PropBorderRightStyle => PropertyValueBorderStyle(self.border_right_style()),
+            PropBorderBottomStyle => PropertyValueBorderStyle(self.border_bottom_style()),
+            PropBorderLeftStyle => PropertyValueBorderStyle(self.border_left_style()),
+            PropBorderTopWidth => PropertyValueBorderWidth(self.border_top_width()),
+            PropBorderRightWidth => PropertyValueBorderWidth(self.border_right_width()),
+            PropBorderBottomWidth => PropertyValueBorderWidth(self.border_bottom_width()),
+            PropBorderLeftWidth => PropertyValueBorderWidth(self.border_left_width()),
+            PropBorderTopColor => {
+                PropertyValueBorderColor(CSSBorderColorColor(self.border_top_color()))
+            }
+            PropBorderRightColor => {
+                PropertyValueBorderColor(CSSBorderColorColor(self.border_right_color()))
+            }
+            PropBorderBottomColor => {
+                PropertyValueBorderColor(CSSBorderColorColor(self.border_bottom_color()))
+            }
+            PropBorderLeftColor => {
+                PropertyValueBorderColor(CSSBorderColorColor(self.border_left_color()))
+            }
+            PropDisplay => PropertyValueDisplay(self.display(false)),
+            PropPosition => PropertyValuePosition(self.position()),
+            PropFloat => PropertyValueFloat(self.float()),
+            PropClear => PropertyValueClear(self.clear()),
+            PropTop => PropertyValueTop(self.top()),
+            PropRight => PropertyValueRight(self.right()),
+            PropBottom => PropertyValueBottom(self.bottom()),
+            PropLeft => PropertyValueLeft(self.left()),
+            PropWidth => PropertyValueWidth(self.width()),
+            PropHeight => PropertyValueHeight(self.height()),
+            PropLineHeight => PropertyValueLineHeight(self.line_height()),
+            PropVerticalAlign => PropertyValueVerticalAlign(self.vertical_align()),
+            PropZIndex => PropertyValueZIndex(self.z_index()),
+            PropClip => PropertyValueClip(self.clip()),
+            PropOpacity => PropertyValueOpacity(self.opacity()),
+            PropContent => PropertyValueContent(self.content()),
+            PropCounterReset => PropertyValueCounterReset(self.counter_reset()),
+            PropCounterIncrement => PropertyValueCounterIncrement(self.counter_increment()),
+            PropQuotes => PropertyValueQuotes(self.quotes()),
+            PropListStyleType => PropertyValueListStyleType(self.list_style_type()),
+            PropListStylePosition => PropertyValueListStylePosition(self.list_style_position()),
+            PropListStyleImage => PropertyValueListStyleImage(self.list_style_image()),
+            PropPageBreakBefore => PropertyValuePageBreakBefore(self.page_break_before()),
+            PropPageBreakAfter => PropertyValuePageBreakAfter(self.page_break_after()),
+            PropPageBreakInside => PropertyValuePageBreakInside(self.page_break_inside()),
+            PropOrphans => PropertyValueOrphans(self.orphans()),
+            PropWidows => PropertyValueWidows(self.widows()),
+            PropBackgroundColor => {
+                PropertyValueBackgroundColor(CSSBackgroundColorColor(self.background_color()))
+            }
+            PropColor => PropertyValueColor(self.color()),
+            PropFontFamily => PropertyValueFontFamily(self.font_family()),
+            PropFontStyle => PropertyValueFontStyle(self.font_style()),
+            PropFontWeight => PropertyValueFontWeight(self.font_weight()),
+            PropFontSize => PropertyValueFontSize(self.font_size()),
+            PropTextAlign => PropertyValueTextAlign(self.text_align()),
+            PropTextDecoration => PropertyValueTextDecoration(self.text_decoration()),
+            PropCursor => PropertyValueCursor(self.cursor()),
+            PropOutlineWidth => PropertyValueBorderWidth(self.outline_width()),
+            PropOutlineStyle => PropertyValueBorderStyle(self.outline_style()),
+            PropOutlineColor => PropertyValueOutlineColor(self.outline_color())
+        }
+    }
+
+    /**
+    Classifies the differences from `other` by the work they cause a layout
+    engine, according to `Property::damage`.
+    */
+    pub fn diff(&self, other: &CompleteStyle) -> RestyleDamage {
+        let mut damage = NO_DAMAGE;
+        for &property in Property::all().iter() {
+            if damage.contains(property.damage()) {
+                loop;
+            }
+            if self.get(property) != other.get(property) {
+                damage.insert(property.damage());
+            }
+        }
+        damage
+    }
+
+    /**
+    Serializes every property as a declaration block, in `Property::all()`
+    order, for getComputedStyle and style snapshots.
+    */
+    pub fn to_css_text(&self) -> ~str {
+        let declarations = do Property::all().map |&property| {
+            fmt!("%s: %s;", property.name(), self.get(property).to_css())
+        };
+        declarations.connect(" ")
+    }
+
 }
 
 #[inline]
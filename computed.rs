@@ -3,11 +3,17 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use color::{Color, rgba};
-use units::{Length, Px, Em};
+use extra::url::Url;
+use units::{Length, Px, Em, Ex, Deg, Grad, Rad, Ms, S, Hz, KHz};
+use units::{Dimension, DimensionLength, DimensionPercentage, DimensionAngle, DimensionTime};
+use units::DimensionFrequency;
 use netsurfcss::util::css_fixed_to_float;
 use std::either::{Either, Left, Right};
+use wapcaplet::LwcString;
 use n;
 use values::*;
+use property::*;
+use serialize::ToCss;
 
 pub struct ComputedStyle<'self> {
     inner: n::c::CssComputedStyle<'self>
@@ -98,23 +104,23 @@ impl<'self> ComputedStyle<'self> {
     }
 
     #[inline]
-    pub fn border_top_color(&self) -> CSSValue<Color> {
-        convert_net_color_value(self.inner.border_top_color())
+    pub fn border_top_color(&self) -> CSSValue<CSSBorderColor> {
+        convert_net_border_color_value(self.inner.border_top_color())
     }
 
     #[inline]
-    pub fn border_right_color(&self) -> CSSValue<Color> {
-        convert_net_color_value(self.inner.border_right_color())
+    pub fn border_right_color(&self) -> CSSValue<CSSBorderColor> {
+        convert_net_border_color_value(self.inner.border_right_color())
     }
 
     #[inline]
-    pub fn border_bottom_color(&self) -> CSSValue<Color> {
-        convert_net_color_value(self.inner.border_bottom_color())
+    pub fn border_bottom_color(&self) -> CSSValue<CSSBorderColor> {
+        convert_net_border_color_value(self.inner.border_bottom_color())
     }
 
     #[inline]
-    pub fn border_left_color(&self) -> CSSValue<Color> {
-        convert_net_color_value(self.inner.border_left_color())
+    pub fn border_left_color(&self) -> CSSValue<CSSBorderColor> {
+        convert_net_border_color_value(self.inner.border_left_color())
     }
 
     // CSS 2.1, Section 9 - Visual formatting model
@@ -139,6 +145,26 @@ impl<'self> ComputedStyle<'self> {
         convert_net_clear_value(self.inner.clear())
     }
 
+    #[inline]
+    pub fn top(&self) -> CSSValue<CSSTop> {
+        convert_net_top_value(self.inner.top())
+    }
+
+    #[inline]
+    pub fn right(&self) -> CSSValue<CSSRight> {
+        convert_net_right_value(self.inner.right())
+    }
+
+    #[inline]
+    pub fn bottom(&self) -> CSSValue<CSSBottom> {
+        convert_net_bottom_value(self.inner.bottom())
+    }
+
+    #[inline]
+    pub fn left(&self) -> CSSValue<CSSLeft> {
+        convert_net_left_value(self.inner.left())
+    }
+
     // CSS 2.1, Section 10 - Visual formatting model details
 
     #[inline]
@@ -163,15 +189,92 @@ impl<'self> ComputedStyle<'self> {
 
     // CSS 2.1, Section 11 - Visual effects
 
+    #[inline]
+    pub fn z_index(&self) -> CSSValue<CSSZIndex> {
+        convert_net_z_index_value(self.inner.z_index())
+    }
+
+    #[inline]
+    pub fn clip(&self) -> CSSValue<CSSClip> {
+        convert_net_clip_value(self.inner.clip())
+    }
+
+    // CSS Color Module Level 3, Section 3.2 - Transparency
+
+    #[inline]
+    pub fn opacity(&self) -> CSSValue<f64> {
+        convert_net_opacity_value(self.inner.opacity())
+    }
+
     // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
 
+    #[inline]
+    pub fn content(&self) -> CSSValue<CSSContent> {
+        convert_net_content_value(self.inner.content())
+    }
+
+    #[inline]
+    pub fn counter_reset(&self) -> CSSValue<CSSCounterReset> {
+        convert_net_counter_reset_value(self.inner.counter_reset())
+    }
+
+    #[inline]
+    pub fn counter_increment(&self) -> CSSValue<CSSCounterIncrement> {
+        convert_net_counter_increment_value(self.inner.counter_increment())
+    }
+
+    #[inline]
+    pub fn quotes(&self) -> CSSValue<CSSQuotes> {
+        convert_net_quotes_value(self.inner.quotes())
+    }
+
+    #[inline]
+    pub fn list_style_type(&self) -> CSSValue<CSSListStyleType> {
+        convert_net_list_style_type_value(self.inner.list_style_type())
+    }
+
+    #[inline]
+    pub fn list_style_position(&self) -> CSSValue<CSSListStylePosition> {
+        convert_net_list_style_position_value(self.inner.list_style_position())
+    }
+
+    #[inline]
+    pub fn list_style_image(&self) -> CSSValue<CSSListStyleImage> {
+        convert_net_list_style_image_value(self.inner.list_style_image())
+    }
+
     // CSS 2.1, Section 13 - Paged media
 
+    #[inline]
+    pub fn page_break_before(&self) -> CSSValue<CSSPageBreakBefore> {
+        convert_net_page_break_before_value(self.inner.page_break_before())
+    }
+
+    #[inline]
+    pub fn page_break_after(&self) -> CSSValue<CSSPageBreakAfter> {
+        convert_net_page_break_after_value(self.inner.page_break_after())
+    }
+
+    #[inline]
+    pub fn page_break_inside(&self) -> CSSValue<CSSPageBreakInside> {
+        convert_net_page_break_inside_value(self.inner.page_break_inside())
+    }
+
+    #[inline]
+    pub fn orphans(&self) -> CSSValue<int> {
+        convert_net_orphans_value(self.inner.orphans())
+    }
+
+    #[inline]
+    pub fn widows(&self) -> CSSValue<int> {
+        convert_net_widows_value(self.inner.widows())
+    }
+
     // CSS 2.1, Section 14 - Colors and Backgrounds
 
     #[inline]
-    pub fn background_color(&self) -> CSSValue<Color> {
-        convert_net_color_value(self.inner.background_color())
+    pub fn background_color(&self) -> CSSValue<CSSBackgroundColor> {
+        convert_net_background_color_value(self.inner.background_color())
     }
 
     #[inline]
@@ -217,6 +320,109 @@ impl<'self> ComputedStyle<'self> {
 
     // CSS 2.1, Section 18 - User interface
 
+    #[inline]
+    pub fn cursor(&self) -> CSSValue<CSSCursor> {
+        convert_net_cursor_value(self.inner.cursor())
+    }
+
+    /** 'outline-width' accepts the same values as 'border-width' */
+    #[inline]
+    pub fn outline_width(&self) -> CSSValue<CSSBorderWidth> {
+        convert_net_border_width(self.inner.outline_width())
+    }
+
+    /** 'outline-style' accepts the same values as 'border-style', except 'hidden' */
+    #[inline]
+    pub fn outline_style(&self) -> CSSValue<CSSBorderStyle> {
+        convert_net_border_style(self.inner.outline_style())
+    }
+
+    #[inline]
+    pub fn outline_color(&self) -> CSSValue<CSSOutlineColor> {
+        convert_net_outline_color_value(self.inner.outline_color())
+    }
+
+    // Generic access
+
+    /**
+    Fetches any property by its `Property` name. 'display' is computed as for
+    an element other than the root.
+    */
+    pub fn get(&self, property: Property) -> CSSValue<PropertyValue> {
+        match property {
+            PropMarginTop => wrap(self.margin_top(), PropertyValueMargin),
+            PropMarginRight => wrap(self.margin_right(), PropertyValueMargin),
+            PropMarginBottom => wrap(self.margin_bottom(), PropertyValueMargin),
+            PropMarginLeft => wrap(self.margin_left(), PropertyValueMargin),
+            PropPaddingTop => wrap(self.padding_top(), PropertyValuePadding),
+            PropPaddingRight => wrap(self.padding_right(), PropertyValuePadding),
+            PropPaddingBottom => wrap(self.padding_bottom(), PropertyValuePadding),
+            PropPaddingLeft => wrap(self.padding_left(), PropertyValuePadding),
+            PropBorderTopStyle => wrap(self.border_top_style(), PropertyValueBorderStyle),
+            PropBorderRightStyle => wrap(self.border_right_style(), PropertyValueBorderStyle),
+            PropBorderBottomStyle => wrap(self.border_bottom_style(), PropertyValueBorderStyle),
+            PropBorderLeftStyle => wrap(self.border_left_style(), PropertyValueBorderStyle),
+            PropBorderTopWidth => wrap(self.border_top_width(), PropertyValueBorderWidth),
+            PropBorderRightWidth => wrap(self.border_right_width(), PropertyValueBorderWidth),
+            PropBorderBottomWidth => wrap(self.border_bottom_width(), PropertyValueBorderWidth),
+            PropBorderLeftWidth => wrap(self.border_left_width(), PropertyValueBorderWidth),
+            PropBorderTopColor => wrap(self.border_top_color(), PropertyValueBorderColor),
+            PropBorderRightColor => wrap(self.border_right_color(), PropertyValueBorderColor),
+            PropBorderBottomColor => wrap(self.border_bottom_color(), PropertyValueBorderColor),
+            PropBorderLeftColor => wrap(self.border_left_color(), PropertyValueBorderColor),
+            PropDisplay => wrap(self.display(false), PropertyValueDisplay),
+            PropPosition => wrap(self.position(), PropertyValuePosition),
+            PropFloat => wrap(self.float(), PropertyValueFloat),
+            PropClear => wrap(self.clear(), PropertyValueClear),
+            PropTop => wrap(self.top(), PropertyValueTop),
+            PropRight => wrap(self.right(), PropertyValueRight),
+            PropBottom => wrap(self.bottom(), PropertyValueBottom),
+            PropLeft => wrap(self.left(), PropertyValueLeft),
+            PropWidth => wrap(self.width(), PropertyValueWidth),
+            PropHeight => wrap(self.height(), PropertyValueHeight),
+            PropLineHeight => wrap(self.line_height(), PropertyValueLineHeight),
+            PropVerticalAlign => wrap(self.vertical_align(), PropertyValueVerticalAlign),
+            PropZIndex => wrap(self.z_index(), PropertyValueZIndex),
+            PropClip => wrap(self.clip(), PropertyValueClip),
+            PropOpacity => wrap(self.opacity(), PropertyValueOpacity),
+            PropContent => wrap(self.content(), PropertyValueContent),
+            PropCounterReset => wrap(self.counter_reset(), PropertyValueCounterReset),
+            PropCounterIncrement => wrap(self.counter_increment(), PropertyValueCounterIncrement),
+            PropQuotes => wrap(self.quotes(), PropertyValueQuotes),
+            PropListStyleType => wrap(self.list_style_type(), PropertyValueListStyleType),
+            PropListStylePosition => wrap(self.list_style_position(), PropertyValueListStylePosition),
+            PropListStyleImage => wrap(self.list_style_image(), PropertyValueListStyleImage),
+            PropPageBreakBefore => wrap(self.page_break_before(), PropertyValuePageBreakBefore),
+            PropPageBreakAfter => wrap(self.page_break_after(), PropertyValuePageBreakAfter),
+            PropPageBreakInside => wrap(self.page_break_inside(), PropertyValuePageBreakInside),
+            PropOrphans => wrap(self.orphans(), PropertyValueOrphans),
+            PropWidows => wrap(self.widows(), PropertyValueWidows),
+            PropBackgroundColor => wrap(self.background_color(), PropertyValueBackgroundColor),
+            PropColor => wrap(self.color(), PropertyValueColor),
+            PropFontFamily => wrap(self.font_family(), PropertyValueFontFamily),
+            PropFontStyle => wrap(self.font_style(), PropertyValueFontStyle),
+            PropFontWeight => wrap(self.font_weight(), PropertyValueFontWeight),
+            PropFontSize => wrap(self.font_size(), PropertyValueFontSize),
+            PropTextAlign => wrap(self.text_align(), PropertyValueTextAlign),
+            PropTextDecoration => wrap(self.text_decoration(), PropertyValueTextDecoration),
+            PropCursor => wrap(self.cursor(), PropertyValueCursor),
+            PropOutlineWidth => wrap(self.outline_width(), PropertyValueBorderWidth),
+            PropOutlineStyle => wrap(self.outline_style(), PropertyValueBorderStyle),
+            PropOutlineColor => wrap(self.outline_color(), PropertyValueOutlineColor)
+        }
+    }
+
+    /**
+    Serializes every property as a declaration block, in `Property::all()`
+    order, for getComputedStyle and style snapshots.
+    */
+    pub fn to_css_text(&self) -> ~str {
+        let declarations = do Property::all().map |&property| {
+            fmt!("%s: %s;", property.name(), self.get(property).to_css())
+        };
+        declarations.connect(" ")
+    }
+
 }
 
 #[inline(always)]
@@ -224,14 +430,41 @@ fn convert_net_color(color: n::t::CssColor) -> Color {
     rgba(color.r, color.g, color.b, (color.a as f64) / 255.0)
 }
 
+#[inline(always)]
+fn is_transparent(color: &n::t::CssColor) -> bool {
+    color.r == 0 && color.g == 0 && color.b == 0 && color.a == 0
+}
+
 #[inline(always)]
 fn convert_net_color_value(color: n::v::CssColorValue) -> CSSValue<Color> {
     match color {
         n::v::CssColorInherit => Inherit,
+        // CSS Color 3, 4.5: 'color: currentColor' is the same as 'color: inherit'
+        n::v::CssColorCurrentColor => Inherit,
         n::v::CssColorColor(v) => Specified(convert_net_color(v))
     }
 }
 
+#[inline(always)]
+fn convert_net_border_color_value(color: n::v::CssColorValue) -> CSSValue<CSSBorderColor> {
+    match color {
+        n::v::CssColorInherit => Inherit,
+        n::v::CssColorCurrentColor => Specified(CSSBorderColorCurrentColor),
+        n::v::CssColorColor(v) if is_transparent(&v) => Specified(CSSBorderColorTransparent),
+        n::v::CssColorColor(v) => Specified(CSSBorderColorColor(convert_net_color(v)))
+    }
+}
+
+#[inline(always)]
+fn convert_net_background_color_value(color: n::v::CssColorValue) -> CSSValue<CSSBackgroundColor> {
+    match color {
+        n::v::CssColorInherit => Inherit,
+        n::v::CssColorCurrentColor => Specified(CSSBackgroundColorCurrentColor),
+        n::v::CssColorColor(v) if is_transparent(&v) => Specified(CSSBackgroundColorTransparent),
+        n::v::CssColorColor(v) => Specified(CSSBackgroundColorColor(convert_net_color(v)))
+    }
+}
+
 #[inline(always)]
 fn convert_net_border_style(style: n::v::CssBorderStyleValue) -> CSSValue<CSSBorderStyle> {
     match style {
@@ -265,8 +498,7 @@ fn convert_net_margin(margin: n::v::CssMarginValue) -> CSSValue<CSSMargin> {
     match margin {
         n::v::CssMarginInherit => Inherit,
         n::v::CssMarginSet(value) => {
-            let length = convert_net_unit_to_length_or_percent(value);
-            match length {
+            match convert_net_unit_to_length_or_percent(value) {
                 Left(abs) => Specified(CSSMarginLength(abs)),
                 Right(percent) => Specified(CSSMarginPercentage(percent))
             }
@@ -280,8 +512,7 @@ fn convert_net_padding(padding: n::v::CssPaddingValue) -> CSSValue<CSSPadding> {
     match padding {
         n::v::CssPaddingInherit => Inherit,
         n::v::CssPaddingSet(value) => {
-            let length = convert_net_unit_to_length_or_percent(value);
-            match length {
+            match convert_net_unit_to_length_or_percent(value) {
                 Left(abs) => Specified(CSSPaddingLength(abs)),
                 Right(percent) => Specified(CSSPaddingPercentage(percent))
             }
@@ -294,8 +525,7 @@ fn convert_net_width_value(value: n::v::CssWidthValue) -> CSSValue<CSSWidth> {
     match value {
         n::v::CssWidthInherit => Inherit,
         n::v::CssWidthSet(value) => {
-            let length = convert_net_unit_to_length_or_percent(value);
-            match length {
+            match convert_net_unit_to_length_or_percent(value) {
                 Left(abs) => Specified(CSSWidthLength(abs)),
                 Right(percent) => Specified(CSSWidthPercentage(percent))
             }
@@ -374,6 +604,62 @@ fn convert_net_position_value(value: n::v::CssPositionValue) -> CSSValue<CSSPosi
     }
 }
 
+#[inline(always)]
+fn convert_net_top_value(value: n::v::CssTopValue) -> CSSValue<CSSTop> {
+    match value {
+        n::v::CssTopInherit => Inherit,
+        n::v::CssTopSet(value) => {
+            match convert_net_unit_to_length_or_percent(value) {
+                Left(abs) => Specified(CSSTopLength(abs)),
+                Right(percent) => Specified(CSSTopPercentage(percent))
+            }
+        }
+        n::v::CssTopAuto => Specified(CSSTopAuto)
+    }
+}
+
+#[inline(always)]
+fn convert_net_right_value(value: n::v::CssRightValue) -> CSSValue<CSSRight> {
+    match value {
+        n::v::CssRightInherit => Inherit,
+        n::v::CssRightSet(value) => {
+            match convert_net_unit_to_length_or_percent(value) {
+                Left(abs) => Specified(CSSRightLength(abs)),
+                Right(percent) => Specified(CSSRightPercentage(percent))
+            }
+        }
+        n::v::CssRightAuto => Specified(CSSRightAuto)
+    }
+}
+
+#[inline(always)]
+fn convert_net_bottom_value(value: n::v::CssBottomValue) -> CSSValue<CSSBottom> {
+    match value {
+        n::v::CssBottomInherit => Inherit,
+        n::v::CssBottomSet(value) => {
+            match convert_net_unit_to_length_or_percent(value) {
+                Left(abs) => Specified(CSSBottomLength(abs)),
+                Right(percent) => Specified(CSSBottomPercentage(percent))
+            }
+        }
+        n::v::CssBottomAuto => Specified(CSSBottomAuto)
+    }
+}
+
+#[inline(always)]
+fn convert_net_left_value(value: n::v::CssLeftValue) -> CSSValue<CSSLeft> {
+    match value {
+        n::v::CssLeftInherit => Inherit,
+        n::v::CssLeftSet(value) => {
+            match convert_net_unit_to_length_or_percent(value) {
+                Left(abs) => Specified(CSSLeftLength(abs)),
+                Right(percent) => Specified(CSSLeftPercentage(percent))
+            }
+        }
+        n::v::CssLeftAuto => Specified(CSSLeftAuto)
+    }
+}
+
 #[inline(always)]
 fn convert_net_font_family_value(value: n::v::CssFontFamilyValue) -> CSSValue<~[CSSFontFamily]> {
     use units::{Serif, SansSerif, Cursive, Fantasy, Monospace};
@@ -447,15 +733,18 @@ fn convert_net_font_weight_value(value: n::v::CssFontWeightValue) -> CSSValue<CS
 fn convert_net_text_align_value(value: n::v::CssTextAlignValue) -> CSSValue<CSSTextAlign> {
     match value {
         n::v::CssTextAlignInherit => Inherit,
-        n::v::CssTextAlignInheritIfNonMagic => unimpl("inherit if non-magic? wtf?"),
+        // libcss's UA value for `th`: the parent's alignment, unless that came from an
+        // HTML `align` attribute (one of the `Libcss` values below); composition resolves it
+        n::v::CssTextAlignInheritIfNonMagic => Inherit,
         n::v::CssTextAlignLeft => Specified(CSSTextAlignLeft),
         n::v::CssTextAlignRight => Specified(CSSTextAlignRight),
         n::v::CssTextAlignCenter => Specified(CSSTextAlignCenter),
         n::v::CssTextAlignJustify => Specified(CSSTextAlignJustify),
         n::v::CssTextAlignDefault => Specified(CSSTextAlignLeft),
-        n::v::CssTextAlignLibcssLeft => unimpl("text-align libcss left"),
-        n::v::CssTextAlignLibcssCenter => unimpl("text-align libcss center"),
-        n::v::CssTextAlignLibcssRight => unimpl("text-align libcss right"),
+        // The alignments of HTML `align` attributes, which only differ in how they inherit
+        n::v::CssTextAlignLibcssLeft => Specified(CSSTextAlignLeft),
+        n::v::CssTextAlignLibcssCenter => Specified(CSSTextAlignCenter),
+        n::v::CssTextAlignLibcssRight => Specified(CSSTextAlignRight),
     }
 }
 
@@ -507,6 +796,301 @@ fn convert_net_vertical_align_value(value: n::v::CssVerticalAlignValue) -> CSSVa
     }
 }
 
+#[inline(always)]
+fn convert_net_z_index_value(value: n::v::CssZIndexValue) -> CSSValue<CSSZIndex> {
+    match value {
+        n::v::CssZIndexInherit => Inherit,
+        n::v::CssZIndexAuto => Specified(CSSZIndexAuto),
+        n::v::CssZIndexSet(z) => Specified(CSSZIndexInteger(z as int))
+    }
+}
+
+#[inline(always)]
+fn convert_net_clip_value(value: n::v::CssClipValue) -> CSSValue<CSSClip> {
+    match value {
+        n::v::CssClipInherit => Inherit,
+        n::v::CssClipAuto => Specified(CSSClipAuto),
+        n::v::CssClipRect(rect) => {
+            Specified(CSSClipShape(CSSClipRect {
+                top: convert_net_clip_edge(rect.top),
+                right: convert_net_clip_edge(rect.right),
+                bottom: convert_net_clip_edge(rect.bottom),
+                left: convert_net_clip_edge(rect.left)
+            }))
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_clip_edge(edge: Option<n::t::CssUnit>) -> CSSClipEdge {
+    match edge {
+        None => CSSClipEdgeAuto,
+        Some(unit) => CSSClipEdgeLength(convert_net_unit_to_length(unit))
+    }
+}
+
+#[inline(always)]
+fn convert_net_opacity_value(value: n::v::CssOpacityValue) -> CSSValue<f64> {
+    match value {
+        n::v::CssOpacityInherit => Inherit,
+        n::v::CssOpacitySet(v) => {
+            // CSS Color 3: values outside [0, 1] are clamped
+            let v = css_fixed_to_float(v);
+            Specified(if v < 0.0 { 0.0 } else if v > 1.0 { 1.0 } else { v })
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_outline_color_value(value: n::v::CssOutlineColorValue) -> CSSValue<CSSOutlineColor> {
+    match value {
+        n::v::CssOutlineColorInherit => Inherit,
+        n::v::CssOutlineColorInvert => Specified(CSSOutlineColorInvert),
+        n::v::CssOutlineColorColor(color) => Specified(CSSOutlineColorColor(convert_net_color(color)))
+    }
+}
+
+#[inline(always)]
+fn convert_net_cursor_value(value: n::v::CssCursorValue) -> CSSValue<CSSCursor> {
+    match value {
+        n::v::CssCursorInherit => Inherit,
+        n::v::CssCursorSet(urls, keyword) => {
+            Specified(CSSCursor {
+                urls: urls.iter().filter_map(|url| convert_net_url(url)).collect(),
+                keyword: convert_net_cursor_keyword(keyword)
+            })
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_cursor_keyword(keyword: n::v::CssCursorKeyword) -> CSSCursorKeyword {
+    match keyword {
+        n::v::CssCursorAuto => CSSCursorAuto,
+        n::v::CssCursorCrosshair => CSSCursorCrosshair,
+        n::v::CssCursorDefault => CSSCursorDefault,
+        n::v::CssCursorPointer => CSSCursorPointer,
+        n::v::CssCursorMove => CSSCursorMove,
+        n::v::CssCursorEResize => CSSCursorEResize,
+        n::v::CssCursorNeResize => CSSCursorNeResize,
+        n::v::CssCursorNwResize => CSSCursorNwResize,
+        n::v::CssCursorNResize => CSSCursorNResize,
+        n::v::CssCursorSeResize => CSSCursorSeResize,
+        n::v::CssCursorSwResize => CSSCursorSwResize,
+        n::v::CssCursorSResize => CSSCursorSResize,
+        n::v::CssCursorWResize => CSSCursorWResize,
+        n::v::CssCursorText => CSSCursorText,
+        n::v::CssCursorWait => CSSCursorWait,
+        n::v::CssCursorHelp => CSSCursorHelp,
+        n::v::CssCursorProgress => CSSCursorProgress
+    }
+}
+
+#[inline(always)]
+fn convert_net_content_value(value: n::v::CssContentValue) -> CSSValue<CSSContent> {
+    match value {
+        n::v::CssContentInherit => Inherit,
+        n::v::CssContentNormal => Specified(CSSContentNormal),
+        n::v::CssContentNone => Specified(CSSContentNone),
+        n::v::CssContentSet(items) => {
+            let items = items.iter().filter_map(|item| convert_net_content_item(item)).collect();
+            Specified(CSSContentContent(items))
+        }
+    }
+}
+
+// Items with an unparseable url are dropped
+#[inline(always)]
+fn convert_net_content_item(item: &n::v::CssContentItem) -> Option<CSSContentItem> {
+    let item = match *item {
+        n::v::CssContentItemString(ref s) => CSSContentItemString(s.to_str()),
+        n::v::CssContentItemUri(ref uri) => {
+            match convert_net_url(uri) {
+                Some(url) => CSSContentItemUri(url),
+                None => return None
+            }
+        }
+        n::v::CssContentItemCounter(ref name, style) => {
+            CSSContentItemCounter(name.to_str(), convert_net_list_style_type(style))
+        }
+        n::v::CssContentItemCounters(ref name, ref sep, style) => {
+            CSSContentItemCounters(name.to_str(), sep.to_str(), convert_net_list_style_type(style))
+        }
+        n::v::CssContentItemAttr(ref name) => CSSContentItemAttr(name.to_str()),
+        n::v::CssContentItemOpenQuote => CSSContentItemOpenQuote,
+        n::v::CssContentItemCloseQuote => CSSContentItemCloseQuote,
+        n::v::CssContentItemNoOpenQuote => CSSContentItemNoOpenQuote,
+        n::v::CssContentItemNoCloseQuote => CSSContentItemNoCloseQuote
+    };
+    Some(item)
+}
+
+#[inline(always)]
+fn convert_net_counters(counters: &[(LwcString, n::ll::t::css_fixed)]) -> ~[CSSCounter] {
+    do counters.map |counter| {
+        let (ref name, value) = *counter;
+        CSSCounter {
+            name: name.to_str(),
+            value: css_fixed_to_float(value) as int
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_counter_reset_value(value: n::v::CssCounterResetValue) -> CSSValue<CSSCounterReset> {
+    match value {
+        n::v::CssCounterResetInherit => Inherit,
+        n::v::CssCounterResetNone => Specified(CSSCounterResetNone),
+        n::v::CssCounterResetNamed(counters) => {
+            Specified(CSSCounterResetCounters(convert_net_counters(counters)))
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_counter_increment_value(value: n::v::CssCounterIncrementValue)
+                                       -> CSSValue<CSSCounterIncrement> {
+    match value {
+        n::v::CssCounterIncrementInherit => Inherit,
+        n::v::CssCounterIncrementNone => Specified(CSSCounterIncrementNone),
+        n::v::CssCounterIncrementNamed(counters) => {
+            Specified(CSSCounterIncrementCounters(convert_net_counters(counters)))
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_quotes_value(value: n::v::CssQuotesValue) -> CSSValue<CSSQuotes> {
+    match value {
+        n::v::CssQuotesInherit => Inherit,
+        n::v::CssQuotesNone => Specified(CSSQuotesNone),
+        n::v::CssQuotesString(strings) => {
+            // libcss stores the open/close pairs as one flat list
+            let mut pairs = ~[];
+            let mut i = 0;
+            while i + 1 < strings.len() {
+                pairs.push((strings[i].to_str(), strings[i + 1].to_str()));
+                i += 2;
+            }
+            Specified(CSSQuotesStrings(pairs))
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_list_style_type_value(value: n::v::CssListStyleTypeValue) -> CSSValue<CSSListStyleType> {
+    match value {
+        n::v::CssListStyleTypeInherit => Inherit,
+        value => Specified(convert_net_list_style_type(value))
+    }
+}
+
+#[inline(always)]
+fn convert_net_list_style_type(value: n::v::CssListStyleTypeValue) -> CSSListStyleType {
+    match value {
+        // Only the styles of counter() and counters() get here, which can't be
+        // 'inherit'; decimal is their default
+        n::v::CssListStyleTypeInherit => CSSListStyleTypeDecimal,
+        n::v::CssListStyleTypeDisc => CSSListStyleTypeDisc,
+        n::v::CssListStyleTypeCircle => CSSListStyleTypeCircle,
+        n::v::CssListStyleTypeSquare => CSSListStyleTypeSquare,
+        n::v::CssListStyleTypeDecimal => CSSListStyleTypeDecimal,
+        n::v::CssListStyleTypeDecimalLeadingZero => CSSListStyleTypeDecimalLeadingZero,
+        n::v::CssListStyleTypeLowerRoman => CSSListStyleTypeLowerRoman,
+        n::v::CssListStyleTypeUpperRoman => CSSListStyleTypeUpperRoman,
+        n::v::CssListStyleTypeLowerGreek => CSSListStyleTypeLowerGreek,
+        n::v::CssListStyleTypeLowerLatin => CSSListStyleTypeLowerLatin,
+        n::v::CssListStyleTypeUpperLatin => CSSListStyleTypeUpperLatin,
+        n::v::CssListStyleTypeArmenian => CSSListStyleTypeArmenian,
+        n::v::CssListStyleTypeGeorgian => CSSListStyleTypeGeorgian,
+        n::v::CssListStyleTypeLowerAlpha => CSSListStyleTypeLowerAlpha,
+        n::v::CssListStyleTypeUpperAlpha => CSSListStyleTypeUpperAlpha,
+        n::v::CssListStyleTypeNone => CSSListStyleTypeNone
+    }
+}
+
+#[inline(always)]
+fn convert_net_list_style_position_value(value: n::v::CssListStylePositionValue)
+                                         -> CSSValue<CSSListStylePosition> {
+    match value {
+        n::v::CssListStylePositionInherit => Inherit,
+        n::v::CssListStylePositionInside => Specified(CSSListStylePositionInside),
+        n::v::CssListStylePositionOutside => Specified(CSSListStylePositionOutside)
+    }
+}
+
+#[inline(always)]
+fn convert_net_list_style_image_value(value: n::v::CssListStyleImageValue) -> CSSValue<CSSListStyleImage> {
+    match value {
+        n::v::CssListStyleImageInherit => Inherit,
+        n::v::CssListStyleImageNone => Specified(CSSListStyleImageNone),
+        n::v::CssListStyleImageUri(uri) => {
+            match convert_net_url(&uri) {
+                Some(url) => Specified(CSSListStyleImageUri(url)),
+                None => Specified(CSSListStyleImageNone)
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn convert_net_page_break_before_value(value: n::v::CssPageBreakBeforeValue)
+                                       -> CSSValue<CSSPageBreakBefore> {
+    match value {
+        n::v::CssPageBreakBeforeInherit => Inherit,
+        n::v::CssPageBreakBeforeAuto => Specified(CSSPageBreakBeforeAuto),
+        n::v::CssPageBreakBeforeAlways => Specified(CSSPageBreakBeforeAlways),
+        n::v::CssPageBreakBeforeAvoid => Specified(CSSPageBreakBeforeAvoid),
+        n::v::CssPageBreakBeforeLeft => Specified(CSSPageBreakBeforeLeft),
+        n::v::CssPageBreakBeforeRight => Specified(CSSPageBreakBeforeRight)
+    }
+}
+
+#[inline(always)]
+fn convert_net_page_break_after_value(value: n::v::CssPageBreakAfterValue)
+                                      -> CSSValue<CSSPageBreakAfter> {
+    match value {
+        n::v::CssPageBreakAfterInherit => Inherit,
+        n::v::CssPageBreakAfterAuto => Specified(CSSPageBreakAfterAuto),
+        n::v::CssPageBreakAfterAlways => Specified(CSSPageBreakAfterAlways),
+        n::v::CssPageBreakAfterAvoid => Specified(CSSPageBreakAfterAvoid),
+        n::v::CssPageBreakAfterLeft => Specified(CSSPageBreakAfterLeft),
+        n::v::CssPageBreakAfterRight => Specified(CSSPageBreakAfterRight)
+    }
+}
+
+#[inline(always)]
+fn convert_net_page_break_inside_value(value: n::v::CssPageBreakInsideValue)
+                                       -> CSSValue<CSSPageBreakInside> {
+    match value {
+        n::v::CssPageBreakInsideInherit => Inherit,
+        n::v::CssPageBreakInsideAuto => Specified(CSSPageBreakInsideAuto),
+        n::v::CssPageBreakInsideAvoid => Specified(CSSPageBreakInsideAvoid)
+    }
+}
+
+#[inline(always)]
+fn convert_net_orphans_value(value: n::v::CssOrphansValue) -> CSSValue<int> {
+    match value {
+        n::v::CssOrphansInherit => Inherit,
+        n::v::CssOrphansSet(count) => Specified(css_fixed_to_float(count) as int)
+    }
+}
+
+#[inline(always)]
+fn convert_net_widows_value(value: n::v::CssWidowsValue) -> CSSValue<int> {
+    match value {
+        n::v::CssWidowsInherit => Inherit,
+        n::v::CssWidowsSet(count) => Specified(css_fixed_to_float(count) as int)
+    }
+}
+
+// Author data, so an unparseable url is ignored rather than failed on
+#[inline(always)]
+fn convert_net_url(url: &LwcString) -> Option<Url> {
+    FromStr::from_str(url.to_str_slice())
+}
+
 #[inline(always)]
 fn convert_net_unit_to_length(unit: n::t::CssUnit) -> Length {
     match convert_net_unit_to_length_or_percent(unit) {
@@ -517,17 +1101,41 @@ fn convert_net_unit_to_length(unit: n::t::CssUnit) -> Length {
 
 // Always inline due to SCCP possibilities.
 #[inline(always)]
-fn convert_net_unit_to_length_or_percent(unit: n::t::CssUnit) -> Either<Length, f64> {
+pub fn convert_net_unit_to_length_or_percent(unit: n::t::CssUnit) -> Either<Length, f64> {
+    match convert_net_unit_to_dimension(unit) {
+        DimensionLength(length) => Left(length),
+        DimensionPercentage(percent) => Right(percent),
+        _ => fail!(~"unexpected non-length unit")
+    }
+}
+
+#[inline(always)]
+fn convert_net_unit_to_dimension(unit: n::t::CssUnit) -> Dimension {
     match unit {
-        n::t::CssUnitPx(l) => Left(Px(css_fixed_to_float(l))),
-        n::t::CssUnitEm(l) => Left(Em(css_fixed_to_float(l))),
-        n::t::CssUnitPt(l) => Left(Px(css_fixed_to_float(l) / 72.0 * 96.0)),
-        n::t::CssUnitCm(l) => Left(Px(css_fixed_to_float(l) / 2.54 * 96.0)),
-        n::t::CssUnitMm(l) => Left(Px(css_fixed_to_float(l) / 25.4 * 96.0)),
-        n::t::CssUnitIn(l) => Left(Px(css_fixed_to_float(l) / 1.0 * 96.0)),
-        n::t::CssUnitPc(l) => Left(Px(css_fixed_to_float(l) / 6.0 * 96.0)),
-        n::t::CssUnitPct(p) => Right(css_fixed_to_float(p)),
-        _ => unimpl("unit")
+        n::t::CssUnitPx(l) => DimensionLength(Px(css_fixed_to_float(l))),
+        n::t::CssUnitEm(l) => DimensionLength(Em(css_fixed_to_float(l))),
+        n::t::CssUnitEx(l) => DimensionLength(Ex(css_fixed_to_float(l))),
+        n::t::CssUnitPt(l) => DimensionLength(Px(css_fixed_to_float(l) / 72.0 * 96.0)),
+        n::t::CssUnitCm(l) => DimensionLength(Px(css_fixed_to_float(l) / 2.54 * 96.0)),
+        n::t::CssUnitMm(l) => DimensionLength(Px(css_fixed_to_float(l) / 25.4 * 96.0)),
+        n::t::CssUnitIn(l) => DimensionLength(Px(css_fixed_to_float(l) / 1.0 * 96.0)),
+        n::t::CssUnitPc(l) => DimensionLength(Px(css_fixed_to_float(l) / 6.0 * 96.0)),
+        n::t::CssUnitPct(p) => DimensionPercentage(css_fixed_to_float(p)),
+        n::t::CssUnitDeg(a) => DimensionAngle(Deg(css_fixed_to_float(a))),
+        n::t::CssUnitGrad(a) => DimensionAngle(Grad(css_fixed_to_float(a))),
+        n::t::CssUnitRad(a) => DimensionAngle(Rad(css_fixed_to_float(a))),
+        n::t::CssUnitMs(t) => DimensionTime(Ms(css_fixed_to_float(t))),
+        n::t::CssUnitS(t) => DimensionTime(S(css_fixed_to_float(t))),
+        n::t::CssUnitHz(f) => DimensionFrequency(Hz(css_fixed_to_float(f))),
+        n::t::CssUnitKHz(f) => DimensionFrequency(KHz(css_fixed_to_float(f)))
+    }
+}
+
+#[inline(always)]
+fn wrap<T>(value: CSSValue<T>, tag: &fn(T) -> PropertyValue) -> CSSValue<PropertyValue> {
+    match value {
+        Inherit => Inherit,
+        Specified(value) => Specified(tag(value))
     }
 }
 
@@ -0,0 +1,90 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+The work a layout engine has to redo after a style changes
+
+Each property has the damage its changes cause, see `Property::damage`.
+Damage nests: rebuilding the box tree implies a reflow, and a reflow
+implies a repaint, so every set includes the lesser kinds.
+*/
+
+use std::ops::BitOr;
+
+#[deriving(Eq, Clone)]
+pub struct RestyleDamage {
+    bits: u8
+}
+
+static REPAINT_BIT: u8 = 0x01;
+static REFLOW_BIT: u8 = 0x02;
+static REBUILD_BOX_TREE_BIT: u8 = 0x04;
+
+/** Nothing visible changed */
+pub static NO_DAMAGE: RestyleDamage = RestyleDamage { bits: 0 };
+/** The box has to be painted again, its geometry is unchanged */
+pub static REPAINT: RestyleDamage = RestyleDamage { bits: REPAINT_BIT };
+/** The geometry of the box, and possibly its neighbours, has to be recomputed */
+pub static REFLOW: RestyleDamage = RestyleDamage { bits: REPAINT_BIT | REFLOW_BIT };
+/** The boxes generated for the element have to be built again */
+pub static REBUILD_BOX_TREE: RestyleDamage = RestyleDamage {
+    bits: REPAINT_BIT | REFLOW_BIT | REBUILD_BOX_TREE_BIT
+};
+
+impl RestyleDamage {
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /** Whether every kind of damage in `other` is also in `self` */
+    pub fn contains(&self, other: RestyleDamage) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn insert(&mut self, other: RestyleDamage) {
+        self.bits |= other.bits;
+    }
+
+    pub fn needs_repaint(&self) -> bool {
+        self.contains(REPAINT)
+    }
+
+    pub fn needs_reflow(&self) -> bool {
+        self.contains(REFLOW)
+    }
+
+    pub fn needs_box_tree_rebuild(&self) -> bool {
+        self.contains(REBUILD_BOX_TREE)
+    }
+}
+
+impl BitOr<RestyleDamage, RestyleDamage> for RestyleDamage {
+    fn bitor(&self, other: &RestyleDamage) -> RestyleDamage {
+        RestyleDamage { bits: self.bits | other.bits }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_damage_sets() {
+        assert!(NO_DAMAGE.is_empty());
+        assert!(!REPAINT.is_empty());
+        assert!(REBUILD_BOX_TREE.needs_reflow() && REBUILD_BOX_TREE.needs_repaint());
+        assert!(REFLOW.needs_repaint() && !REFLOW.needs_box_tree_rebuild());
+        assert!(!REPAINT.needs_reflow());
+        assert!(REFLOW.contains(REPAINT));
+        assert!(!REPAINT.contains(REFLOW));
+        assert!(REPAINT | REFLOW == REFLOW);
+        assert!(NO_DAMAGE | REPAINT == REPAINT);
+
+        let mut damage = NO_DAMAGE;
+        damage.insert(REPAINT);
+        assert!(damage == REPAINT);
+        damage.insert(REBUILD_BOX_TREE);
+        assert!(damage == REBUILD_BOX_TREE);
+    }
+}
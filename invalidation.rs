@@ -0,0 +1,323 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Selector-based style invalidation
+
+When a class, id, attribute or state of an element changes, only the
+selectors that mention it can start or stop matching, and where they mention
+it tells which elements are affected. In `.menu > li` a change to the 'menu'
+class can only affect the element's descendants, while in `.open + li` a
+change to 'open' affects the following siblings. A feature in a compound
+selector with a type selector, like 'active' in `li.active`, only counts for
+elements of that name.
+
+A feature inside `:not()` counts as part of the compound selector containing
+it, since it can start or stop that compound matching just the same.
+
+A selector we can't parse might depend on anything, so once one is added
+every change invalidates the whole subtree.
+*/
+
+use std::ascii::StrAsciiExt;
+use std::hashmap::HashMap;
+use selectors::{Selector, Compound, Combinator, IdSelector, ClassSelector, AttributeSelector};
+use selectors::{PseudoClassSelector, NegationSelector, PseudoElementSelector};
+
+/** The elements whose styles a change may affect, relative to the changed element */
+#[deriving(Eq, Clone)]
+pub enum InvalidationScope {
+    /** No selector depends on the change */
+    InvalidateNothing,
+    /** The element itself */
+    InvalidateSelf,
+    /** The element and its descendants */
+    InvalidateDescendants,
+    /** The element and its following siblings */
+    InvalidateSiblings,
+    /** The element, its following siblings, and the descendants of all of them */
+    InvalidateSubtree
+}
+
+/** A change to an element that selectors can observe */
+#[deriving(Eq, Clone)]
+pub enum ElementChange {
+    /** A class was added or removed */
+    ClassChange(~str),
+    /** The id changed from or to this one */
+    IdChange(~str),
+    /** An attribute was set, changed or removed */
+    AttributeChange(~str),
+    /** A state pseudo-class, like 'hover' or 'focus', started or stopped applying */
+    StateChange(~str)
+}
+
+// Where a feature appears: the element name its compound requires, if any, and the scope
+struct Dependency {
+    name: Option<~str>,
+    scope: InvalidationScope
+}
+
+impl Dependency {
+    fn applies_to(&self, name: &str) -> bool {
+        match self.name {
+            Some(ref required) => required.eq_ignore_ascii_case(name),
+            None => true
+        }
+    }
+}
+
+impl InvalidationScope {
+    /** The smallest scope covering both `self` and `other` */
+    pub fn union(&self, other: InvalidationScope) -> InvalidationScope {
+        match (*self, other) {
+            (InvalidateNothing, scope) | (scope, InvalidateNothing) => scope,
+            (a, b) if a == b => a,
+            (InvalidateSelf, scope) | (scope, InvalidateSelf) => scope,
+            _ => InvalidateSubtree
+        }
+    }
+
+    pub fn includes_descendants(&self) -> bool {
+        match *self {
+            InvalidateDescendants | InvalidateSubtree => true,
+            _ => false
+        }
+    }
+
+    pub fn includes_siblings(&self) -> bool {
+        match *self {
+            InvalidateSiblings | InvalidateSubtree => true,
+            _ => false
+        }
+    }
+
+    // The scope of a feature one combinator further left than one with this scope
+    fn across(&self, combinator: Combinator) -> InvalidationScope {
+        if combinator.is_sibling() {
+            self.union(InvalidateSiblings)
+        } else {
+            self.union(InvalidateDescendants)
+        }
+    }
+}
+
+/**
+Which classes, ids, attributes and pseudo-classes appear in a set of
+selectors, with the scope a change to each of them invalidates
+*/
+pub struct InvalidationMap {
+    classes: HashMap<~str, ~[Dependency]>,
+    ids: HashMap<~str, ~[Dependency]>,
+    // Attribute and pseudo-class names are lowercased
+    attributes: HashMap<~str, ~[Dependency]>,
+    pseudo_classes: HashMap<~str, ~[Dependency]>,
+    // Whether a selector list we couldn't parse was added
+    unparseable: bool
+}
+
+impl InvalidationMap {
+    pub fn new() -> InvalidationMap {
+        InvalidationMap {
+            classes: HashMap::new(),
+            ids: HashMap::new(),
+            attributes: HashMap::new(),
+            pseudo_classes: HashMap::new(),
+            unparseable: false
+        }
+    }
+
+    /** Adds selectors, like the selector list of a style rule */
+    pub fn add_selectors(&mut self, selectors: &[Selector]) {
+        for selector in selectors.iter() {
+            let mut scope = InvalidateSelf;
+            for compound in selector.compounds.rev_iter() {
+                self.add_compound(compound, &compound.name, scope);
+                for &combinator in compound.combinator.iter() {
+                    scope = scope.across(combinator);
+                }
+            }
+        }
+    }
+
+    /** Adds a selector list we couldn't parse, which any change may affect */
+    pub fn add_unparseable(&mut self) {
+        self.unparseable = true;
+    }
+
+    /**
+    What `change` to an element named `element` invalidates, according to the
+    selectors added so far
+    */
+    pub fn scope_for(&self, element: &str, change: &ElementChange) -> InvalidationScope {
+        if self.unparseable {
+            return InvalidateSubtree;
+        }
+        match *change {
+            ClassChange(ref class) => {
+                lookup(&self.classes, element, class.as_slice())
+                    .union(lookup(&self.attributes, element, "class"))
+            }
+            IdChange(ref id) => {
+                lookup(&self.ids, element, id.as_slice())
+                    .union(lookup(&self.attributes, element, "id"))
+            }
+            AttributeChange(ref name) => {
+                let name = name.to_ascii_lower();
+                let scope = lookup(&self.attributes, element, name.as_slice());
+                // The class and id attributes also feed class and id selectors
+                if name == ~"class" {
+                    scope.union(lookup_all(&self.classes, element))
+                } else if name == ~"id" {
+                    scope.union(lookup_all(&self.ids, element))
+                } else {
+                    scope
+                }
+            }
+            StateChange(ref name) => {
+                lookup(&self.pseudo_classes, element, name.to_ascii_lower().as_slice())
+            }
+        }
+    }
+
+    // `element` is the type selector of the compound, which a negated one is part of
+    fn add_compound(&mut self,
+                    compound: &Compound,
+                    element: &Option<~str>,
+                    scope: InvalidationScope) {
+        for simple_selector in compound.simple_selectors.iter() {
+            let feature = match *simple_selector {
+                IdSelector(ref id) => Some((&mut self.ids, id.clone())),
+                ClassSelector(ref class) => Some((&mut self.classes, class.clone())),
+                AttributeSelector(ref name) => Some((&mut self.attributes, name.clone())),
+                PseudoClassSelector(ref name, _) => Some((&mut self.pseudo_classes, name.clone())),
+                NegationSelector(ref negated) => {
+                    self.add_compound(&**negated, element, scope);
+                    None
+                }
+                PseudoElementSelector(_) => None
+            };
+            match feature {
+                Some((map, key)) => add_dependency(map, key, element.clone(), scope),
+                None => ()
+            }
+        }
+    }
+}
+
+fn add_dependency(map: &mut HashMap<~str, ~[Dependency]>,
+                  key: ~str,
+                  element: Option<~str>,
+                  scope: InvalidationScope) {
+    let dependencies = map.find_or_insert_with(key, |_| ~[]);
+    for dependency in dependencies.mut_iter() {
+        if dependency.name == element {
+            dependency.scope = dependency.scope.union(scope);
+            return;
+        }
+    }
+    dependencies.push(Dependency { name: element, scope: scope });
+}
+
+// The scope of the dependencies on `key` that apply to elements named `element`
+fn lookup(map: &HashMap<~str, ~[Dependency]>, element: &str, key: &str) -> InvalidationScope {
+    match map.find_equiv(&key) {
+        Some(dependencies) => union_for(dependencies.as_slice(), element),
+        None => InvalidateNothing
+    }
+}
+
+// The same for every key of the map
+fn lookup_all(map: &HashMap<~str, ~[Dependency]>, element: &str) -> InvalidationScope {
+    map.iter().fold(InvalidateNothing, |scope, (_, dependencies)| {
+        scope.union(union_for(dependencies.as_slice(), element))
+    })
+}
+
+fn union_for(dependencies: &[Dependency], element: &str) -> InvalidationScope {
+    dependencies.iter().filter(|dependency| dependency.applies_to(element))
+        .fold(InvalidateNothing, |scope, dependency| scope.union(dependency.scope))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use selectors::parse_selector_list;
+
+    fn map(selectors: &[&str]) -> InvalidationMap {
+        let mut map = InvalidationMap::new();
+        for selectors in selectors.iter() {
+            map.add_selectors(parse_selector_list(*selectors).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_union() {
+        assert!(InvalidateNothing.union(InvalidateSelf) == InvalidateSelf);
+        assert!(InvalidateSelf.union(InvalidateDescendants) == InvalidateDescendants);
+        assert!(InvalidateSiblings.union(InvalidateSelf) == InvalidateSiblings);
+        assert!(InvalidateDescendants.union(InvalidateSiblings) == InvalidateSubtree);
+        assert!(InvalidateSubtree.union(InvalidateNothing) == InvalidateSubtree);
+        assert!(InvalidateSubtree.includes_descendants() && InvalidateSubtree.includes_siblings());
+        assert!(!InvalidateSelf.includes_descendants() && !InvalidateSelf.includes_siblings());
+    }
+
+    #[test]
+    fn test_positions() {
+        let map = map(["div.a", ".menu > li", "#main p", ".open + li", ".x ~ .y .z",
+                       "a:hover", "li:hover span", "input[type=text]", "*[data-x] + p"]);
+        assert!(map.scope_for("div", &ClassChange(~"a")) == InvalidateSelf);
+        assert!(map.scope_for("span", &ClassChange(~"a")) == InvalidateNothing);
+        assert!(map.scope_for("span", &ClassChange(~"menu")) == InvalidateDescendants);
+        assert!(map.scope_for("span", &IdChange(~"main")) == InvalidateDescendants);
+        assert!(map.scope_for("span", &ClassChange(~"open")) == InvalidateSiblings);
+        assert!(map.scope_for("span", &ClassChange(~"x")) == InvalidateSubtree);
+        assert!(map.scope_for("span", &ClassChange(~"y")) == InvalidateDescendants);
+        assert!(map.scope_for("span", &ClassChange(~"z")) == InvalidateSelf);
+        assert!(map.scope_for("li", &StateChange(~"hover")) == InvalidateDescendants);
+        assert!(map.scope_for("LI", &StateChange(~"Hover")) == InvalidateDescendants);
+        assert!(map.scope_for("a", &StateChange(~"hover")) == InvalidateSelf);
+        assert!(map.scope_for("span", &StateChange(~"hover")) == InvalidateNothing);
+        assert!(map.scope_for("span", &StateChange(~"focus")) == InvalidateNothing);
+        assert!(map.scope_for("input", &AttributeChange(~"TYPE")) == InvalidateSelf);
+        assert!(map.scope_for("span", &AttributeChange(~"data-x")) == InvalidateSiblings);
+        assert!(map.scope_for("span", &ClassChange(~"unused")) == InvalidateNothing);
+        assert!(map.scope_for("span", &ClassChange(~"A")) == InvalidateNothing);
+    }
+
+    #[test]
+    fn test_selector_lists_and_functions() {
+        let map = map([".a, .b .c", "li:not(.d) .e", "li:nth-child(2n+1).f", "p::before.g",
+                       "a[title=\"x, .h\"]",
+                       "span:not(p).k"]);
+        assert!(map.scope_for("span", &ClassChange(~"a")) == InvalidateSelf);
+        assert!(map.scope_for("span", &ClassChange(~"b")) == InvalidateDescendants);
+        assert!(map.scope_for("li", &ClassChange(~"d")) == InvalidateDescendants);
+        assert!(map.scope_for("li", &ClassChange(~"f")) == InvalidateSelf);
+        assert!(map.scope_for("p", &ClassChange(~"g")) == InvalidateSelf);
+        assert!(map.scope_for("span", &ClassChange(~"k")) == InvalidateSelf);
+        assert!(map.scope_for("p", &ClassChange(~"k")) == InvalidateNothing);
+        assert!(map.scope_for("span", &StateChange(~"before")) == InvalidateNothing);
+        assert!(map.scope_for("span", &ClassChange(~"h")) == InvalidateNothing);
+    }
+
+    #[test]
+    fn test_class_and_id_attributes() {
+        let map = map([".a .b", "[class~=c] + p", "#d"]);
+        assert!(map.scope_for("span", &ClassChange(~"b")) == InvalidateSiblings);
+        assert!(map.scope_for("span", &AttributeChange(~"class")) == InvalidateSubtree);
+        assert!(map.scope_for("span", &AttributeChange(~"id")) == InvalidateSelf);
+        assert!(map.scope_for("span", &IdChange(~"e")) == InvalidateNothing);
+    }
+
+    #[test]
+    fn test_unparseable() {
+        let mut map = map([".a"]);
+        map.add_unparseable();
+        assert!(map.scope_for("span", &ClassChange(~"a")) == InvalidateSubtree);
+        assert!(map.scope_for("span", &ClassChange(~"unused")) == InvalidateSubtree);
+        assert!(map.scope_for("span", &StateChange(~"hover")) == InvalidateSubtree);
+    }
+}
@@ -0,0 +1,543 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+The style rules matching a node, for inspectors
+
+libcss doesn't say which rules it matched, so, like `@page` rules, style rules
+are recovered from the stylesheet source, those of `@media` blocks for the
+media type included, and matched again here. `SelectHandler` answers for type, id
+and class selectors, the `:link`, `:visited` and `:root` pseudo-classes,
+negations of these, and the descendant and child combinators. Where a selector
+needs anything else, like siblings, attributes or dynamic states, whether
+libcss matched it is unknown: its rule is listed as such and takes no part in
+the cascade here. Rules for pseudo-elements don't match the element itself.
+
+Shorthands are expanded, so a declaration is overridden once every longhand it
+sets is overridden. libcss drops declarations of properties it doesn't know
+and those with invalid values, and these are marked ignored. Values are
+checked by having libcss parse the declaration on its own, which takes the
+properties of the declaration to be computed here: otherwise, whether libcss
+kept it is unknown, and it takes no part in the cascade here.
+*/
+
+use std::hashmap::HashMap;
+use std::ascii::StrAsciiExt;
+use std::libc;
+use extra::sort;
+use extra::url::Url;
+use parser::{SourceRule, SourceDeclaration};
+use selectors::{Compound, parse_selector_list, ChildCombinator};
+use selectors::{IdSelector, ClassSelector, AttributeSelector, PseudoClassSelector};
+use selectors::{NegationSelector, PseudoElementSelector};
+use select::{SelectCtx, SelectHandler, SheetHandle};
+use bloom::AncestorBloomFilter;
+use stylesheet::Stylesheet;
+use types::{StylesheetOrigin, OriginAuthor, MediaType};
+use property::{Property, PropertyValue};
+use values::CSSValue;
+use serialize::ToCss;
+use util::VoidPtrLike;
+
+/** Ids, then classes and pseudo-classes, then type selectors (CSS 2.1, Section 6.4.3) */
+pub type Specificity = (uint, uint, uint);
+
+/** A style rule matching a node */
+pub struct MatchedRule {
+    /**
+    The selector of the rule's selector list that matched, the most specific if
+    several did, preferring those known to match
+    */
+    selector: ~str,
+    specificity: Specificity,
+    origin: StylesheetOrigin,
+    /** The sheet of the rule, `None` for the declarations of an inline style */
+    sheet: Option<SheetHandle>,
+    /** The 1-based line on which the rule starts */
+    line: uint,
+    /**
+    Whether the selector needs something `SelectHandler` can't tell, so libcss
+    may or may not have matched it. Its declarations neither win nor override.
+    */
+    unknown: bool,
+    declarations: ~[MatchedDeclaration]
+}
+
+pub struct MatchedDeclaration {
+    /** The lowercased property name */
+    name: ~str,
+    value: ~str,
+    important: bool,
+    /** The longhands the declaration sets, only its own property unless it's a shorthand */
+    longhands: ~[~str],
+    /**
+    Whether libcss drops the declaration for naming a property it doesn't know
+    or for an invalid value
+    */
+    ignored: bool,
+    /**
+    Whether libcss may or may not drop the declaration, since some property it
+    sets isn't computed here. It neither wins nor overrides.
+    */
+    unknown: bool,
+    /** Whether declarations of higher precedence set every longhand this one sets */
+    overridden: bool
+}
+
+// What `SelectHandler` tells of a selector matching a node
+#[deriving(Eq)]
+enum MatchResult {
+    Matches,
+    // Depends on something `SelectHandler` can't tell
+    MayMatch,
+    DoesNotMatch
+}
+
+impl MatchResult {
+    fn and(self, other: MatchResult) -> MatchResult {
+        match (self, other) {
+            (DoesNotMatch, _) | (_, DoesNotMatch) => DoesNotMatch,
+            (MayMatch, _) | (_, MayMatch) => MayMatch,
+            (Matches, Matches) => Matches
+        }
+    }
+
+    fn negate(self) -> MatchResult {
+        match self {
+            Matches => DoesNotMatch,
+            MayMatch => MayMatch,
+            DoesNotMatch => Matches
+        }
+    }
+}
+
+// Origin and importance, whether from an inline style, specificity, then source order
+type RulePrecedence = (uint, bool, Specificity, uint);
+// And then the order of the declaration within its rule
+type DeclarationPrecedence = (uint, bool, Specificity, uint, uint);
+
+struct Candidate {
+    // The position of the rule among all rules of the cascade
+    order: uint,
+    rule: MatchedRule
+}
+
+impl Candidate {
+    fn precedence(&self) -> RulePrecedence {
+        (self.rule.origin.cascade_level(false), self.rule.sheet.is_none(), self.rule.specificity,
+         self.order)
+    }
+
+    fn declaration_precedence(&self, index: uint) -> DeclarationPrecedence {
+        let important = self.rule.declarations[index].important;
+        (self.rule.origin.cascade_level(important), self.rule.sheet.is_none(),
+         self.rule.specificity, self.order, index)
+    }
+}
+
+/**
+Match the style rules of `sheets` for `media`, given in cascade order,
+against `node`. The declarations of the node's inline style, if any, are
+listed as an author rule with an empty selector, winning over the rules of
+author sheets like a more specific selector (CSS 2.1, Section 6.4.3).
+The rules are returned in cascade order by their normal declarations, so the
+last rule wins over the others, except where `!important` declarations of
+earlier rules win, as the `overridden` flags show. If given, `bloom` has to
+hold exactly the ancestors of `node`, and spares walking them for descendant
+selectors whose ancestor it rules out.
+*/
+pub fn match_rules<N, H: SelectHandler<N>>(
+        sheets: &[(SheetHandle, StylesheetOrigin, &[SourceRule])],
+        media: MediaType,
+        node: &N,
+        inline_style: Option<&[SourceDeclaration]>,
+        handler: &H,
+        bloom: Option<&AncestorBloomFilter>) -> ~[MatchedRule] {
+    let mut candidates = ~[];
+    let mut order = 0;
+
+    for &(sheet, origin, ref rules) in sheets.iter() {
+        for rule in rules.iter() {
+            if rule.at_keyword.is_some() || !rule.applies_to(media) {
+                loop;
+            }
+            order += 1;
+            // libcss drops rules with an invalid selector
+            let selectors = match parse_selector_list(rule.prelude) {
+                Some(selectors) => selectors,
+                None => ~[]
+            };
+            // A selector known to match is preferred to a more specific one that may
+            let mut best: Option<(&str, Specificity, bool)> = None;
+            for selector in selectors.iter() {
+                let result = matches(selector.compounds.as_slice(), node, handler, bloom);
+                if result == DoesNotMatch {
+                    loop;
+                }
+                let known = result == Matches;
+                let specificity = selector.specificity();
+                match best {
+                    Some((_, best_specificity, best_known))
+                        if (best_known, best_specificity) >= (known, specificity) => (),
+                    _ => best = Some((selector.text.as_slice(), specificity, known))
+                }
+            }
+            match best {
+                Some((selector, specificity, known)) => {
+                    candidates.push(Candidate {
+                        order: order,
+                        rule: MatchedRule {
+                            selector: selector.to_owned(),
+                            specificity: specificity,
+                            origin: origin,
+                            sheet: Some(sheet),
+                            line: rule.line,
+                            unknown: !known,
+                            declarations: matched_declarations(rule.declarations)
+                        }
+                    });
+                }
+                None => ()
+            }
+        }
+    }
+    for declarations in inline_style.iter() {
+        order += 1;
+        candidates.push(Candidate {
+            order: order,
+            rule: MatchedRule {
+                selector: ~"",
+                specificity: (0, 0, 0),
+                origin: OriginAuthor,
+                sheet: None,
+                line: 1,
+                unknown: false,
+                declarations: matched_declarations(*declarations)
+            }
+        });
+    }
+
+    let mut winners: HashMap<~str, DeclarationPrecedence> = HashMap::new();
+    for candidate in candidates.iter().filter(|candidate| !candidate.rule.unknown) {
+        for (i, declaration) in candidate.rule.declarations.iter().enumerate() {
+            if declaration.ignored || declaration.unknown {
+                loop;
+            }
+            let precedence = candidate.declaration_precedence(i);
+            for longhand in declaration.longhands.iter() {
+                let wins = match winners.find(longhand) {
+                    Some(&winner) => precedence > winner,
+                    None => true
+                };
+                if wins {
+                    winners.insert(longhand.clone(), precedence);
+                }
+            }
+        }
+    }
+    for candidate in candidates.mut_iter() {
+        for i in range(0, candidate.rule.declarations.len()) {
+            let precedence = candidate.declaration_precedence(i);
+            let declaration = &mut candidate.rule.declarations[i];
+            // Declarations of unknown rules are overridden where they would lose
+            let overridden = do declaration.longhands.iter().all |longhand| {
+                match winners.find(longhand) {
+                    Some(&winner) => winner > precedence,
+                    None => false
+                }
+            };
+            declaration.overridden = !declaration.ignored && overridden;
+        }
+    }
+
+    sort::quick_sort(candidates, |a, b| a.precedence() <= b.precedence());
+    candidates.move_iter().map(|candidate| candidate.rule).collect()
+}
+
+fn matched_declarations(declarations: &[SourceDeclaration]) -> ~[MatchedDeclaration] {
+    do declarations.map |declaration| {
+        let longhands = longhands(declaration.name);
+        let accepted = match longhands {
+            Some(ref longhands) => {
+                is_accepted(declaration.name, declaration.value, longhands.as_slice())
+            }
+            None => Some(false)
+        };
+        MatchedDeclaration {
+            name: declaration.name.clone(),
+            value: declaration.value.clone(),
+            important: declaration.important,
+            longhands: match longhands {
+                Some(longhands) => longhands,
+                None => ~[]
+            },
+            ignored: accepted == Some(false),
+            unknown: accepted.is_none(),
+            overridden: false
+        }
+    }
+}
+
+// Whether libcss keeps the declaration `name: value`, setting `longhands`, or
+// `None` if some longhand isn't computed here. The declaration is parsed as a
+// style attribute twice, once after setting the longhands to 'inherit' and once
+// after setting them to their initial values: if libcss drops it, these
+// differ, and if it keeps it, the styles are the same.
+fn is_accepted(name: &str, value: &str, longhands: &[~str]) -> Option<bool> {
+    let mut properties = ~[];
+    for longhand in longhands.iter() {
+        match Property::from_name(*longhand) {
+            Some(property) => properties.push(property),
+            None => return None
+        }
+    }
+    let declaration = fmt!("%s: %s", name, value);
+    let inherit = properties.map(|property| fmt!("%s: inherit", property.name()));
+    let initial = do properties.map |property| {
+        fmt!("%s: %s", property.name(), property.initial_value().to_css())
+    };
+    let after_inherit = fmt!("%s; %s", inherit.connect("; "), declaration);
+    let after_initial = fmt!("%s; %s", initial.connect("; "), declaration);
+    Some(probe(after_inherit.as_slice(), properties.as_slice()) ==
+         probe(after_initial.as_slice(), properties.as_slice()))
+}
+
+// The computed values of `properties` for an element with the style attribute `style`
+fn probe(style: &str, properties: &[Property]) -> ~[CSSValue<PropertyValue>] {
+    let url: Url = FromStr::from_str("about:blank").unwrap();
+    let inline_style = Stylesheet::from_attribute(url, style);
+    let select_ctx = SelectCtx::new();
+    let results = select_ctx.select_style(&ProbeNode, Some(&inline_style), &ProbeHandler);
+    let computed = results.computed_style();
+    properties.map(|&property| computed.get(property))
+}
+
+// The lone element of `probe`
+struct ProbeNode;
+
+impl VoidPtrLike for ProbeNode {
+    fn from_void_ptr(_node: *libc::c_void) -> ProbeNode {
+        ProbeNode
+    }
+
+    fn to_void_ptr(&self) -> *libc::c_void {
+        // libcss only hands the node back, but it has to be non-null
+        1u as *libc::c_void
+    }
+}
+
+struct ProbeHandler;
+
+impl SelectHandler<ProbeNode> for ProbeHandler {
+    fn with_node_name<R>(&self, _node: &ProbeNode, f: &fn(&str) -> R) -> R {
+        f("div")
+    }
+    fn with_node_classes<R>(&self, _node: &ProbeNode, f: &fn(Option<&str>) -> R) -> R {
+        f(None)
+    }
+    fn with_node_id<R>(&self, _node: &ProbeNode, f: &fn(Option<&str>) -> R) -> R {
+        f(None)
+    }
+    fn named_parent_node(&self, _node: &ProbeNode, _name: &str) -> Option<ProbeNode> {
+        None
+    }
+    fn parent_node(&self, _node: &ProbeNode) -> Option<ProbeNode> {
+        None
+    }
+    fn node_has_class(&self, _node: &ProbeNode, _name: &str) -> bool {
+        false
+    }
+    fn node_has_id(&self, _node: &ProbeNode, _name: &str) -> bool {
+        false
+    }
+    fn named_ancestor_node(&self, _node: &ProbeNode, _name: &str) -> Option<ProbeNode> {
+        None
+    }
+    fn node_is_root(&self, _node: &ProbeNode) -> bool {
+        true
+    }
+    fn node_is_link(&self, _node: &ProbeNode) -> bool {
+        false
+    }
+}
+
+fn compound_matches<N, H: SelectHandler<N>>(compound: &Compound,
+                                            node: &N,
+                                            handler: &H) -> MatchResult {
+    let name_matches = match compound.name {
+        Some(ref name) => {
+            handler.with_node_name(node, |n| n.eq_ignore_ascii_case(name.as_slice()))
+        }
+        None => true
+    };
+    if !name_matches {
+        return DoesNotMatch;
+    }
+    do compound.simple_selectors.iter().fold(Matches) |result, simple_selector| {
+        let simple_result = match *simple_selector {
+            IdSelector(ref id) => answer(handler.node_has_id(node, id.as_slice())),
+            ClassSelector(ref class) => answer(handler.node_has_class(node, class.as_slice())),
+            PseudoClassSelector(ref name, None) if name.as_slice() == "link" => {
+                answer(handler.node_is_link(node))
+            }
+            PseudoClassSelector(ref name, None) if name.as_slice() == "root" => {
+                answer(handler.node_is_root(node))
+            }
+            // The select handler given to libcss never takes a link for visited
+            PseudoClassSelector(ref name, None) if name.as_slice() == "visited" => DoesNotMatch,
+            NegationSelector(ref negated) => compound_matches(&**negated, node, handler).negate(),
+            // Only the styles of pseudo-elements use these rules
+            PseudoElementSelector(_) => DoesNotMatch,
+            AttributeSelector(_) | PseudoClassSelector(*) => MayMatch
+        };
+        result.and(simple_result)
+    }
+}
+
+fn answer(matches: bool) -> MatchResult {
+    if matches { Matches } else { DoesNotMatch }
+}
+
+// Whether the selector, given as compound selectors, matches `node`, whose ancestors `bloom`
+// holds if given
+fn matches<N, H: SelectHandler<N>>(compounds: &[Compound],
+                                   node: &N,
+                                   handler: &H,
+                                   bloom: Option<&AncestorBloomFilter>) -> MatchResult {
+    let last = compounds.len() - 1;
+    let result = compound_matches(&compounds[last], node, handler);
+    if result == DoesNotMatch {
+        return DoesNotMatch;
+    }
+    let rest = compounds.slice_to(last);
+    match compounds[last].combinator {
+        None => result,
+        Some(ChildCombinator) => {
+            match handler.parent_node(node) {
+                Some(parent) => result.and(matches(rest, &parent, handler, None)),
+                None => DoesNotMatch
+            }
+        }
+        Some(combinator) if combinator.is_sibling() => result.and(MayMatch),
+        Some(_) => {
+            match bloom {
+                Some(bloom) if !might_match_ancestor(&rest[rest.len() - 1], bloom) => {
+                    return DoesNotMatch;
+                }
+                _ => ()
+            }
+            let mut ancestors_result = DoesNotMatch;
+            let mut current = handler.parent_node(node);
+            while current.is_some() {
+                let ancestor = current.unwrap();
+                match matches(rest, &ancestor, handler, None) {
+                    Matches => return result,
+                    MayMatch => ancestors_result = MayMatch,
+                    DoesNotMatch => ()
+                }
+                current = handler.parent_node(&ancestor);
+            }
+            result.and(ancestors_result)
+        }
+    }
+}
+
+// False if none of the ancestors in `bloom` can match the compound selector
+fn might_match_ancestor(compound: &Compound, bloom: &AncestorBloomFilter) -> bool {
+    let name_might_match = match compound.name {
+        Some(ref name) => bloom.might_have_ancestor_named(name.as_slice()),
+        None => true
+    };
+    name_might_match && do compound.simple_selectors.iter().all |simple_selector| {
+        match *simple_selector {
+            IdSelector(ref id) => bloom.might_have_ancestor_with_id(id.as_slice()),
+            ClassSelector(ref class) => bloom.might_have_ancestor_with_class(class.as_slice()),
+            _ => true
+        }
+    }
+}
+
+static SIDES: &'static [&'static str] = &["top", "right", "bottom", "left"];
+static BORDER_PARTS: &'static [&'static str] = &["width", "style", "color"];
+static LIST_STYLE_LONGHANDS: &'static [&'static str] =
+    &["list-style-type", "list-style-position", "list-style-image"];
+static FONT_LONGHANDS: &'static [&'static str] =
+    &["font-style", "font-variant", "font-weight", "font-size", "line-height", "font-family"];
+static BACKGROUND_LONGHANDS: &'static [&'static str] =
+    &["background-color", "background-image", "background-repeat", "background-attachment",
+      "background-position"];
+
+// The longhands of the CSS 2.1 property index, and 'opacity'
+static LONGHANDS: &'static [&'static str] = &[
+    "azimuth", "background-attachment", "background-color", "background-image",
+    "background-position", "background-repeat", "border-collapse", "border-spacing",
+    "border-top-color", "border-right-color", "border-bottom-color", "border-left-color",
+    "border-top-style", "border-right-style", "border-bottom-style", "border-left-style",
+    "border-top-width", "border-right-width", "border-bottom-width", "border-left-width",
+    "bottom", "caption-side", "clear", "clip", "color", "content", "counter-increment",
+    "counter-reset", "cue-after", "cue-before", "cursor", "direction", "display", "elevation",
+    "empty-cells", "float", "font-family", "font-size", "font-style", "font-variant",
+    "font-weight", "height", "left", "letter-spacing", "line-height", "list-style-image",
+    "list-style-position", "list-style-type", "margin-top", "margin-right", "margin-bottom",
+    "margin-left", "max-height", "max-width", "min-height", "min-width", "opacity", "orphans",
+    "outline-color", "outline-style", "outline-width", "overflow", "padding-top",
+    "padding-right", "padding-bottom", "padding-left", "page-break-after", "page-break-before",
+    "page-break-inside", "pause-after", "pause-before", "pitch", "pitch-range", "play-during",
+    "position", "quotes", "richness", "right", "speak", "speak-header", "speak-numeral",
+    "speak-punctuation", "speech-rate", "stress", "table-layout", "text-align",
+    "text-decoration", "text-indent", "text-transform", "top", "unicode-bidi",
+    "vertical-align", "visibility", "voice-family", "volume", "white-space", "widows", "width",
+    "word-spacing", "z-index"
+];
+
+// The longhands a declaration of the property sets, `None` if libcss doesn't know it
+fn longhands(name: &str) -> Option<~[~str]> {
+    let expanded = match name {
+        "margin" | "padding" => per_side(name, ""),
+        "border-width" | "border-style" | "border-color" => per_side("border", name.slice_from(6)),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            BORDER_PARTS.map(|part| fmt!("%s-%s", name, *part))
+        }
+        "border" => {
+            let mut all = ~[];
+            for part in BORDER_PARTS.iter() {
+                all.push_all_move(per_side("border", fmt!("-%s", *part)));
+            }
+            all
+        }
+        "outline" => BORDER_PARTS.map(|part| fmt!("outline-%s", *part)),
+        "list-style" => LIST_STYLE_LONGHANDS.map(|longhand| longhand.to_owned()),
+        "font" => FONT_LONGHANDS.map(|longhand| longhand.to_owned()),
+        "background" => BACKGROUND_LONGHANDS.map(|longhand| longhand.to_owned()),
+        "cue" | "pause" => ~[fmt!("%s-before", name), fmt!("%s-after", name)],
+        _ if LONGHANDS.iter().any(|&longhand| longhand == name) => ~[name.to_owned()],
+        _ => return None
+    };
+    Some(expanded)
+}
+
+fn per_side(prefix: &str, suffix: &str) -> ~[~str] {
+    SIDES.map(|side| fmt!("%s-%s%s", prefix, *side, suffix))
+}
+
+#[cfg(test)]
+mod test {
+    use super::longhands;
+
+    #[test]
+    fn test_longhands() {
+        assert!(longhands("color") == Some(~[~"color"]));
+        assert!(longhands("margin") ==
+                Some(~[~"margin-top", ~"margin-right", ~"margin-bottom", ~"margin-left"]));
+        assert!(longhands("border-style") == Some(~[~"border-top-style", ~"border-right-style",
+                                                    ~"border-bottom-style", ~"border-left-style"]));
+        assert!(longhands("border-left") ==
+                Some(~[~"border-left-width", ~"border-left-style", ~"border-left-color"]));
+        assert!(longhands("border").unwrap().len() == 12);
+        assert!(longhands("font").unwrap().contains(&~"line-height"));
+        assert!(longhands("pause") == Some(~[~"pause-before", ~"pause-after"]));
+        assert!(longhands("colour").is_none());
+        assert!(longhands("-moz-border-radius").is_none());
+    }
+}
@@ -0,0 +1,158 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Page box styles from `@page` rules (CSS 2.1, Section 13.2)
+
+libcss doesn't expose `@page` rules, so these are recovered from the
+stylesheet source and cascaded here.
+*/
+
+use std::ascii::StrAsciiExt;
+use std::either::{Left, Right};
+use parser::{SourceRule, SourceDeclaration};
+use types::{StylesheetOrigin, MediaType};
+use units::parsing::parse_length_or_percentage;
+use values::{CSSMargin, CSSMarginLength, CSSMarginPercentage, CSSMarginAuto};
+
+#[deriving(Eq, Clone)]
+pub enum PageSide {
+    PageLeft,
+    PageRight
+}
+
+/**
+The margins of a page box. Sides no `@page` rule sets are `None`, leaving the
+choice to the user agent.
+*/
+pub struct PageStyle {
+    margin_top: Option<CSSMargin>,
+    margin_right: Option<CSSMargin>,
+    margin_bottom: Option<CSSMargin>,
+    margin_left: Option<CSSMargin>
+}
+
+/**
+Cascade the `@page` rules in `sheets` for a page on `media`. The first page
+of a document is also either a left or a right page, depending on the
+direction of the document.
+*/
+pub fn cascade_page_rules(sheets: &[(StylesheetOrigin, &[SourceRule])],
+                          media: MediaType,
+                          first: bool,
+                          side: PageSide) -> PageStyle {
+    // Indexed by side: top, right, bottom, left
+    let mut winners: [Option<(PagePrecedence, &str)>, ..4] = [None, None, None, None];
+    let mut order = 0;
+
+    for &(origin, ref rules) in sheets.iter() {
+        for rule in rules.iter().filter(|rule| rule.applies_to(media)) {
+            let specificity = match rule.at_keyword {
+                Some(ref keyword) if keyword.as_slice() == "page" => {
+                    page_selector_specificity(rule.prelude, first, side)
+                }
+                _ => None
+            };
+            match specificity {
+                None => (),
+                Some(specificity) => {
+                    for declaration in rule.declarations.iter() {
+                        order += 1;
+                        let precedence = (origin.cascade_level(declaration.important),
+                                          specificity,
+                                          order);
+                        // Invalid declarations are ignored, and a `margin` with an
+                        // invalid part is invalid as a whole (CSS 2.1, Section 4.2)
+                        let values = expand_margin(declaration);
+                        let invalid = do values.iter().any |value| {
+                            match *value {
+                                Some(value) => parse_margin(value).is_none(),
+                                None => false
+                            }
+                        };
+                        if invalid {
+                            loop;
+                        }
+                        for (i, value) in values.move_iter().enumerate() {
+                            match value {
+                                Some(value) if winners[i].is_none() ||
+                                               precedence > winners[i].unwrap().first() => {
+                                    winners[i] = Some((precedence, value));
+                                }
+                                _ => ()
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // The winning values were validated when they won
+    let margin = |i: uint| match winners[i] {
+        Some((_, value)) => parse_margin(value),
+        None => None
+    };
+    PageStyle {
+        margin_top: margin(0),
+        margin_right: margin(1),
+        margin_bottom: margin(2),
+        margin_left: margin(3)
+    }
+}
+
+type PagePrecedence = (uint, uint, uint);
+
+/**
+Returns None if the page selector doesn't apply to this page. `:first` is
+more specific than `:left` and `:right`, which are more specific than no
+pseudo-class at all.
+*/
+fn page_selector_specificity(selector: &str, first: bool, side: PageSide) -> Option<uint> {
+    let mut specificity = 0;
+    for pseudo in selector.split_iter(':') {
+        let pseudo = pseudo.trim().to_ascii_lower();
+        match pseudo.as_slice() {
+            "" => (),
+            "first" if first => specificity += 2,
+            "left" if side == PageLeft => specificity += 1,
+            "right" if side == PageRight => specificity += 1,
+            _ => return None
+        }
+    }
+    Some(specificity)
+}
+
+/** Returns the value a declaration gives to each margin, in top, right, bottom, left order */
+fn expand_margin<'a>(declaration: &'a SourceDeclaration) -> ~[Option<&'a str>] {
+    let value = declaration.value.as_slice();
+    match declaration.name.as_slice() {
+        "margin-top" => ~[Some(value), None, None, None],
+        "margin-right" => ~[None, Some(value), None, None],
+        "margin-bottom" => ~[None, None, Some(value), None],
+        "margin-left" => ~[None, None, None, Some(value)],
+        "margin" => {
+            let parts: ~[&'a str] = value.word_iter().collect();
+            match parts.len() {
+                1 => ~[Some(parts[0]), Some(parts[0]), Some(parts[0]), Some(parts[0])],
+                2 => ~[Some(parts[0]), Some(parts[1]), Some(parts[0]), Some(parts[1])],
+                3 => ~[Some(parts[0]), Some(parts[1]), Some(parts[2]), Some(parts[1])],
+                4 => ~[Some(parts[0]), Some(parts[1]), Some(parts[2]), Some(parts[3])],
+                _ => ~[None, None, None, None]
+            }
+        }
+        _ => ~[None, None, None, None]
+    }
+}
+
+fn parse_margin(value: &str) -> Option<CSSMargin> {
+    if value.eq_ignore_ascii_case("auto") {
+        return Some(CSSMarginAuto);
+    }
+    match parse_length_or_percentage(value) {
+        Some(Left(length)) => Some(CSSMarginLength(length)),
+        Some(Right(percent)) => Some(CSSMarginPercentage(percent)),
+        None => None
+    }
+}
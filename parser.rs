@@ -9,27 +9,37 @@ Constructs a list of css style rules from a token stream
 // TODO: fail according to the css spec instead of failing when things
 // are not as expected
 
+use std::ascii::StrAsciiExt;
+use std::local_data;
+use std::str;
+use std::vec;
 use util::DataStream;
 use netsurfcss::stylesheet::{CssStylesheet, CssStylesheetParams, CssStylesheetParamsVersion1, css_stylesheet_create};
 use netsurfcss::types::CssLevel21;
 use netsurfcss::CssResult;
 use wapcaplet::LwcString;
+use lwcstr_from_rust_str = wapcaplet::from_rust_string;
 use extra::url::Url;
-use netsurfcss::stylesheet::CssUrlResolutionFn;
+use netsurfcss::stylesheet::{CssUrlResolutionFn, CssColorResolutionFn};
+use netsurfcss::types::CssColor;
+use netsurfcss::errors::CssInvalid;
+use color::{SystemColorPalette, parse_system_color};
+use types::MediaType;
 
-fn default_params(url: Url) -> CssStylesheetParams {
+fn default_params(url: Url, title: &str) -> CssStylesheetParams {
     let resolve: CssUrlResolutionFn = resolve_url;
+    let resolve_color: CssColorResolutionFn = resolve_system_color;
     CssStylesheetParams {
         params_version: CssStylesheetParamsVersion1,
         level: CssLevel21,
         charset: ~"UTF-8",
         url: url.to_str(),
-        title: ~"FIXME-css-title",
+        title: title.to_owned(),
         allow_quirks: false,
         inline_style: false,
         resolve: Some(resolve),
         import: None,
-        color: None,
+        color: Some(resolve_color),
         font: None,
     }
 }
@@ -39,31 +49,463 @@ fn default_params(url: Url) -> CssStylesheetParams {
 // so DataStream is an @fn which can't be sent to the lexer task.
 // So the DataStreamFactory gives the caller an opportunity to create
 // the data stream from inside the lexer task.
-pub fn parse_stylesheet(url: Url, input: @mut DataStream) -> CssStylesheet {
-    let params = default_params(url);
+//
+// libcss doesn't expose the rules it parsed, so the `@page` rules and the
+// selectors and declarations of style rules are recovered from the source
+// with `scan_rules`, which isn't kept. The selector parser shares its
+// tokenizing with the scanner. Untitled sheets are given an empty title.
+pub fn parse_stylesheet(url: Url,
+                        title: &str,
+                        palette: &SystemColorPalette,
+                        input: @mut DataStream) -> (CssStylesheet, ~[SourceRule]) {
+    let params = default_params(url, title);
     let mut sheet = css_stylesheet_create(&params);
+    let mut data = ~[];
 
-    loop {
-        match input.read() {
-            Some(data) => {
-                sheet.append_data(data);
+    do with_palette(palette) {
+        loop {
+            match input.read() {
+                Some(chunk) => {
+                    sheet.append_data(chunk);
+                    data.push_all(chunk);
+                }
+                None => break
             }
-            None => break
         }
+        sheet.data_done();
     }
-    sheet.data_done();
-    sheet
+
+    let rules = if str::is_utf8(data) { scan_rules(str::from_utf8(data)) } else { ~[] };
+    (sheet, rules)
 }
 
-pub fn parse_style_attribute(url: Url, data: &str) -> CssStylesheet {
-    let mut params = default_params(url);
+pub fn parse_style_attribute(url: Url, data: &str, palette: &SystemColorPalette) -> CssStylesheet {
+    let mut params = default_params(url, "");
     params.inline_style = true;
     let mut sheet = css_stylesheet_create(&params);
-    sheet.append_data(data.as_bytes());
-    sheet.data_done();
+    do with_palette(palette) {
+        sheet.append_data(data.as_bytes());
+        sheet.data_done();
+    }
     sheet
 }
 
-fn resolve_url(_base: &str, _rel: &LwcString) -> CssResult<LwcString> {
-    fail!(~"resolving url");
+// The palette `resolve_system_color` reads while a sheet is being parsed,
+// since libcss's callback takes nothing but the color's name
+static system_color_palette: local_data::Key<SystemColorPalette> = &local_data::Key;
+
+// Runs `f`, which parses a sheet, with `palette` for its system colors
+fn with_palette<R>(palette: &SystemColorPalette, f: &fn() -> R) -> R {
+    local_data::set(system_color_palette, palette.clone());
+    let result = f();
+    local_data::pop(system_color_palette);
+    result
+}
+
+// libcss asks us to make every url() absolute against the sheet's url
+fn resolve_url(base: &str, rel: &LwcString) -> CssResult<LwcString> {
+    Ok(lwcstr_from_rust_str(join_url(base, rel.to_str_slice())))
+}
+
+/**
+Resolve `url` against `base` (RFC 3986, section 5.2). A relative `url` is
+returned unchanged if `base` isn't absolute.
+*/
+pub fn join_url(base: &str, url: &str) -> ~str {
+    if has_scheme(url) || !has_scheme(base) {
+        return url.to_owned();
+    }
+    if url.starts_with("#") {
+        return base.slice_to(base.find('#').unwrap_or(base.len())) + url;
+    }
+    let base = base.slice_to(base.find(|c: char| c == '?' || c == '#').unwrap_or(base.len()));
+    let scheme_end = base.find(':').unwrap() + 1;
+    if url.starts_with("//") {
+        return base.slice_to(scheme_end) + url;
+    }
+    let path_start = if base.slice_from(scheme_end).starts_with("//") {
+        match base.slice_from(scheme_end + 2).find('/') {
+            Some(i) => scheme_end + 2 + i,
+            None => base.len()
+        }
+    } else {
+        scheme_end
+    };
+    let suffix_start = url.find(|c: char| c == '?' || c == '#').unwrap_or(url.len());
+    let (url_path, suffix) = (url.slice_to(suffix_start), url.slice_from(suffix_start));
+    let base_path = base.slice_from(path_start);
+    let path = if url_path.is_empty() {
+        base_path.to_owned()
+    } else if url_path.starts_with("/") {
+        url_path.to_owned()
+    } else {
+        match base_path.rfind('/') {
+            Some(i) => base_path.slice_to(i + 1) + url_path,
+            None => ~"/" + url_path
+        }
+    };
+    base.slice_to(path_start) + remove_dot_segments(path) + suffix
+}
+
+fn has_scheme(url: &str) -> bool {
+    match url.find(':') {
+        Some(end) => end > 0 && url.slice_to(end).iter().enumerate().all(|(i, c)| {
+            c.is_ascii() && (c.is_alphabetic() ||
+                             i > 0 && (c.is_digit() || c == '+' || c == '-' || c == '.'))
+        }),
+        None => false
+    }
+}
+
+fn remove_dot_segments(path: &str) -> ~str {
+    let parts: ~[&str] = path.split_iter('/').collect();
+    let mut segments: ~[&str] = ~[];
+    for (i, &part) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        match part {
+            "." => if last { segments.push("") },
+            ".." => {
+                // The leading empty segment of an absolute path stays
+                if segments.len() > 1 { segments.pop(); }
+                if last { segments.push("") }
+            }
+            _ => segments.push(part)
+        }
+    }
+    segments.connect("/")
+}
+
+// libcss asks us for the values of system colors, which come from the
+// palette the sheet is parsed with
+fn resolve_system_color(name: &LwcString) -> CssResult<CssColor> {
+    match parse_system_color(name.to_str_slice()) {
+        Some(system_color) => {
+            let color = do local_data::get(system_color_palette) |palette| {
+                match palette {
+                    Some(palette) => palette.get(system_color),
+                    None => SystemColorPalette::default().get(system_color)
+                }
+            };
+            Ok(CssColor {
+                r: color.red,
+                g: color.green,
+                b: color.blue,
+                a: 255
+            })
+        }
+        None => Err(CssInvalid)
+    }
+}
+
+/** A rule as written in the stylesheet source */
+pub struct SourceRule {
+    /** The lowercased name of an at-rule, without the '@' */
+    at_keyword: Option<~str>,
+    /** The selector list of a style rule, or whatever follows the at-keyword */
+    prelude: ~str,
+    declarations: ~[SourceDeclaration],
+    /** The 1-based line on which the rule starts */
+    line: uint,
+    /** The lowercased media list of the `@media` block the rule is in, if any */
+    media: Option<~[~str]>,
+}
+
+impl SourceRule {
+    /**
+    Whether the rule applies to `media`. libcss only knows media types, so a
+    media query with more than a type doesn't match.
+    */
+    pub fn applies_to(&self, media: MediaType) -> bool {
+        match self.media {
+            Some(ref list) => do list.iter().any |medium| {
+                medium.as_slice() == "all" || medium.as_slice() == media.name()
+            },
+            None => true
+        }
+    }
+}
+
+pub struct SourceDeclaration {
+    /** The lowercased property name */
+    name: ~str,
+    value: ~str,
+    important: bool,
+}
+
+/**
+Split stylesheet source into rules and declarations without interpreting them.
+
+This is not a validating parser - libcss remains the authority on what the
+cascade contains. The rules of `@media` blocks are read as if they were at
+the top level, keeping the media list, see `SourceRule::applies_to`.
+Block-less at-rules and the contents of at-rules other than `@page` are
+skipped.
+*/
+pub fn scan_rules(source: &str) -> ~[SourceRule] {
+    let source = strip_comments(source);
+    let mut rules = ~[];
+    scan_block(source, 0, source.len(), 1, &None, &mut rules);
+    rules
+}
+
+// Scans the rules from `start` to `end`, the first of which starts on `line`,
+// in a `@media` block for `media` if given
+fn scan_block(source: &str,
+              start: uint,
+              end: uint,
+              line: uint,
+              media: &Option<~[~str]>,
+              rules: &mut ~[SourceRule]) {
+    let bytes = source.slice_to(end).as_bytes();
+    let mut pos = start;
+    let mut line = line;
+    let mut counted_to = start;
+
+    loop {
+        pos = skip_whitespace(bytes, pos);
+        if pos >= bytes.len() {
+            break
+        }
+        line += count_newlines(bytes, counted_to, pos);
+        counted_to = pos;
+
+        let prelude_end = find_unquoted(bytes, pos, ['{' as u8, ';' as u8]);
+        if prelude_end >= bytes.len() {
+            break
+        }
+
+        if bytes[prelude_end] == ';' as u8 {
+            // @charset, @import and friends
+            pos = prelude_end + 1;
+        } else {
+            let block_end = find_block_end(bytes, prelude_end + 1);
+            let prelude = source.slice(pos, prelude_end).trim();
+            let block = source.slice(prelude_end + 1, block_end);
+
+            if prelude.starts_with("@") {
+                let keyword = at_keyword(prelude);
+                let rest = prelude.slice_from(1 + keyword.len()).trim();
+                if keyword.as_slice() == "page" {
+                    rules.push(SourceRule {
+                        prelude: rest.to_owned(),
+                        at_keyword: Some(keyword),
+                        declarations: scan_declarations(block),
+                        line: line,
+                        media: media.clone(),
+                    });
+                } else if keyword.as_slice() == "media" {
+                    let block_line = line + count_newlines(bytes, pos, prelude_end + 1);
+                    let list: ~[~str] = rest.split_iter(',')
+                        .map(|medium| medium.trim().to_ascii_lower()).collect();
+                    scan_block(source, prelude_end + 1, block_end, block_line, &Some(list),
+                               rules);
+                }
+            } else {
+                rules.push(SourceRule {
+                    at_keyword: None,
+                    prelude: prelude.to_owned(),
+                    declarations: scan_declarations(block),
+                    line: line,
+                    media: media.clone(),
+                });
+            }
+            pos = block_end + 1;
+        }
+    }
+}
+
+/** Split the contents of a declaration block, e.g. a style attribute */
+pub fn scan_declarations(block: &str) -> ~[SourceDeclaration] {
+    let bytes = block.as_bytes();
+    let mut declarations = ~[];
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let end = find_unquoted(bytes, pos, [';' as u8]);
+        let declaration = block.slice(pos, end);
+        let colon = find_unquoted(declaration.as_bytes(), 0, [':' as u8]);
+        if colon < declaration.len() {
+            let name = declaration.slice_to(colon).trim();
+            let mut value = declaration.slice_from(colon + 1).trim();
+            let mut important = false;
+            match value.rfind('!') {
+                Some(bang) if value.slice_from(bang + 1).trim().eq_ignore_ascii_case("important") => {
+                    value = value.slice_to(bang).trim();
+                    important = true;
+                }
+                _ => ()
+            }
+            if !name.is_empty() && !value.is_empty() {
+                declarations.push(SourceDeclaration {
+                    name: name.to_ascii_lower(),
+                    value: value.to_owned(),
+                    important: important,
+                });
+            }
+        }
+        pos = end + 1;
+    }
+    declarations
+}
+
+/** Reads an identifier, escapes included, returning it and the index just past it */
+pub fn read_ident(source: &str, start: uint) -> (~str, uint) {
+    let bytes = source.as_bytes();
+    let mut end = start;
+    while end < bytes.len() {
+        let b = bytes[end];
+        if b == '\\' as u8 && end + 1 < bytes.len() {
+            end += 2;
+        } else if (b as char).is_alphanumeric() || b == '-' as u8 || b == '_' as u8 || b >= 0x80 {
+            end += 1;
+        } else {
+            break;
+        }
+    }
+    (source.slice(start, end).to_owned(), end)
+}
+
+/** Blank out comments, keeping newlines so that line numbers survive */
+fn strip_comments(source: &str) -> ~str {
+    let bytes = source.as_bytes();
+    let mut out = vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == '"' as u8 || bytes[i] == '\'' as u8 {
+            let end = skip_string(bytes, i);
+            out.push_all(bytes.slice(i, end));
+            i = end;
+        } else if bytes[i] == '/' as u8 && i + 1 < bytes.len() && bytes[i + 1] == '*' as u8 {
+            let mut j = i + 2;
+            while j + 1 < bytes.len() && !(bytes[j] == '*' as u8 && bytes[j + 1] == '/' as u8) {
+                j += 1;
+            }
+            let end = if j + 1 < bytes.len() { j + 2 } else { bytes.len() };
+            for k in range(i, end) {
+                out.push(if bytes[k] == '\n' as u8 { '\n' as u8 } else { ' ' as u8 });
+            }
+            i = end;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    str::from_utf8(out)
+}
+
+/** Returns the index just past the string starting at `start` */
+pub fn skip_string(bytes: &[u8], start: uint) -> uint {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if bytes[i] == '\\' as u8 {
+            i += 2;
+        } else if bytes[i] == quote || bytes[i] == '\n' as u8 {
+            return i + 1;
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/** Find the first of `stops` outside of strings and brackets, or the end of input */
+pub fn find_unquoted(bytes: &[u8], start: uint, stops: &[u8]) -> uint {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == '"' as u8 || b == '\'' as u8 {
+            i = skip_string(bytes, i);
+        } else if b == '\\' as u8 {
+            i += 2;
+        } else {
+            if depth == 0 && stops.contains(&b) {
+                return i;
+            }
+            if b == '(' as u8 || b == '[' as u8 {
+                depth += 1;
+            } else if (b == ')' as u8 || b == ']' as u8) && depth > 0 {
+                depth -= 1;
+            }
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/** Find the '}' closing the block whose contents start at `start` */
+fn find_block_end(bytes: &[u8], start: uint) -> uint {
+    let mut depth = 1;
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == '"' as u8 || b == '\'' as u8 {
+            i = skip_string(bytes, i);
+        } else if b == '\\' as u8 {
+            i += 2;
+        } else {
+            if b == '{' as u8 {
+                depth += 1;
+            } else if b == '}' as u8 {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+pub fn skip_whitespace(bytes: &[u8], start: uint) -> uint {
+    let mut i = start;
+    while i < bytes.len() && is_whitespace(bytes[i]) {
+        i += 1;
+    }
+    i
+}
+
+fn count_newlines(bytes: &[u8], from: uint, to: uint) -> uint {
+    let mut count = 0;
+    for &b in bytes.slice(from, to).iter() {
+        if b == '\n' as u8 {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn at_keyword(prelude: &str) -> ~str {
+    let name = prelude.slice_from(1);
+    let end = match name.find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_')) {
+        Some(end) => end,
+        None => name.len()
+    };
+    name.slice_to(end).to_ascii_lower()
+}
+
+pub fn is_whitespace(b: u8) -> bool {
+    b == ' ' as u8 || b == '\t' as u8 || b == '\n' as u8 || b == '\r' as u8 || b == 0x0c
+}
+
+#[cfg(test)]
+mod test {
+    use super::join_url;
+
+    #[test]
+    fn test_join_url() {
+        let base = "http://foo.com/css/main.css?v=1#top";
+        assert_eq!(join_url(base, "a.png"), ~"http://foo.com/css/a.png");
+        assert_eq!(join_url(base, "../img/a.png?x#y"), ~"http://foo.com/img/a.png?x#y");
+        assert_eq!(join_url(base, "./a/./b/../c"), ~"http://foo.com/css/a/c");
+        assert_eq!(join_url(base, "../../../a.png"), ~"http://foo.com/a.png");
+        assert_eq!(join_url(base, "/a.png"), ~"http://foo.com/a.png");
+        assert_eq!(join_url(base, "//bar.com/a.png"), ~"http://bar.com/a.png");
+        assert_eq!(join_url(base, "#frag"), ~"http://foo.com/css/main.css?v=1#frag");
+        assert_eq!(join_url(base, "data:image/png,x"), ~"data:image/png,x");
+        assert_eq!(join_url("http://foo.com", "a.png"), ~"http://foo.com/a.png");
+        assert_eq!(join_url("not a url", "a.png"), ~"a.png");
+    }
 }
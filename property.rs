@@ -0,0 +1,459 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Properties as values, for code that deals with properties generically
+
+`ComputedStyle` has an accessor per property. `Property` names each of them,
+with the metadata from the CSS 2.1 property index, and `ComputedStyle::get`
+fetches any of them as a `PropertyValue`.
+*/
+
+use std::ascii::StrAsciiExt;
+use color::{Color, rgb};
+use damage::{RestyleDamage, NO_DAMAGE, REPAINT, REFLOW, REBUILD_BOX_TREE};
+use units::{Px, Medium, SansSerif};
+use values::*;
+
+/** The media groups of CSS 2.1, Section 7.3.1, that properties apply to */
+#[deriving(Eq, Clone)]
+pub enum MediaGroup {
+    MediaVisual,
+    MediaAural,
+    MediaPaged,
+    MediaInteractive,
+    MediaAll
+}
+
+#[deriving(Eq, Clone)]
+pub enum Property {
+    // CSS 2.1, Section 8 - Box model
+    PropMarginTop,
+    PropMarginRight,
+    PropMarginBottom,
+    PropMarginLeft,
+    PropPaddingTop,
+    PropPaddingRight,
+    PropPaddingBottom,
+    PropPaddingLeft,
+    PropBorderTopStyle,
+    PropBorderRightStyle,
+    PropBorderBottomStyle,
+    PropBorderLeftStyle,
+    PropBorderTopWidth,
+    PropBorderRightWidth,
+    PropBorderBottomWidth,
+    PropBorderLeftWidth,
+    PropBorderTopColor,
+    PropBorderRightColor,
+    PropBorderBottomColor,
+    PropBorderLeftColor,
+
+    // CSS 2.1, Section 9 - Visual formatting model
+    PropDisplay,
+    PropPosition,
+    PropFloat,
+    PropClear,
+    PropTop,
+    PropRight,
+    PropBottom,
+    PropLeft,
+
+    // CSS 2.1, Section 10 - Visual formatting model details
+    PropWidth,
+    PropHeight,
+    PropLineHeight,
+    PropVerticalAlign,
+
+    // CSS 2.1, Section 11 - Visual effects
+    PropZIndex,
+    PropClip,
+
+    // CSS Color Module Level 3, Section 3.2 - Transparency
+    PropOpacity,
+
+    // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
+    PropContent,
+    PropCounterReset,
+    PropCounterIncrement,
+    PropQuotes,
+    PropListStyleType,
+    PropListStylePosition,
+    PropListStyleImage,
+
+    // CSS 2.1, Section 13 - Paged media
+    PropPageBreakBefore,
+    PropPageBreakAfter,
+    PropPageBreakInside,
+    PropOrphans,
+    PropWidows,
+
+    // CSS 2.1, Section 14 - Colors and Backgrounds
+    PropBackgroundColor,
+    PropColor,
+
+    // CSS 2.1, Section 15 - Fonts
+    PropFontFamily,
+    PropFontStyle,
+    PropFontWeight,
+    PropFontSize,
+
+    // CSS 2.1, Section 16 - Text
+    PropTextAlign,
+    PropTextDecoration,
+
+    // CSS 2.1, Section 18 - User interface
+    PropCursor,
+    PropOutlineWidth,
+    PropOutlineStyle,
+    PropOutlineColor
+}
+
+/** A property value, tagged with the type of value */
+#[deriving(Eq)]
+pub enum PropertyValue {
+    PropertyValueMargin(CSSMargin),
+    PropertyValuePadding(CSSPadding),
+    PropertyValueBorderStyle(CSSBorderStyle),
+    PropertyValueBorderWidth(CSSBorderWidth),
+    PropertyValueBorderColor(CSSBorderColor),
+    PropertyValueDisplay(CSSDisplay),
+    PropertyValuePosition(CSSPosition),
+    PropertyValueFloat(CSSFloat),
+    PropertyValueClear(CSSClear),
+    PropertyValueTop(CSSTop),
+    PropertyValueRight(CSSRight),
+    PropertyValueBottom(CSSBottom),
+    PropertyValueLeft(CSSLeft),
+    PropertyValueWidth(CSSWidth),
+    PropertyValueHeight(CSSHeight),
+    PropertyValueLineHeight(CSSLineHeight),
+    PropertyValueVerticalAlign(CSSVerticalAlign),
+    PropertyValueZIndex(CSSZIndex),
+    PropertyValueClip(CSSClip),
+    PropertyValueOpacity(f64),
+    PropertyValueContent(CSSContent),
+    PropertyValueCounterReset(CSSCounterReset),
+    PropertyValueCounterIncrement(CSSCounterIncrement),
+    PropertyValueQuotes(CSSQuotes),
+    PropertyValueListStyleType(CSSListStyleType),
+    PropertyValueListStylePosition(CSSListStylePosition),
+    PropertyValueListStyleImage(CSSListStyleImage),
+    PropertyValuePageBreakBefore(CSSPageBreakBefore),
+    PropertyValuePageBreakAfter(CSSPageBreakAfter),
+    PropertyValuePageBreakInside(CSSPageBreakInside),
+    PropertyValueOrphans(int),
+    PropertyValueWidows(int),
+    PropertyValueBackgroundColor(CSSBackgroundColor),
+    PropertyValueColor(Color),
+    PropertyValueFontFamily(~[CSSFontFamily]),
+    PropertyValueFontStyle(CSSFontStyle),
+    PropertyValueFontWeight(CSSFontWeight),
+    PropertyValueFontSize(CSSFontSize),
+    PropertyValueTextAlign(CSSTextAlign),
+    PropertyValueTextDecoration(CSSTextDecoration),
+    PropertyValueCursor(CSSCursor),
+    PropertyValueOutlineColor(CSSOutlineColor)
+}
+
+static ALL_PROPERTIES: &'static [Property] = &[
+    PropMarginTop,
+    PropMarginRight,
+    PropMarginBottom,
+    PropMarginLeft,
+    PropPaddingTop,
+    PropPaddingRight,
+    PropPaddingBottom,
+    PropPaddingLeft,
+    PropBorderTopStyle,
+    PropBorderRightStyle,
+    PropBorderBottomStyle,
+    PropBorderLeftStyle,
+    PropBorderTopWidth,
+    PropBorderRightWidth,
+    PropBorderBottomWidth,
+    PropBorderLeftWidth,
+    PropBorderTopColor,
+    PropBorderRightColor,
+    PropBorderBottomColor,
+    PropBorderLeftColor,
+    PropDisplay,
+    PropPosition,
+    PropFloat,
+    PropClear,
+    PropTop,
+    PropRight,
+    PropBottom,
+    PropLeft,
+    PropWidth,
+    PropHeight,
+    PropLineHeight,
+    PropVerticalAlign,
+    PropZIndex,
+    PropClip,
+    PropOpacity,
+    PropContent,
+    PropCounterReset,
+    PropCounterIncrement,
+    PropQuotes,
+    PropListStyleType,
+    PropListStylePosition,
+    PropListStyleImage,
+    PropPageBreakBefore,
+    PropPageBreakAfter,
+    PropPageBreakInside,
+    PropOrphans,
+    PropWidows,
+    PropBackgroundColor,
+    PropColor,
+    PropFontFamily,
+    PropFontStyle,
+    PropFontWeight,
+    PropFontSize,
+    PropTextAlign,
+    PropTextDecoration,
+    PropCursor,
+    PropOutlineWidth,
+    PropOutlineStyle,
+    PropOutlineColor,
+];
+
+static VISUAL: &'static [MediaGroup] = &[MediaVisual];
+static VISUAL_PAGED: &'static [MediaGroup] = &[MediaVisual, MediaPaged];
+static VISUAL_INTERACTIVE: &'static [MediaGroup] = &[MediaVisual, MediaInteractive];
+static ALL: &'static [MediaGroup] = &[MediaAll];
+
+impl Property {
+    /** Every supported property, in the order of the CSS 2.1 chapters defining them */
+    pub fn all() -> &'static [Property] {
+        ALL_PROPERTIES
+    }
+
+    /** Looks up a property by name, ignoring ASCII case */
+    pub fn from_name(name: &str) -> Option<Property> {
+        for &property in ALL_PROPERTIES.iter() {
+            if name.eq_ignore_ascii_case(property.name()) {
+                return Some(property);
+            }
+        }
+        None
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            PropMarginTop => "margin-top",
+            PropMarginRight => "margin-right",
+            PropMarginBottom => "margin-bottom",
+            PropMarginLeft => "margin-left",
+            PropPaddingTop => "padding-top",
+            PropPaddingRight => "padding-right",
+            PropPaddingBottom => "padding-bottom",
+            PropPaddingLeft => "padding-left",
+            PropBorderTopStyle => "border-top-style",
+            PropBorderRightStyle => "border-right-style",
+            PropBorderBottomStyle => "border-bottom-style",
+            PropBorderLeftStyle => "border-left-style",
+            PropBorderTopWidth => "border-top-width",
+            PropBorderRightWidth => "border-right-width",
+            PropBorderBottomWidth => "border-bottom-width",
+            PropBorderLeftWidth => "border-left-width",
+            PropBorderTopColor => "border-top-color",
+            PropBorderRightColor => "border-right-color",
+            PropBorderBottomColor => "border-bottom-color",
+            PropBorderLeftColor => "border-left-color",
+            PropDisplay => "display",
+            PropPosition => "position",
+            PropFloat => "float",
+            PropClear => "clear",
+            PropTop => "top",
+            PropRight => "right",
+            PropBottom => "bottom",
+            PropLeft => "left",
+            PropWidth => "width",
+            PropHeight => "height",
+            PropLineHeight => "line-height",
+            PropVerticalAlign => "vertical-align",
+            PropZIndex => "z-index",
+            PropClip => "clip",
+            PropOpacity => "opacity",
+            PropContent => "content",
+            PropCounterReset => "counter-reset",
+            PropCounterIncrement => "counter-increment",
+            PropQuotes => "quotes",
+            PropListStyleType => "list-style-type",
+            PropListStylePosition => "list-style-position",
+            PropListStyleImage => "list-style-image",
+            PropPageBreakBefore => "page-break-before",
+            PropPageBreakAfter => "page-break-after",
+            PropPageBreakInside => "page-break-inside",
+            PropOrphans => "orphans",
+            PropWidows => "widows",
+            PropBackgroundColor => "background-color",
+            PropColor => "color",
+            PropFontFamily => "font-family",
+            PropFontStyle => "font-style",
+            PropFontWeight => "font-weight",
+            PropFontSize => "font-size",
+            PropTextAlign => "text-align",
+            PropTextDecoration => "text-decoration",
+            PropCursor => "cursor",
+            PropOutlineWidth => "outline-width",
+            PropOutlineStyle => "outline-style",
+            PropOutlineColor => "outline-color"
+        }
+    }
+
+    /** Whether the property is inherited by default */
+    pub fn is_inherited(&self) -> bool {
+        match *self {
+            PropLineHeight | PropQuotes | PropListStyleType | PropListStylePosition |
+            PropListStyleImage | PropOrphans | PropWidows | PropColor | PropFontFamily |
+            PropFontStyle | PropFontWeight | PropFontSize | PropTextAlign | PropCursor => true,
+            _ => false
+        }
+    }
+
+    /**
+    What a layout engine has to redo when the property's computed value
+    changes. Only 'cursor' can change without any damage.
+    */
+    pub fn damage(&self) -> RestyleDamage {
+        match *self {
+            PropDisplay | PropPosition | PropFloat | PropContent | PropCounterReset |
+            PropCounterIncrement | PropQuotes | PropListStyleType | PropListStylePosition |
+            PropListStyleImage => REBUILD_BOX_TREE,
+            PropBorderTopColor | PropBorderRightColor | PropBorderBottomColor |
+            PropBorderLeftColor | PropZIndex | PropClip | PropOpacity | PropBackgroundColor |
+            PropColor | PropTextDecoration | PropOutlineWidth | PropOutlineStyle |
+            PropOutlineColor => REPAINT,
+            PropCursor => NO_DAMAGE,
+            _ => REFLOW
+        }
+    }
+
+    /** The media groups the property applies to */
+    pub fn media_groups(&self) -> &'static [MediaGroup] {
+        match *self {
+            PropPageBreakBefore | PropPageBreakAfter | PropPageBreakInside | PropOrphans |
+            PropWidows => VISUAL_PAGED,
+            PropCursor | PropOutlineWidth | PropOutlineStyle |
+            PropOutlineColor => VISUAL_INTERACTIVE,
+            PropDisplay | PropContent | PropCounterReset | PropCounterIncrement => ALL,
+            _ => VISUAL
+        }
+    }
+
+    /**
+    The initial value. Where CSS 2.1 leaves it to the user agent, as for
+    'color' and 'font-family', this is libcss's default.
+    */
+    pub fn initial_value(&self) -> PropertyValue {
+        match *self {
+            PropMarginTop => PropertyValueMargin(CSSMarginLength(Px(0.0))),
+            PropMarginRight => PropertyValueMargin(CSSMarginLength(Px(0.0))),
+            PropMarginBottom => PropertyValueMargin(CSSMarginLength(Px(0.0))),
+            PropMarginLeft => PropertyValueMargin(CSSMarginLength(Px(0.0))),
+            PropPaddingTop => PropertyValuePadding(CSSPaddingLength(Px(0.0))),
+            PropPaddingRight => PropertyValuePadding(CSSPaddingLength(Px(0.0))),
+            PropPaddingBottom => PropertyValuePadding(CSSPaddingLength(Px(0.0))),
+            PropPaddingLeft => PropertyValuePadding(CSSPaddingLength(Px(0.0))),
+            PropBorderTopStyle => PropertyValueBorderStyle(CSSBorderStyleNone),
+            PropBorderRightStyle => PropertyValueBorderStyle(CSSBorderStyleNone),
+            PropBorderBottomStyle => PropertyValueBorderStyle(CSSBorderStyleNone),
+            PropBorderLeftStyle => PropertyValueBorderStyle(CSSBorderStyleNone),
+            PropBorderTopWidth => PropertyValueBorderWidth(CSSBorderWidthMedium),
+            PropBorderRightWidth => PropertyValueBorderWidth(CSSBorderWidthMedium),
+            PropBorderBottomWidth => PropertyValueBorderWidth(CSSBorderWidthMedium),
+            PropBorderLeftWidth => PropertyValueBorderWidth(CSSBorderWidthMedium),
+            PropBorderTopColor => PropertyValueBorderColor(CSSBorderColorCurrentColor),
+            PropBorderRightColor => PropertyValueBorderColor(CSSBorderColorCurrentColor),
+            PropBorderBottomColor => PropertyValueBorderColor(CSSBorderColorCurrentColor),
+            PropBorderLeftColor => PropertyValueBorderColor(CSSBorderColorCurrentColor),
+            PropDisplay => PropertyValueDisplay(CSSDisplayInline),
+            PropPosition => PropertyValuePosition(CSSPositionStatic),
+            PropFloat => PropertyValueFloat(CSSFloatNone),
+            PropClear => PropertyValueClear(CSSClearNone),
+            PropTop => PropertyValueTop(CSSTopAuto),
+            PropRight => PropertyValueRight(CSSRightAuto),
+            PropBottom => PropertyValueBottom(CSSBottomAuto),
+            PropLeft => PropertyValueLeft(CSSLeftAuto),
+            PropWidth => PropertyValueWidth(CSSWidthAuto),
+            PropHeight => PropertyValueHeight(CSSHeightAuto),
+            PropLineHeight => PropertyValueLineHeight(CSSLineHeightNormal),
+            PropVerticalAlign => PropertyValueVerticalAlign(CSSVerticalAlignBaseline),
+            PropZIndex => PropertyValueZIndex(CSSZIndexAuto),
+            PropClip => PropertyValueClip(CSSClipAuto),
+            PropOpacity => PropertyValueOpacity(1.0),
+            PropContent => PropertyValueContent(CSSContentNormal),
+            PropCounterReset => PropertyValueCounterReset(CSSCounterResetNone),
+            PropCounterIncrement => PropertyValueCounterIncrement(CSSCounterIncrementNone),
+            PropQuotes => PropertyValueQuotes(CSSQuotesNone),
+            PropListStyleType => PropertyValueListStyleType(CSSListStyleTypeDisc),
+            PropListStylePosition => PropertyValueListStylePosition(CSSListStylePositionOutside),
+            PropListStyleImage => PropertyValueListStyleImage(CSSListStyleImageNone),
+            PropPageBreakBefore => PropertyValuePageBreakBefore(CSSPageBreakBeforeAuto),
+            PropPageBreakAfter => PropertyValuePageBreakAfter(CSSPageBreakAfterAuto),
+            PropPageBreakInside => PropertyValuePageBreakInside(CSSPageBreakInsideAuto),
+            PropOrphans => PropertyValueOrphans(2),
+            PropWidows => PropertyValueWidows(2),
+            PropBackgroundColor => PropertyValueBackgroundColor(CSSBackgroundColorTransparent),
+            PropColor => PropertyValueColor(rgb(0, 0, 0)),
+            PropFontFamily => PropertyValueFontFamily(~[CSSFontFamilyGenericFamily(SansSerif)]),
+            PropFontStyle => PropertyValueFontStyle(CSSFontStyleNormal),
+            PropFontWeight => PropertyValueFontWeight(CSSFontWeightNormal),
+            PropFontSize => PropertyValueFontSize(CSSFontSizeAbsoluteSize(Medium)),
+            PropTextAlign => PropertyValueTextAlign(CSSTextAlignLeft),
+            PropTextDecoration => PropertyValueTextDecoration(CSSTextDecorationNone),
+            PropCursor => PropertyValueCursor(CSSCursor { urls: ~[], keyword: CSSCursorAuto }),
+            PropOutlineWidth => PropertyValueBorderWidth(CSSBorderWidthMedium),
+            PropOutlineStyle => PropertyValueBorderStyle(CSSBorderStyleNone),
+            PropOutlineColor => PropertyValueOutlineColor(CSSOutlineColorInvert)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use values::{CSSMarginLength, CSSFontSizeAbsoluteSize, CSSBorderWidthMedium};
+    use units::{Px, Medium};
+    use damage::{NO_DAMAGE, REPAINT, REFLOW, REBUILD_BOX_TREE};
+
+    #[test]
+    fn test_names() {
+        for &property in Property::all().iter() {
+            assert!(Property::from_name(property.name()) == Some(property));
+        }
+        assert!(Property::from_name("Margin-Top") == Some(PropMarginTop));
+        assert!(Property::from_name("z-index") == Some(PropZIndex));
+        assert!(Property::from_name("margin") == None);
+        assert!(Property::from_name("") == None);
+    }
+
+    #[test]
+    fn test_metadata() {
+        // `all` lists every variant once, in declaration order, ending with the last one
+        for (i, &property) in Property::all().iter().enumerate() {
+            assert!(property as uint == i);
+            assert!(Property::from_name(property.name()) == Some(property));
+        }
+        assert!(Property::all().len() == PropOutlineColor as uint + 1);
+        assert!(PropColor.is_inherited());
+        assert!(PropFontSize.is_inherited());
+        assert!(!PropMarginTop.is_inherited());
+        assert!(!PropPageBreakInside.is_inherited());
+        assert!(PropWidth.media_groups().to_owned() == ~[MediaVisual]);
+        assert!(PropWidows.media_groups().to_owned() == ~[MediaVisual, MediaPaged]);
+        assert!(PropContent.media_groups().to_owned() == ~[MediaAll]);
+        assert!(PropMarginLeft.initial_value() == PropertyValueMargin(CSSMarginLength(Px(0.0))));
+        let medium = CSSFontSizeAbsoluteSize(Medium);
+        assert!(PropFontSize.initial_value() == PropertyValueFontSize(medium));
+        assert!(PropOutlineWidth.initial_value() == PropertyValueBorderWidth(CSSBorderWidthMedium));
+        assert!(PropOrphans.initial_value() == PropertyValueOrphans(2));
+        assert!(PropDisplay.damage() == REBUILD_BOX_TREE);
+        assert!(PropFontSize.damage() == REFLOW);
+        assert!(PropBackgroundColor.damage() == REPAINT);
+        assert!(PropCursor.damage() == NO_DAMAGE);
+    }
+}
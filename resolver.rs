@@ -0,0 +1,163 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Style resolution for a whole tree
+
+`StyleResolver` walks a tree in document order, selecting the style of each
+node and completing it against its parent's, so embedders don't have to
+write that loop themselves. It can keep an ancestor bloom filter and share
+styles between siblings along the way.
+*/
+
+use select::{SelectCtx, SelectHandler};
+use stylesheet::Stylesheet;
+use complete::CompleteSelectResults;
+use sharing::StyleSharingCache;
+use bloom::AncestorBloomFilter;
+use types::{MediaType, MediaTypeScreen};
+use units::Viewport;
+use util::VoidPtrLike;
+
+/** The structure of the tree, beyond the parent links `SelectHandler` gives */
+pub trait StyleTree<N> {
+    /** The element children of `node`, in document order */
+    fn children(&self, node: &N) -> ~[N];
+    fn with_inline_style<R>(&self, node: &N, f: &fn(Option<&Stylesheet>) -> R) -> R;
+}
+
+pub struct StyleResolver<'self> {
+    ctx: &'self SelectCtx,
+    viewport: Viewport,
+    /** The media type styles are selected for, the screen unless set */
+    media: MediaType,
+    /** Used to reject descendant selectors early, if enabled */
+    bloom: Option<AncestorBloomFilter>,
+    /** Used to share styles between siblings, if enabled, and its statistics */
+    sharing: Option<StyleSharingCache<@CompleteSelectResults>>
+}
+
+impl<'self> StyleResolver<'self> {
+    pub fn new(ctx: &'self SelectCtx, viewport: Viewport) -> StyleResolver<'self> {
+        StyleResolver {
+            ctx: ctx,
+            viewport: viewport,
+            media: MediaTypeScreen,
+            bloom: None,
+            sharing: None
+        }
+    }
+
+    pub fn set_media(&mut self, media: MediaType) {
+        self.media = media;
+    }
+
+    pub fn enable_bloom_filter(&mut self) {
+        if self.bloom.is_none() {
+            self.bloom = Some(AncestorBloomFilter::new());
+        }
+    }
+
+    pub fn enable_style_sharing(&mut self) {
+        if self.sharing.is_none() {
+            self.sharing = Some(StyleSharingCache::new());
+        }
+    }
+
+    /**
+    Computes the complete style of `root`, the root element of the document,
+    and of all its descendants, in document order. `sink` gets each node
+    after its parent and before its children.
+    */
+    pub fn resolve<N: VoidPtrLike, H: SelectHandler<N>, T: StyleTree<N>>(
+            &mut self,
+            root: &N,
+            handler: &H,
+            tree: &T,
+            sink: &fn(&N, &CompleteSelectResults)) {
+        // The DOM may have changed since the last resolution
+        for bloom in self.bloom.mut_iter() {
+            bloom.clear();
+        }
+        for cache in self.sharing.mut_iter() {
+            cache.clear();
+        }
+        self.resolve_subtree(root, None, handler, tree, sink);
+    }
+
+    fn resolve_subtree<N: VoidPtrLike, H: SelectHandler<N>, T: StyleTree<N>>(
+            &mut self,
+            node: &N,
+            parent: Option<@CompleteSelectResults>,
+            handler: &H,
+            tree: &T,
+            sink: &fn(&N, &CompleteSelectResults)) {
+        let results = self.resolve_node(node, parent, handler, tree);
+        sink(node, &*results);
+
+        let children = tree.children(node);
+        if children.is_empty() {
+            return;
+        }
+        for bloom in self.bloom.mut_iter() {
+            bloom.push(node, handler);
+        }
+        for child in children.iter() {
+            self.resolve_subtree(child, Some(results), handler, tree, |n, r| sink(n, r));
+        }
+        for bloom in self.bloom.mut_iter() {
+            bloom.pop();
+        }
+    }
+
+    fn resolve_node<N: VoidPtrLike, H: SelectHandler<N>, T: StyleTree<N>>(
+            &mut self,
+            node: &N,
+            parent: Option<@CompleteSelectResults>,
+            handler: &H,
+            tree: &T) -> @CompleteSelectResults {
+        do tree.with_inline_style(node) |inline_style| {
+            let key = match self.sharing {
+                Some(_) => self.ctx.sharing_key(node, inline_style, handler),
+                None => None
+            };
+            let shared = match (&mut self.sharing, &key) {
+                (&Some(ref mut cache), &Some(ref key)) => cache.find(key),
+                (&Some(ref mut cache), &None) => {
+                    cache.ineligible += 1;
+                    None
+                }
+                _ => None
+            };
+
+            match shared {
+                Some(results) => results,
+                None => {
+                    let selected = match self.bloom {
+                        Some(ref bloom) => {
+                            self.ctx.select_style_with_bloom(node, self.media, inline_style,
+                                                             handler, bloom)
+                        }
+                        None => {
+                            self.ctx.select_style_for_media(node, self.media, inline_style,
+                                                            handler)
+                        }
+                    };
+                    let results = @match parent {
+                        Some(parent) => CompleteSelectResults::new_from_parent(parent, selected),
+                        None => {
+                            CompleteSelectResults::new_root_in_viewport(selected,
+                                                                        self.viewport.clone())
+                        }
+                    };
+                    match (&mut self.sharing, key) {
+                        (&Some(ref mut cache), Some(key)) => cache.insert(key, results),
+                        _ => ()
+                    }
+                    results
+                }
+            }
+        }
+    }
+}
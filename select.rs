@@ -10,17 +10,107 @@ about the DOM are encapsulated in the `SelectHandler` type which the `SelectCtx`
 uses to query various DOM and UA properties.
 */
 
+use std::cast;
+use std::util;
 use stylesheet::Stylesheet;
 use computed::ComputedStyle;
 use util::VoidPtrLike;
 use wapcaplet::LwcString;
 use lwcstr_from_rust_str = wapcaplet::from_rust_string;
 use n::u::{rust_str_to_net_qname, net_qname_to_rust_str};
-use types::StylesheetOrigin;
+use types::{StylesheetOrigin, PseudoElement, MediaType, MediaTypeScreen};
+use parser::SourceRule;
+use page::{PageSide, PageStyle, cascade_page_rules};
+use matched::{MatchedRule, match_rules};
+use sharing::{StyleSharingCache, SharingKey};
+use bloom::AncestorBloomFilter;
+use color::SystemColorPalette;
+use invalidation::{InvalidationMap, InvalidationScope, InvalidateNothing, ElementChange};
+use netsurfcss::stylesheet::CssStylesheet;
 use n;
 
 pub struct SelectCtx {
-    inner: n::s::CssSelectCtx
+    // libcss can only have sheets appended, so any other change to the sheets
+    // in the cascade replaces the context, handing the new one the same sheets
+    inner: n::s::CssSelectCtx,
+    // What we keep of each sheet after handing it to libcss, in cascade order
+    sheets: ~[SheetInfo],
+    next_handle: uint,
+    // The stylesheet set chosen with `select_sheet_set`, overriding the preferred one
+    selected_set: Option<~str>,
+    // The system colors every sheet of the context has to be parsed with
+    palette: SystemColorPalette
+}
+
+/** Identifies a stylesheet appended to a `SelectCtx` */
+#[deriving(Eq, Clone)]
+pub struct SheetHandle(uint);
+
+/** A stylesheet of a `SelectCtx`, as listed by `SelectCtx::sheets` */
+#[deriving(Eq, Clone)]
+pub struct SheetStatus {
+    handle: SheetHandle,
+    origin: StylesheetOrigin,
+    title: Option<~str>,
+    alternate: bool,
+    /** Whether the sheet was disabled with `SelectCtx::set_sheet_disabled` */
+    disabled: bool,
+    /** Whether the sheet takes part in the cascade, i.e. is enabled and in the current set */
+    applied: bool
+}
+
+struct SheetInfo {
+    handle: SheetHandle,
+    origin: StylesheetOrigin,
+    palette: SystemColorPalette,
+    rules: ~[SourceRule],
+    sibling_sensitive: bool,
+    invalidation: InvalidationMap,
+    title: Option<~str>,
+    alternate: bool,
+    disabled: bool,
+    // Whether libcss has the sheet
+    attached: bool,
+    // The parsed sheet, while libcss doesn't have it
+    parsed: Option<CssStylesheet>
+}
+
+// The layout of `n::s::CssSelectCtx`, which owns the sheets appended to it
+// but has no way to give them back
+struct CssSelectCtxParts {
+    select_ctx: *n::ll::s::css_select_ctx,
+    sheets: ~[CssStylesheet]
+}
+
+// Destroys a libcss context, giving back its sheets in the order they were
+// appended. FIXME: netsurfcss has no binding for this, so it relies on the
+// layout of `CssSelectCtx`.
+fn take_sheets(ctx: n::s::CssSelectCtx) -> ~[CssStylesheet] {
+    unsafe {
+        let parts: CssSelectCtxParts = cast::transmute(ctx);
+        n::ll::s::css_select_ctx_destroy(parts.select_ctx);
+        parts.sheets
+    }
+}
+
+impl SheetInfo {
+    fn is_attached(&self) -> bool {
+        self.attached
+    }
+
+    fn take_parsed(&mut self) -> CssStylesheet {
+        self.parsed.take_unwrap()
+    }
+
+    // Persistent sheets are in every set, titled ones only in the set of
+    // their name, and untitled alternates in none
+    fn is_in_set(&self, set: Option<&str>) -> bool {
+        match (&self.title, set) {
+            (&Some(ref title), Some(set)) => title.as_slice() == set,
+            (&Some(_), None) => false,
+            (&None, _) => !self.alternate
+        }
+    }
 }
 
 /**
@@ -32,21 +122,238 @@ the `select_style` method.
 */
 impl SelectCtx {
     pub fn new() -> SelectCtx {
+        SelectCtx::new_with_palette(SystemColorPalette::default())
+    }
+
+    /**
+    A context for sheets taking system colors like `ButtonFace` from
+    `palette`, see `Stylesheet::new_with_palette`. After the palette changes,
+    e.g. with the desktop theme, the sheets have to be parsed again into a
+    new context.
+    */
+    pub fn new_with_palette(palette: SystemColorPalette) -> SelectCtx {
         SelectCtx {
-            inner: n::s::css_select_ctx_create()
+            inner: n::s::css_select_ctx_create(),
+            sheets: ~[],
+            next_handle: 0,
+            selected_set: None,
+            palette: palette
         }
     }
 
+    /** The palette the sheets of the context take system colors from */
+    pub fn system_color_palette<'a>(&'a self) -> &'a SystemColorPalette {
+        &self.palette
+    }
+
     /**
     Add `Stylesheet`s to the selection context, where they will participate in the cascade
-    during future selector matching
+    during future selector matching. Fails if the sheet was parsed with another
+    palette than the context's.
+    */
+    pub fn append_sheet(&mut self, sheet: Stylesheet, origin: StylesheetOrigin) -> SheetHandle {
+        let position = self.sheets.len();
+        self.insert_sheet_at(position, sheet, origin)
+    }
+
+    /**
+    Add a `Stylesheet` to the cascade just before the sheet `before`, e.g. to
+    keep sheets in document order. Fails if `before` isn't in the context.
+    Unlike appending, this hands libcss every applied sheet again, which
+    doesn't parse them again but takes time linear in the number of sheets.
+    */
+    pub fn insert_sheet_before(&mut self,
+                               sheet: Stylesheet,
+                               origin: StylesheetOrigin,
+                               before: SheetHandle) -> SheetHandle {
+        let position = self.position(before);
+        self.insert_sheet_at(position, sheet, origin)
+    }
+
+    /**
+    Take a sheet out of the cascade, giving it back, e.g. to be inserted again
+    elsewhere. Fails if `handle` isn't in the context. Removing an applied
+    sheet hands libcss the other applied sheets again, like
+    `insert_sheet_before`.
     */
-    pub fn append_sheet(&mut self, sheet: Stylesheet, origin: StylesheetOrigin) {
-        let sheet = match sheet {
-            Stylesheet { inner: inner } => inner
+    pub fn remove_sheet(&mut self, handle: SheetHandle) -> Stylesheet {
+        let position = self.position(handle);
+        if self.sheets[position].attached {
+            self.reset_inner();
+        }
+        let mut info = self.sheets.remove(position);
+        let inner = info.take_parsed();
+        // The sheet may have been the one naming the preferred set
+        self.update_sheet_set();
+        let SheetInfo { palette: palette, rules: rules, sibling_sensitive: sibling_sensitive,
+                        invalidation: invalidation, title: title, alternate: alternate, _ } = info;
+        Stylesheet {
+            inner: inner,
+            palette: palette,
+            rules: rules,
+            sibling_sensitive: sibling_sensitive,
+            invalidation: invalidation,
+            title: title,
+            alternate: alternate
+        }
+    }
+
+    /**
+    Disable or re-enable a sheet, as for `<link disabled>`. A disabled sheet
+    keeps its place in the cascade but takes no part in selector matching.
+    Fails if `handle` isn't in the context. Unless the sheet comes after every
+    applied one, this hands libcss the applied sheets again, like
+    `insert_sheet_before`.
+    */
+    pub fn set_sheet_disabled(&mut self, handle: SheetHandle, disabled: bool) {
+        let position = self.position(handle);
+        self.sheets[position].disabled = disabled;
+        self.update_sheet_set();
+    }
+
+    /** The sheets of the context, in cascade order */
+    pub fn sheets(&self) -> ~[SheetStatus] {
+        do self.sheets.map |sheet| {
+            SheetStatus {
+                handle: sheet.handle,
+                origin: sheet.origin,
+                title: sheet.title.clone(),
+                alternate: sheet.alternate,
+                disabled: sheet.disabled,
+                applied: sheet.is_attached()
+            }
+        }
+    }
+
+    /**
+    The names of the stylesheet sets, in cascade order and without duplicates,
+    e.g. for a "Page Style" menu
+    */
+    pub fn sheet_sets(&self) -> ~[~str] {
+        let mut sets: ~[~str] = ~[];
+        for sheet in self.sheets.iter() {
+            for title in sheet.title.iter() {
+                if !sets.contains(title) {
+                    sets.push(title.clone());
+                }
+            }
+        }
+        sets
+    }
+
+    /**
+    The preferred stylesheet set, named by the first titled sheet that isn't
+    an alternate
+    */
+    pub fn preferred_sheet_set(&self) -> Option<~str> {
+        for sheet in self.sheets.iter() {
+            if !sheet.alternate && sheet.title.is_some() {
+                return sheet.title.clone();
+            }
+        }
+        None
+    }
+
+    /** The stylesheet set in the cascade: the selected one, or else the preferred one */
+    pub fn current_sheet_set(&self) -> Option<~str> {
+        match self.selected_set {
+            Some(ref set) => Some(set.clone()),
+            None => self.preferred_sheet_set()
+        }
+    }
+
+    /**
+    Select the stylesheet set named `set`, or go back to the preferred one
+    with `None`. Titled sheets of other sets are taken out of the cascade;
+    untitled sheets always apply. Selecting a set no sheet has leaves only
+    the untitled sheets.
+    */
+    pub fn select_sheet_set(&mut self, set: Option<&str>) {
+        self.selected_set = set.map(|set| set.to_owned());
+        self.update_sheet_set();
+    }
+
+    fn insert_sheet_at(&mut self,
+                       position: uint,
+                       sheet: Stylesheet,
+                       origin: StylesheetOrigin) -> SheetHandle {
+        if sheet.palette != self.palette {
+            fail!("stylesheet was parsed with another palette than the select context's");
+        }
+        let handle = SheetHandle(self.next_handle);
+        self.next_handle += 1;
+        let info = match sheet {
+            Stylesheet { inner: inner, palette: palette, rules: rules,
+                         sibling_sensitive: sibling_sensitive, invalidation: invalidation,
+                         title: title, alternate: alternate } => {
+                SheetInfo {
+                    handle: handle,
+                    origin: origin,
+                    palette: palette,
+                    rules: rules,
+                    sibling_sensitive: sibling_sensitive,
+                    invalidation: invalidation,
+                    title: title,
+                    alternate: alternate,
+                    disabled: false,
+                    // Handed to libcss by `update_sheet_set` if it applies
+                    attached: false,
+                    parsed: Some(inner)
+                }
+            }
         };
 
-        self.inner.append_sheet(sheet, origin.to_net(), n::ll::t::CSS_MEDIA_SCREEN)
+        self.sheets.insert(position, info);
+        self.update_sheet_set();
+        handle
+    }
+
+    // Hands libcss the sheets that are enabled and in the current set. Unless
+    // that only takes appending sheets, libcss is given a new context first.
+    fn update_sheet_set(&mut self) {
+        let current = self.current_sheet_set();
+        let applies = do self.sheets.map |sheet| {
+            !sheet.disabled && sheet.is_in_set(current.map(|set| set.as_slice()))
+        };
+        let mut appending = true;
+        let mut added = false;
+        for (sheet, &applies) in self.sheets.iter().zip(applies.iter()) {
+            if sheet.attached {
+                appending = appending && applies && !added;
+            } else {
+                added = added || applies;
+            }
+        }
+        if !appending {
+            self.reset_inner();
+        }
+        for (sheet, &applies) in self.sheets.mut_iter().zip(applies.iter()) {
+            if applies && !sheet.attached {
+                // The media styles are for is chosen when selecting them
+                self.inner.append_sheet(sheet.take_parsed(), sheet.origin.to_net(),
+                                        n::ll::t::CSS_MEDIA_ALL);
+                sheet.attached = true;
+            }
+        }
+    }
+
+    // Replaces the libcss context with an empty one, for `update_sheet_set` to
+    // fill, taking back the sheets of the old one. libcss has the attached
+    // sheets in cascade order, since `update_sheet_set` only ever appends.
+    fn reset_inner(&mut self) {
+        let old = util::replace(&mut self.inner, n::s::css_select_ctx_create());
+        let mut parsed = take_sheets(old).move_iter();
+        for sheet in self.sheets.mut_iter().filter(|sheet| sheet.attached) {
+            sheet.parsed = parsed.next();
+            sheet.attached = false;
+        }
+    }
+
+    fn position(&self, handle: SheetHandle) -> uint {
+        match self.sheets.iter().position(|sheet| sheet.handle == handle) {
+            Some(position) => position,
+            None => fail!(fmt!("stylesheet %? is not in the select context", handle))
+        }
     }
 
     /**
@@ -58,8 +365,47 @@ impl SelectCtx {
                                                              node: &N,
                                                              inline_style: Option<&Stylesheet>,
                                                              handler: &H) -> SelectResults {
+        self.select_style_inner(node, MediaTypeScreen, inline_style, handler, None)
+    }
+
+    /**
+    Select the style for a single node like `select_style`, for `media`
+    instead of the screen, e.g. for printing
+    */
+    pub fn select_style_for_media<N: VoidPtrLike, H: SelectHandler<N>>(
+            &self,
+            node: &N,
+            media: MediaType,
+            inline_style: Option<&Stylesheet>,
+            handler: &H) -> SelectResults {
+        self.select_style_inner(node, media, inline_style, handler, None)
+    }
+
+    /**
+    Select the style for a single node like `select_style_for_media`, rejecting
+    descendant selectors early with `bloom`, which has to hold exactly the
+    ancestors of `node`.
+    */
+    pub fn select_style_with_bloom<N: VoidPtrLike, H: SelectHandler<N>>(
+            &self,
+            node: &N,
+            media: MediaType,
+            inline_style: Option<&Stylesheet>,
+            handler: &H,
+            bloom: &AncestorBloomFilter) -> SelectResults {
+        self.select_style_inner(node, media, inline_style, handler, Some(bloom))
+    }
+
+    fn select_style_inner<N: VoidPtrLike, H: SelectHandler<N>>(&self,
+                                                               node: &N,
+                                                               media: MediaType,
+                                                               inline_style: Option<&Stylesheet>,
+                                                               handler: &H,
+                                                               bloom: Option<&AncestorBloomFilter>)
+                                                               -> SelectResults {
         let inner_handler = SelectHandlerWrapper {
-            inner: handler
+            inner: handler,
+            bloom: bloom.map(|&bloom| bloom as *AncestorBloomFilter)
         };
         let inner_inline_style = match inline_style {
             None => None,
@@ -68,11 +414,142 @@ impl SelectCtx {
         SelectResults {
             inner: self.inner.select_style::<N, SelectHandlerWrapper<N, H>>(
                 node,
-                n::ll::t::CSS_MEDIA_SCREEN,
+                media.to_net(),
                 inner_inline_style,
                 &inner_handler)
         }
     }
+
+    /**
+    Select the style for a single node like `select_style_for_media`, but
+    reuse the results of an indistinguishable sibling from `cache` when
+    possible. Nodes with an inline style never share. `cache` has to be used
+    for a single media type.
+    */
+    pub fn select_style_shared<N: VoidPtrLike, H: SelectHandler<N>>(
+            &self,
+            node: &N,
+            media: MediaType,
+            inline_style: Option<&Stylesheet>,
+            handler: &H,
+            cache: &mut StyleSharingCache<@SelectResults>) -> @SelectResults {
+        match self.sharing_key(node, inline_style, handler) {
+            None => {
+                cache.ineligible += 1;
+                @self.select_style_for_media(node, media, inline_style, handler)
+            }
+            Some(key) => {
+                match cache.find(&key) {
+                    Some(results) => results,
+                    None => {
+                        let results = @self.select_style_for_media(node, media, None, handler);
+                        cache.insert(key, results);
+                        results
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+    What `node` can share its style by, or `None` if it's not a candidate for
+    sharing, given the sheets of the context
+    */
+    pub fn sharing_key<N: VoidPtrLike, H: SelectHandler<N>>(&self,
+                                                            node: &N,
+                                                            inline_style: Option<&Stylesheet>,
+                                                            handler: &H) -> Option<SharingKey> {
+        if inline_style.is_none() && !self.is_sibling_sensitive() {
+            SharingKey::for_node(node, handler)
+        } else {
+            None
+        }
+    }
+
+    fn is_sibling_sensitive(&self) -> bool {
+        self.sheets.iter().any(|sheet| sheet.is_attached() && sheet.sibling_sensitive)
+    }
+
+    /**
+    Which elements may need their style selected again after `change` to
+    `node`, relative to `node`
+    */
+    pub fn invalidate<N, H: SelectHandler<N>>(&self,
+                                              node: &N,
+                                              change: &ElementChange,
+                                              handler: &H) -> InvalidationScope {
+        do handler.with_node_name(node) |name| {
+            let mut scope = InvalidateNothing;
+            for sheet in self.sheets.iter().filter(|sheet| sheet.is_attached()) {
+                scope = scope.union(sheet.invalidation.scope_for(name, change));
+            }
+            scope
+        }
+    }
+
+    /**
+    Select the style of a page box for `media` from the `@page` rules of the
+    appended sheets. `first` is set for the first page of the document, which
+    is also a left or right page.
+    */
+    pub fn select_page_style(&self, media: MediaType, first: bool, side: PageSide) -> PageStyle {
+        let mut sheets = ~[];
+        for sheet in self.sheets.iter().filter(|sheet| sheet.is_attached()) {
+            let rules: &[SourceRule] = sheet.rules;
+            sheets.push((sheet.origin, rules));
+        }
+        cascade_page_rules(sheets, media, first, side)
+    }
+
+    /**
+    The style rules of the cascade matching `node`, in cascade order, with
+    the selector that matched, its specificity and where the rule comes from,
+    and which of its declarations were overridden, for `media`. The
+    declarations of `inline_style` come last, as a rule without a sheet. This
+    is for inspectors: `matched::match_rules` tells what it can't see that
+    libcss can.
+    */
+    pub fn matched_rules<N, H: SelectHandler<N>>(&self,
+                                                 node: &N,
+                                                 media: MediaType,
+                                                 inline_style: Option<&Stylesheet>,
+                                                 handler: &H) -> ~[MatchedRule] {
+        self.matched_rules_inner(node, media, inline_style, handler, None)
+    }
+
+    /**
+    Like `matched_rules`, but rejecting descendant selectors by the names, ids
+    and classes of the ancestors in `bloom`, which has to hold exactly the
+    ancestors of `node`.
+    */
+    pub fn matched_rules_with_bloom<N, H: SelectHandler<N>>(
+            &self,
+            node: &N,
+            media: MediaType,
+            inline_style: Option<&Stylesheet>,
+            handler: &H,
+            bloom: &AncestorBloomFilter) -> ~[MatchedRule] {
+        self.matched_rules_inner(node, media, inline_style, handler, Some(bloom))
+    }
+
+    fn matched_rules_inner<N, H: SelectHandler<N>>(&self,
+                                                   node: &N,
+                                                   media: MediaType,
+                                                   inline_style: Option<&Stylesheet>,
+                                                   handler: &H,
+                                                   bloom: Option<&AncestorBloomFilter>)
+                                                   -> ~[MatchedRule] {
+        let mut sheets = ~[];
+        for sheet in self.sheets.iter().filter(|sheet| sheet.is_attached()) {
+            let rules: &[SourceRule] = sheet.rules;
+            sheets.push((sheet.handle, sheet.origin, rules));
+        }
+        let inline_declarations = match inline_style {
+            Some(sheet) if !sheet.rules.is_empty() => Some(sheet.rules[0].declarations.as_slice()),
+            _ => None
+        };
+        match_rules(sheets, media, node, inline_declarations, handler, bloom)
+    }
 }
 
 /**
@@ -90,6 +567,17 @@ impl<'self> SelectResults {
             inner: self.inner.computed_style(n::s::CssPseudoElementNone)
         }
     }
+
+    /**
+    Retrieve the computed style of one of the node's pseudo-elements, e.g. the
+    generated content of `::before`
+    */
+    #[inline]
+    pub fn pseudo_element_computed_style(&'self self, pseudo: PseudoElement) -> ComputedStyle<'self> {
+        ComputedStyle {
+            inner: self.inner.computed_style(pseudo.to_net())
+        }
+    }
 }
 
 /**
@@ -111,13 +599,19 @@ pub trait SelectHandler<N> {
 /** Used to convert the netsurfcss CssSelectHandler callbacks to out SelectHandler callbacks */
 struct SelectHandlerWrapper<N, H> {
     // FIXME: Can't encode region variables
-    inner: *H
+    inner: *H,
+    // The ancestors of the node being selected, if the caller keeps track of them
+    bloom: Option<*AncestorBloomFilter>
 }
 
 impl<'self, N, H: SelectHandler<N>> SelectHandlerWrapper<N, H> {
     fn inner_ref(&self) -> &'self H {
         unsafe { &*self.inner }
     }
+
+    fn bloom_ref(&self) -> Option<&'self AncestorBloomFilter> {
+        self.bloom.map(|&bloom| unsafe { &*bloom })
+    }
 }
 
 impl<N, H: SelectHandler<N>> n::s::CssSelectHandler<N> for SelectHandlerWrapper<N, H> {
@@ -165,7 +659,11 @@ impl<N, H: SelectHandler<N>> n::s::CssSelectHandler<N> for SelectHandlerWrapper<
     }
 
     fn named_ancestor_node(&self, node: &N, qname: &n::t::CssQName) -> Option<N> {
-        self.inner_ref().named_ancestor_node(node, net_qname_to_rust_str(qname))
+        let name = net_qname_to_rust_str(qname);
+        match self.bloom_ref() {
+            Some(bloom) if !bloom.might_have_ancestor_named(name) => None,
+            _ => self.inner_ref().named_ancestor_node(node, name)
+        }
     }
 
     fn node_is_root(&self, node: &N) -> bool {
@@ -0,0 +1,353 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Selectors as written in the stylesheet source
+
+libcss matches selectors without exposing them, so style sharing,
+invalidation and the matched rules for inspectors all read them from the
+source with this one parser. As in libcss, a selector list with an invalid
+selector is dropped whole, along with its rule.
+*/
+
+use std::ascii::StrAsciiExt;
+use parser::{read_ident, skip_string, skip_whitespace, find_unquoted};
+
+/** How a compound selector relates to the one on its left */
+#[deriving(Eq, Clone)]
+pub enum Combinator {
+    /** Whitespace */
+    DescendantCombinator,
+    /** `>` */
+    ChildCombinator,
+    /** `+` */
+    AdjacentSiblingCombinator,
+    /** `~` */
+    GeneralSiblingCombinator
+}
+
+impl Combinator {
+    pub fn is_sibling(&self) -> bool {
+        match *self {
+            AdjacentSiblingCombinator | GeneralSiblingCombinator => true,
+            DescendantCombinator | ChildCombinator => false
+        }
+    }
+}
+
+#[deriving(Eq, Clone)]
+pub enum SimpleSelector {
+    IdSelector(~str),
+    ClassSelector(~str),
+    /** The lowercased attribute name; how its value is tested isn't kept */
+    AttributeSelector(~str),
+    /** A lowercased pseudo-class, with the argument of functional ones like `:nth-child(2n)` */
+    PseudoClassSelector(~str, Option<~str>),
+    /** `:not()`, with the compound selector it negates */
+    NegationSelector(~Compound),
+    /** A lowercased pseudo-element, written with one colon or two */
+    PseudoElementSelector(~str)
+}
+
+/** Simple selectors not separated by combinators, like `li.item:hover` */
+#[deriving(Eq, Clone)]
+pub struct Compound {
+    /** The lowercased type selector, `None` for `*` or none at all */
+    name: Option<~str>,
+    simple_selectors: ~[SimpleSelector],
+    /** The combinator to the left, `None` for the first compound */
+    combinator: Option<Combinator>
+}
+
+#[deriving(Eq, Clone)]
+pub struct Selector {
+    /** The selector as written, without surrounding whitespace */
+    text: ~str,
+    /** From left to right */
+    compounds: ~[Compound]
+}
+
+impl Selector {
+    /** Ids, then classes, attributes and pseudo-classes, then types and pseudo-elements */
+    pub fn specificity(&self) -> (uint, uint, uint) {
+        self.compounds.iter().fold((0, 0, 0), |specificity, compound| {
+            compound.add_specificity(specificity)
+        })
+    }
+
+    /** The compound selector matching the element itself */
+    pub fn subject<'a>(&'a self) -> &'a Compound {
+        &self.compounds[self.compounds.len() - 1]
+    }
+}
+
+impl Compound {
+    fn new(combinator: Option<Combinator>) -> Compound {
+        Compound {
+            name: None,
+            simple_selectors: ~[],
+            combinator: combinator
+        }
+    }
+
+    fn add_specificity(&self, (a, b, c): (uint, uint, uint)) -> (uint, uint, uint) {
+        let mut specificity = (a, b, if self.name.is_some() { c + 1 } else { c });
+        for simple_selector in self.simple_selectors.iter() {
+            let (a, b, c) = specificity;
+            specificity = match *simple_selector {
+                IdSelector(_) => (a + 1, b, c),
+                ClassSelector(_) | AttributeSelector(_) | PseudoClassSelector(*) => (a, b + 1, c),
+                // The negation pseudo-class itself doesn't count, its argument does
+                NegationSelector(ref negated) => negated.add_specificity(specificity),
+                PseudoElementSelector(_) => (a, b, c + 1)
+            };
+        }
+        specificity
+    }
+}
+
+/** Parses a selector list, or returns `None` if any of its selectors is invalid */
+pub fn parse_selector_list(selectors: &str) -> Option<~[Selector]> {
+    let mut list = ~[];
+    for text in split_selector_list(selectors).iter() {
+        match parse_selector(*text) {
+            Some(selector) => list.push(selector),
+            None => return None
+        }
+    }
+    Some(list)
+}
+
+/** Splits a selector list at the commas outside of strings, brackets and parentheses */
+fn split_selector_list<'a>(selectors: &'a str) -> ~[&'a str] {
+    let bytes = selectors.as_bytes();
+    let mut list = ~[];
+    let mut start = 0;
+    loop {
+        let end = find_unquoted(bytes, start, [',' as u8]);
+        list.push(selectors.slice(start, end).trim());
+        if end >= bytes.len() {
+            break;
+        }
+        start = end + 1;
+    }
+    list
+}
+
+fn parse_selector(text: &str) -> Option<Selector> {
+    let bytes = text.as_bytes();
+    let mut compounds = ~[];
+    let mut combinator = None;
+    let mut i = 0;
+    loop {
+        let (compound, end) = match parse_compound(text, i, combinator) {
+            Some(parsed) => parsed,
+            None => return None
+        };
+        compounds.push(compound);
+        i = skip_whitespace(bytes, end);
+        if i >= bytes.len() {
+            break;
+        }
+        combinator = match bytes[i] as char {
+            '>' => Some(ChildCombinator),
+            '+' => Some(AdjacentSiblingCombinator),
+            '~' => Some(GeneralSiblingCombinator),
+            _ if i > end => None,
+            _ => return None
+        };
+        if combinator.is_some() {
+            i = skip_whitespace(bytes, i + 1);
+        } else {
+            combinator = Some(DescendantCombinator);
+        }
+    }
+    Some(Selector {
+        text: text.to_owned(),
+        compounds: compounds
+    })
+}
+
+// Parses the compound selector starting at `start`, returning it and the index just past it
+fn parse_compound(text: &str,
+                  start: uint,
+                  combinator: Option<Combinator>) -> Option<(Compound, uint)> {
+    let bytes = text.as_bytes();
+    let mut compound = Compound::new(combinator);
+    let mut i = start;
+    let mut empty = true;
+
+    if i < bytes.len() && bytes[i] == '*' as u8 {
+        i += 1;
+        empty = false;
+    } else {
+        let (name, end) = read_ident(text, i);
+        if !name.is_empty() {
+            compound.name = Some(name.to_ascii_lower());
+            i = end;
+            empty = false;
+        }
+    }
+
+    while i < bytes.len() {
+        let simple_selector = match bytes[i] as char {
+            '#' | '.' => {
+                let (name, end) = read_ident(text, i + 1);
+                if name.is_empty() {
+                    return None;
+                }
+                let id = bytes[i] == '#' as u8;
+                i = end;
+                if id { IdSelector(name) } else { ClassSelector(name) }
+            }
+            '[' => {
+                let (name, end) = read_ident(text, skip_whitespace(bytes, i + 1));
+                let close = find_close(bytes, end, '[' as u8, ']' as u8);
+                if name.is_empty() || close >= bytes.len() {
+                    return None;
+                }
+                i = close + 1;
+                AttributeSelector(name.to_ascii_lower())
+            }
+            ':' => {
+                let double_colon = i + 1 < bytes.len() && bytes[i + 1] == ':' as u8;
+                let (name, end) = read_ident(text, if double_colon { i + 2 } else { i + 1 });
+                if name.is_empty() {
+                    return None;
+                }
+                let name = name.to_ascii_lower();
+                i = end;
+                let pseudo_element = double_colon || match name.as_slice() {
+                    "first-line" | "first-letter" | "before" | "after" => true,
+                    _ => false
+                };
+                if pseudo_element {
+                    PseudoElementSelector(name)
+                } else if i < bytes.len() && bytes[i] == '(' as u8 {
+                    let close = find_close(bytes, i + 1, '(' as u8, ')' as u8);
+                    if close >= bytes.len() {
+                        return None;
+                    }
+                    let argument = text.slice(i + 1, close).trim();
+                    i = close + 1;
+                    if name == ~"not" {
+                        match parse_compound(argument, 0, None) {
+                            Some((negated, end)) if end == argument.len() => {
+                                NegationSelector(~negated)
+                            }
+                            _ => return None
+                        }
+                    } else {
+                        PseudoClassSelector(name, Some(argument.to_owned()))
+                    }
+                } else {
+                    PseudoClassSelector(name, None)
+                }
+            }
+            _ => break
+        };
+        compound.simple_selectors.push(simple_selector);
+        empty = false;
+    }
+
+    if empty { None } else { Some((compound, i)) }
+}
+
+// The index of the `close` matching an `open` before `start`, or the end of input
+fn find_close(bytes: &[u8], start: uint, open: u8, close: u8) -> uint {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == '"' as u8 || b == '\'' as u8 {
+            i = skip_string(bytes, i);
+            loop;
+        } else if b == '\\' as u8 {
+            i += 2;
+            loop;
+        } else if b == open {
+            depth += 1;
+        } else if b == close {
+            if depth == 0 {
+                return i;
+            }
+            depth -= 1;
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(selector: &str) -> Option<Selector> {
+        parse_selector_list(selector).map(|list| {
+            assert!(list.len() == 1);
+            list[0].clone()
+        })
+    }
+
+    fn specificity(selector: &str) -> Option<(uint, uint, uint)> {
+        parse(selector).map(|selector| selector.specificity())
+    }
+
+    #[test]
+    fn test_selector_lists() {
+        let list = parse_selector_list("a, b.c ,d[title=\"x,y\"], :not(p)").unwrap();
+        let texts = list.map(|selector| selector.text.clone());
+        assert!(texts == ~[~"a", ~"b.c", ~"d[title=\"x,y\"]", ~":not(p)"]);
+        // One invalid selector drops the list
+        assert!(parse_selector_list("a, b..c").is_none());
+        assert!(parse_selector_list("a,").is_none());
+    }
+
+    #[test]
+    fn test_compounds() {
+        let selector = parse("UL > li.item:Hover + *#x ~ p::before").unwrap();
+        let names = selector.compounds.map(|compound| compound.name.clone());
+        assert!(names == ~[Some(~"ul"), Some(~"li"), None, Some(~"p")]);
+        let combinators = selector.compounds.map(|compound| compound.combinator);
+        assert!(combinators == ~[None, Some(ChildCombinator), Some(AdjacentSiblingCombinator),
+                                 Some(GeneralSiblingCombinator)]);
+        assert!(selector.compounds[1].simple_selectors
+                == ~[ClassSelector(~"item"), PseudoClassSelector(~"hover", None)]);
+        assert!(selector.subject().simple_selectors == ~[PseudoElementSelector(~"before")]);
+
+        let selector = parse("div  span:nth-child( 2n+1 ):not(.a)[data-X = \"]\"]").unwrap();
+        assert!(selector.compounds[1].combinator == Some(DescendantCombinator));
+        let negated = Compound { name: None, simple_selectors: ~[ClassSelector(~"a")],
+                                 combinator: None };
+        assert!(selector.subject().simple_selectors
+                == ~[PseudoClassSelector(~"nth-child", Some(~"2n+1")),
+                     NegationSelector(~negated),
+                     AttributeSelector(~"data-x")]);
+    }
+
+    #[test]
+    fn test_specificity() {
+        assert!(specificity("*") == Some((0, 0, 0)));
+        assert!(specificity("li") == Some((0, 0, 1)));
+        assert!(specificity("ul li") == Some((0, 0, 2)));
+        assert!(specificity("ul > li.red") == Some((0, 1, 2)));
+        assert!(specificity("#x34y") == Some((1, 0, 0)));
+        assert!(specificity("a:link #nav .item") == Some((1, 2, 1)));
+        assert!(specificity("p:first-line") == Some((0, 0, 2)));
+        assert!(specificity("li:not(#a)[href]") == Some((1, 1, 1)));
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        assert!(parse("> p").is_none());
+        assert!(parse("div >").is_none());
+        assert!(parse("div > > p").is_none());
+        assert!(parse("p.").is_none());
+        assert!(parse("a[href").is_none());
+        assert!(parse("li:nth-child(2").is_none());
+        assert!(parse("p:not(a b)").is_none());
+        assert!(parse("p!").is_none());
+        assert!(parse("").is_none());
+    }
+}
@@ -0,0 +1,1007 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Serialization of values back to CSS text
+
+Follows the CSSOM rules for serializing CSS values: keywords are lowercase,
+numbers use the shortest form, strings are double quoted, and font family
+names are only quoted when they could not be written as identifiers.
+*/
+
+use std::ascii::StrAsciiExt;
+use std::f64;
+use extra::url::Url;
+use color::{Color, SpecifiedColor};
+use units::*;
+use values::*;
+use property::*;
+
+pub trait ToCss {
+    fn to_css(&self) -> ~str;
+}
+
+macro_rules! keywords(
+    ($ty:ident { $($variant:ident => $keyword:expr),+ }) => (
+        impl ToCss for $ty {
+            fn to_css(&self) -> ~str {
+                match *self {
+                    $($variant => $keyword.to_owned()),+
+                }
+            }
+        }
+    )
+)
+
+/** A number in its shortest form, with at most six decimals and no negative zero */
+pub fn serialize_number(value: f64) -> ~str {
+    let value = f64::to_str_digits(value, 6);
+    if value == ~"-0" { ~"0" } else { value }
+}
+
+/** A double quoted string, escaping quotes, backslashes and control characters */
+pub fn serialize_string(value: &str) -> ~str {
+    let mut result = ~"\"";
+    for c in value.iter() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if c < ' ' || c == '\x7f' => result.push_str(fmt!("\\%x ", c as uint)),
+            c => result.push_char(c)
+        }
+    }
+    result.push_char('"');
+    result
+}
+
+/**
+A font family name, unquoted if it is a sequence of identifiers that can't be
+mistaken for a keyword, and quoted otherwise.
+*/
+pub fn serialize_family_name(name: &str) -> ~str {
+    let lower = name.to_ascii_lower();
+    let reserved = [~"serif", ~"sans-serif", ~"cursive", ~"fantasy", ~"monospace",
+                    ~"inherit", ~"initial", ~"unset", ~"default"];
+    if reserved.iter().any(|keyword| *keyword == lower)
+        || !name.split_iter(' ').all(is_identifier) {
+        serialize_string(name)
+    } else {
+        name.to_owned()
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c as uint > 0x7f
+}
+
+fn is_name(c: char) -> bool {
+    is_name_start(c) || c.is_digit() || c == '-'
+}
+
+fn is_identifier(word: &str) -> bool {
+    let rest = if word.starts_with("-") { word.slice_from(1) } else { word };
+    if rest.is_empty() || !is_name_start(rest.char_at(0)) {
+        return false;
+    }
+    rest.iter().all(is_name)
+}
+
+fn serialize_url(url: &Url) -> ~str {
+    fmt!("url(%s)", serialize_string(url.to_str()))
+}
+
+fn serialize_percentage(value: f64) -> ~str {
+    fmt!("%s%%", serialize_number(value))
+}
+
+impl ToCss for f64 {
+    fn to_css(&self) -> ~str {
+        serialize_number(*self)
+    }
+}
+
+impl ToCss for int {
+    fn to_css(&self) -> ~str {
+        self.to_str()
+    }
+}
+
+impl<T: ToCss> ToCss for CSSValue<T> {
+    fn to_css(&self) -> ~str {
+        match *self {
+            Inherit => ~"inherit",
+            Specified(ref value) => value.to_css()
+        }
+    }
+}
+
+/** Lists of values, like the font family list, are separated by commas */
+impl<T: ToCss> ToCss for ~[T] {
+    fn to_css(&self) -> ~str {
+        self.map(|value| value.to_css()).connect(", ")
+    }
+}
+
+// Units
+
+fn unit(length: Length) -> &'static str {
+    match length {
+        Em(_) => "em",
+        Px(_) => "px",
+        Ex(_) => "ex",
+        Rem(_) => "rem",
+        Vw(_) => "vw",
+        Vh(_) => "vh",
+        Vmin(_) => "vmin",
+        Vmax(_) => "vmax"
+    }
+}
+
+impl ToCss for Length {
+    fn to_css(&self) -> ~str {
+        fmt!("%s%s", serialize_number(self.value()), unit(*self))
+    }
+}
+
+/**
+Serialized as `calc()` with the percentage first and the lengths in
+alphabetical order of their units, as CSSOM specifies.
+*/
+impl ToCss for Calc {
+    fn to_css(&self) -> ~str {
+        let mut terms = ~[];
+        for &p in self.percentage.iter() {
+            terms.push((p, serialize_percentage(p.abs())));
+        }
+        let mut lengths = self.lengths.clone();
+        sort_by_unit(&mut lengths);
+        for &length in lengths.iter() {
+            terms.push((length.value(), length.with_value(length.value().abs()).to_css()));
+        }
+        if terms.is_empty() {
+            return ~"calc(0px)";
+        }
+
+        let mut result = ~"calc(";
+        for (i, &(value, ref term)) in terms.iter().enumerate() {
+            if i == 0 {
+                if value < 0.0 { result.push_char('-'); }
+            } else {
+                result.push_str(if value < 0.0 { " - " } else { " + " });
+            }
+            result.push_str(*term);
+        }
+        result.push_char(')');
+        result
+    }
+}
+
+fn sort_by_unit(lengths: &mut ~[Length]) {
+    // Insertion sort, there is at most one length per unit
+    let mut i = 1;
+    while i < lengths.len() {
+        let mut j = i;
+        while j > 0 && unit(lengths[j - 1]) > unit(lengths[j]) {
+            lengths.swap(j - 1, j);
+            j -= 1;
+        }
+        i += 1;
+    }
+}
+
+impl ToCss for LengthOrPercentageOrCalc {
+    fn to_css(&self) -> ~str {
+        match *self {
+            LPCLength(length) => length.to_css(),
+            LPCPercentage(p) => serialize_percentage(p),
+            LPCCalc(ref calc) => calc.to_css()
+        }
+    }
+}
+
+impl ToCss for BoxSizing {
+    fn to_css(&self) -> ~str {
+        match *self {
+            BoxLength(length) => length.to_css(),
+            BoxPercent(p) => serialize_percentage(p),
+            BoxAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for Angle {
+    fn to_css(&self) -> ~str {
+        match *self {
+            Deg(value) => fmt!("%sdeg", serialize_number(value)),
+            Grad(value) => fmt!("%sgrad", serialize_number(value)),
+            Rad(value) => fmt!("%srad", serialize_number(value)),
+            Turn(value) => fmt!("%sturn", serialize_number(value))
+        }
+    }
+}
+
+impl ToCss for Time {
+    fn to_css(&self) -> ~str {
+        match *self {
+            S(value) => fmt!("%ss", serialize_number(value)),
+            Ms(value) => fmt!("%sms", serialize_number(value))
+        }
+    }
+}
+
+impl ToCss for Frequency {
+    fn to_css(&self) -> ~str {
+        match *self {
+            Hz(value) => fmt!("%shz", serialize_number(value)),
+            KHz(value) => fmt!("%skhz", serialize_number(value))
+        }
+    }
+}
+
+impl ToCss for Resolution {
+    fn to_css(&self) -> ~str {
+        match *self {
+            Dpi(value) => fmt!("%sdpi", serialize_number(value)),
+            Dpcm(value) => fmt!("%sdpcm", serialize_number(value)),
+            Dppx(value) => fmt!("%sdppx", serialize_number(value))
+        }
+    }
+}
+
+impl ToCss for Dimension {
+    fn to_css(&self) -> ~str {
+        match *self {
+            DimensionLength(length) => length.to_css(),
+            DimensionPercentage(p) => serialize_percentage(p),
+            DimensionAngle(angle) => angle.to_css(),
+            DimensionTime(time) => time.to_css(),
+            DimensionFrequency(frequency) => frequency.to_css(),
+            DimensionResolution(resolution) => resolution.to_css()
+        }
+    }
+}
+
+impl ToCss for Fixed {
+    fn to_css(&self) -> ~str {
+        serialize_number(self.to_f64())
+    }
+}
+
+keywords!(AbsoluteSize {
+    XXSmall => "xx-small",
+    XSmall => "x-small",
+    Small => "small",
+    Medium => "medium",
+    Large => "large",
+    XLarge => "x-large",
+    XXLarge => "xx-large"
+})
+
+keywords!(RelativeSize {
+    Larger => "larger",
+    Smaller => "smaller"
+})
+
+keywords!(GenericFontFamily {
+    Serif => "serif",
+    SansSerif => "sans-serif",
+    Cursive => "cursive",
+    Fantasy => "fantasy",
+    Monospace => "monospace"
+})
+
+impl ToCss for Color {
+    fn to_css(&self) -> ~str {
+        self.to_css_string()
+    }
+}
+
+impl ToCss for SpecifiedColor {
+    fn to_css(&self) -> ~str {
+        self.to_css_string()
+    }
+}
+
+// CSS 2.1, Section 8 - Box model
+
+impl ToCss for CSSMargin {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSMarginLength(length) => length.to_css(),
+            CSSMarginPercentage(p) => serialize_percentage(p),
+            CSSMarginAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSPadding {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSPaddingLength(length) => length.to_css(),
+            CSSPaddingPercentage(p) => serialize_percentage(p),
+        }
+    }
+}
+
+impl ToCss for CSSBorderWidth {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBorderWidthThin => ~"thin",
+            CSSBorderWidthMedium => ~"medium",
+            CSSBorderWidthThick => ~"thick",
+            CSSBorderWidthLength(length) => length.to_css()
+        }
+    }
+}
+
+impl ToCss for CSSBorderColor {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBorderColorColor(color) => color.to_css(),
+            CSSBorderColorTransparent => ~"transparent",
+            CSSBorderColorCurrentColor => ~"currentcolor"
+        }
+    }
+}
+
+keywords!(CSSBorderStyle {
+    CSSBorderStyleNone => "none",
+    CSSBorderStyleHidden => "hidden",
+    CSSBorderStyleDotted => "dotted",
+    CSSBorderStyleDashed => "dashed",
+    CSSBorderStyleSolid => "solid",
+    CSSBorderStyleDouble => "double",
+    CSSBorderStyleGroove => "groove",
+    CSSBorderStyleRidge => "ridge",
+    CSSBorderStyleInset => "inset",
+    CSSBorderStyleOutset => "outset"
+})
+
+// CSS 2.1, Section 9 - Visual formatting model
+
+keywords!(CSSDisplay {
+    CSSDisplayInline => "inline",
+    CSSDisplayBlock => "block",
+    CSSDisplayListItem => "list-item",
+    CSSDisplayInlineBlock => "inline-block",
+    CSSDisplayTable => "table",
+    CSSDisplayInlineTable => "inline-table",
+    CSSDisplayTableRowGroup => "table-row-group",
+    CSSDisplayTableHeaderGroup => "table-header-group",
+    CSSDisplayTableFooterGroup => "table-footer-group",
+    CSSDisplayTableRow => "table-row",
+    CSSDisplayTableColumnGroup => "table-column-group",
+    CSSDisplayTableColumn => "table-column",
+    CSSDisplayTableCell => "table-cell",
+    CSSDisplayTableCaption => "table-caption",
+    CSSDisplayNone => "none"
+})
+
+keywords!(CSSPosition {
+    CSSPositionStatic => "static",
+    CSSPositionRelative => "relative",
+    CSSPositionAbsolute => "absolute",
+    CSSPositionFixed => "fixed"
+})
+
+impl ToCss for CSSTop {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSTopLength(length) => length.to_css(),
+            CSSTopPercentage(p) => serialize_percentage(p),
+            CSSTopAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSRight {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSRightLength(length) => length.to_css(),
+            CSSRightPercentage(p) => serialize_percentage(p),
+            CSSRightAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSBottom {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBottomLength(length) => length.to_css(),
+            CSSBottomPercentage(p) => serialize_percentage(p),
+            CSSBottomAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSLeft {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSLeftLength(length) => length.to_css(),
+            CSSLeftPercentage(p) => serialize_percentage(p),
+            CSSLeftAuto => ~"auto"
+        }
+    }
+}
+
+keywords!(CSSFloat {
+    CSSFloatLeft => "left",
+    CSSFloatRight => "right",
+    CSSFloatNone => "none"
+})
+
+keywords!(CSSClear {
+    CSSClearLeft => "left",
+    CSSClearRight => "right",
+    CSSClearBoth => "both",
+    CSSClearNone => "none"
+})
+
+keywords!(CSSDirection {
+    CSSDirectionLtr => "ltr",
+    CSSDirectionRtl => "rtl"
+})
+
+// CSS 2.1, Section 10 - Visual formatting model details
+
+impl ToCss for CSSWidth {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSWidthLength(length) => length.to_css(),
+            CSSWidthPercentage(p) => serialize_percentage(p),
+            CSSWidthAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSHeight {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSHeightLength(length) => length.to_css(),
+            CSSHeightPercentage(p) => serialize_percentage(p),
+            CSSHeightAuto => ~"auto"
+        }
+    }
+}
+
+impl ToCss for CSSLineHeight {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSLineHeightNormal => ~"normal",
+            CSSLineHeightNumber(number) => serialize_number(number),
+            CSSLineHeightLength(length) => length.to_css(),
+            CSSLineHeightPercentage(p) => serialize_percentage(p)
+        }
+    }
+}
+
+impl ToCss for CSSVerticalAlign {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSVerticalAlignBaseline => ~"baseline",
+            CSSVerticalAlignSub => ~"sub",
+            CSSVerticalAlignSuper => ~"super",
+            CSSVerticalAlignTop => ~"top",
+            CSSVerticalAlignTextTop => ~"text-top",
+            CSSVerticalAlignMiddle => ~"middle",
+            CSSVerticalAlignBottom => ~"bottom",
+            CSSVerticalAlignTextBottom => ~"text-bottom",
+            CSSVerticalAlignPercentage(p) => serialize_percentage(p),
+            CSSVerticalAlignLength(length) => length.to_css()
+        }
+    }
+}
+
+// CSS 2.1, Section 11 - Visual effects
+
+keywords!(CSSOverflow {
+    CSSOverflowVisible => "visible",
+    CSSOverflowHidden => "hidden",
+    CSSOverflowScroll => "scroll",
+    CSSOverflowAuto => "auto"
+})
+
+keywords!(CSSVisibility {
+    CSSVisibilityVisible => "visible",
+    CSSVisibilityHidden => "hidden",
+    CSSVisibilityCollapse => "collapse"
+})
+
+impl ToCss for CSSZIndex {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSZIndexAuto => ~"auto",
+            CSSZIndexInteger(index) => index.to_str()
+        }
+    }
+}
+
+impl ToCss for CSSClip {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSClipAuto => ~"auto",
+            CSSClipShape(ref rect) => rect.to_css()
+        }
+    }
+}
+
+impl ToCss for CSSClipRect {
+    fn to_css(&self) -> ~str {
+        fmt!("rect(%s, %s, %s, %s)", self.top.to_css(), self.right.to_css(),
+             self.bottom.to_css(), self.left.to_css())
+    }
+}
+
+impl ToCss for CSSClipEdge {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSClipEdgeLength(length) => length.to_css(),
+            CSSClipEdgeAuto => ~"auto"
+        }
+    }
+}
+
+// CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
+
+impl ToCss for CSSContent {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSContentNormal => ~"normal",
+            CSSContentNone => ~"none",
+            CSSContentContent(ref items) => items.map(|item| item.to_css()).connect(" ")
+        }
+    }
+}
+
+impl ToCss for CSSContentItem {
+    fn to_css(&self) -> ~str {
+        // The list style is omitted when it is the default, 'decimal'
+        let style = |style: CSSListStyleType| match style {
+            CSSListStyleTypeDecimal => ~"",
+            style => fmt!(", %s", style.to_css())
+        };
+        match *self {
+            CSSContentItemString(ref string) => serialize_string(*string),
+            CSSContentItemUri(ref url) => serialize_url(url),
+            CSSContentItemCounter(ref name, list_style) => {
+                fmt!("counter(%s%s)", *name, style(list_style))
+            }
+            CSSContentItemCounters(ref name, ref separator, list_style) => {
+                fmt!("counters(%s, %s%s)", *name, serialize_string(*separator), style(list_style))
+            }
+            CSSContentItemAttr(ref name) => fmt!("attr(%s)", *name),
+            CSSContentItemOpenQuote => ~"open-quote",
+            CSSContentItemCloseQuote => ~"close-quote",
+            CSSContentItemNoOpenQuote => ~"no-open-quote",
+            CSSContentItemNoCloseQuote => ~"no-close-quote"
+        }
+    }
+}
+
+impl ToCss for CSSCounter {
+    fn to_css(&self) -> ~str {
+        fmt!("%s %d", self.name, self.value)
+    }
+}
+
+/** Counters whose value is the property's default are written without it */
+fn serialize_counters(counters: &[CSSCounter], default: int) -> ~str {
+    let counters = do counters.map |counter| {
+        if counter.value == default { counter.name.clone() } else { counter.to_css() }
+    };
+    counters.connect(" ")
+}
+
+impl ToCss for CSSCounterReset {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSCounterResetNone => ~"none",
+            CSSCounterResetCounters(ref counters) => serialize_counters(*counters, 0)
+        }
+    }
+}
+
+impl ToCss for CSSCounterIncrement {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSCounterIncrementNone => ~"none",
+            CSSCounterIncrementCounters(ref counters) => serialize_counters(*counters, 1)
+        }
+    }
+}
+
+impl ToCss for CSSQuotes {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSQuotesNone => ~"none",
+            CSSQuotesStrings(ref pairs) => {
+                let pairs = do pairs.map |&(ref open, ref close)| {
+                    fmt!("%s %s", serialize_string(*open), serialize_string(*close))
+                };
+                pairs.connect(" ")
+            }
+        }
+    }
+}
+
+keywords!(CSSListStyleType {
+    CSSListStyleTypeDisc => "disc",
+    CSSListStyleTypeCircle => "circle",
+    CSSListStyleTypeSquare => "square",
+    CSSListStyleTypeDecimal => "decimal",
+    CSSListStyleTypeDecimalLeadingZero => "decimal-leading-zero",
+    CSSListStyleTypeLowerRoman => "lower-roman",
+    CSSListStyleTypeUpperRoman => "upper-roman",
+    CSSListStyleTypeLowerGreek => "lower-greek",
+    CSSListStyleTypeLowerLatin => "lower-latin",
+    CSSListStyleTypeUpperLatin => "upper-latin",
+    CSSListStyleTypeArmenian => "armenian",
+    CSSListStyleTypeGeorgian => "georgian",
+    CSSListStyleTypeLowerAlpha => "lower-alpha",
+    CSSListStyleTypeUpperAlpha => "upper-alpha",
+    CSSListStyleTypeNone => "none"
+})
+
+keywords!(CSSListStylePosition {
+    CSSListStylePositionInside => "inside",
+    CSSListStylePositionOutside => "outside"
+})
+
+impl ToCss for CSSListStyleImage {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSListStyleImageUri(ref url) => serialize_url(url),
+            CSSListStyleImageNone => ~"none"
+        }
+    }
+}
+
+// CSS 2.1, Section 13 - Paged media
+
+keywords!(CSSPageBreakBefore {
+    CSSPageBreakBeforeAuto => "auto",
+    CSSPageBreakBeforeAlways => "always",
+    CSSPageBreakBeforeAvoid => "avoid",
+    CSSPageBreakBeforeLeft => "left",
+    CSSPageBreakBeforeRight => "right"
+})
+
+keywords!(CSSPageBreakAfter {
+    CSSPageBreakAfterAuto => "auto",
+    CSSPageBreakAfterAlways => "always",
+    CSSPageBreakAfterAvoid => "avoid",
+    CSSPageBreakAfterLeft => "left",
+    CSSPageBreakAfterRight => "right"
+})
+
+keywords!(CSSPageBreakInside {
+    CSSPageBreakInsideAuto => "auto",
+    CSSPageBreakInsideAvoid => "avoid"
+})
+
+// CSS 2.1, Section 14 - Colors and Backgrounds
+
+impl ToCss for CSSColor {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSColorColor(color) => color.to_css()
+        }
+    }
+}
+
+impl ToCss for CSSBackgroundColor {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBackgroundColorColor(color) => color.to_css(),
+            CSSBackgroundColorTransparent => ~"transparent",
+            CSSBackgroundColorCurrentColor => ~"currentcolor"
+        }
+    }
+}
+
+impl ToCss for CSSBackgroundImage {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBackgroundUri(ref url) => serialize_url(url),
+            CSSBackgroundImageNone => ~"none"
+        }
+    }
+}
+
+keywords!(CSSBackgroundRepeat {
+    CSSBackgroundRepeatRepeat => "repeat",
+    CSSBackgroundRepeatRepeatX => "repeat-x",
+    CSSBackgroundRepeatRepeatY => "repeat-y",
+    CSSBackgroundRepeatNoRepeat => "no-repeat"
+})
+
+keywords!(CSSBackgroundAttachment {
+    CSSBackgroundAttachmentScroll => "scroll",
+    CSSBackgroundAttachmentFixed => "fixed"
+})
+
+impl ToCss for CSSBackgroundPosition {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSBackgroundPositionPercentage(p) => serialize_percentage(p),
+            CSSBackgroundPositionLength(length) => length.to_css(),
+            CSSBackgroundPositionLeft => ~"left",
+            CSSBackgroundPositionCenter => ~"center",
+            CSSBackgroundPositionRight => ~"right",
+            CSSBackgroundPositionTop => ~"top",
+            CSSBackgroundPositionBottom => ~"bottom"
+        }
+    }
+}
+
+// CSS 2.1, Section 15 - Fonts
+
+impl ToCss for CSSFontFamily {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSFontFamilyFamilyName(ref name) => serialize_family_name(*name),
+            CSSFontFamilyGenericFamily(family) => family.to_css()
+        }
+    }
+}
+
+keywords!(CSSFontStyle {
+    CSSFontStyleNormal => "normal",
+    CSSFontStyleItalic => "italic",
+    CSSFontStyleOblique => "oblique"
+})
+
+keywords!(CSSFontWeight {
+    CSSFontWeightNormal => "normal",
+    CSSFontWeightBold => "bold",
+    CSSFontWeightBolder => "bolder",
+    CSSFontWeightLighter => "lighter",
+    CSSFontWeight100 => "100",
+    CSSFontWeight200 => "200",
+    CSSFontWeight300 => "300",
+    CSSFontWeight400 => "400",
+    CSSFontWeight500 => "500",
+    CSSFontWeight600 => "600",
+    CSSFontWeight700 => "700",
+    CSSFontWeight800 => "800",
+    CSSFontWeight900 => "900"
+})
+
+impl ToCss for CSSFontSize {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSFontSizeAbsoluteSize(size) => size.to_css(),
+            CSSFontSizeRelativeSize(size) => size.to_css(),
+            CSSFontSizeLength(length) => length.to_css(),
+            CSSFontSizePercentage(p) => serialize_percentage(p),
+        }
+    }
+}
+
+// CSS 2.1, Section 16 - Text
+
+keywords!(CSSTextAlign {
+    CSSTextAlignLeft => "left",
+    CSSTextAlignRight => "right",
+    CSSTextAlignCenter => "center",
+    CSSTextAlignJustify => "justify"
+})
+
+keywords!(CSSTextDecoration {
+    CSSTextDecorationNone => "none",
+    CSSTextDecorationUnderline => "underline",
+    CSSTextDecorationOverline => "overline",
+    CSSTextDecorationLineThrough => "line-through",
+    CSSTextDecorationBlink => "blink"
+})
+
+keywords!(CSSTextTransform {
+    CSSTextTransformCapitalize => "capitalize",
+    CSSTextTransformUppercase => "uppercase",
+    CSSTextTransformLowercase => "lowercase",
+    CSSTextTransformNone => "none"
+})
+
+// CSS 2.1, Section 18 - User interface
+
+keywords!(CSSCursorKeyword {
+    CSSCursorAuto => "auto",
+    CSSCursorCrosshair => "crosshair",
+    CSSCursorDefault => "default",
+    CSSCursorPointer => "pointer",
+    CSSCursorMove => "move",
+    CSSCursorEResize => "e-resize",
+    CSSCursorNeResize => "ne-resize",
+    CSSCursorNwResize => "nw-resize",
+    CSSCursorNResize => "n-resize",
+    CSSCursorSeResize => "se-resize",
+    CSSCursorSwResize => "sw-resize",
+    CSSCursorSResize => "s-resize",
+    CSSCursorWResize => "w-resize",
+    CSSCursorText => "text",
+    CSSCursorWait => "wait",
+    CSSCursorHelp => "help",
+    CSSCursorProgress => "progress"
+})
+
+impl ToCss for CSSCursor {
+    fn to_css(&self) -> ~str {
+        let mut parts = self.urls.map(serialize_url);
+        parts.push(self.keyword.to_css());
+        parts.connect(", ")
+    }
+}
+
+impl ToCss for CSSOutlineColor {
+    fn to_css(&self) -> ~str {
+        match *self {
+            CSSOutlineColorColor(color) => color.to_css(),
+            CSSOutlineColorInvert => ~"invert"
+        }
+    }
+}
+
+impl ToCss for PropertyValue {
+    fn to_css(&self) -> ~str {
+        match *self {
+            PropertyValueMargin(ref value) => value.to_css(),
+            PropertyValuePadding(ref value) => value.to_css(),
+            PropertyValueBorderStyle(ref value) => value.to_css(),
+            PropertyValueBorderWidth(ref value) => value.to_css(),
+            PropertyValueBorderColor(ref value) => value.to_css(),
+            PropertyValueDisplay(ref value) => value.to_css(),
+            PropertyValuePosition(ref value) => value.to_css(),
+            PropertyValueFloat(ref value) => value.to_css(),
+            PropertyValueClear(ref value) => value.to_css(),
+            PropertyValueTop(ref value) => value.to_css(),
+            PropertyValueRight(ref value) => value.to_css(),
+            PropertyValueBottom(ref value) => value.to_css(),
+            PropertyValueLeft(ref value) => value.to_css(),
+            PropertyValueWidth(ref value) => value.to_css(),
+            PropertyValueHeight(ref value) => value.to_css(),
+            PropertyValueLineHeight(ref value) => value.to_css(),
+            PropertyValueVerticalAlign(ref value) => value.to_css(),
+            PropertyValueZIndex(ref value) => value.to_css(),
+            PropertyValueClip(ref value) => value.to_css(),
+            PropertyValueOpacity(ref value) => value.to_css(),
+            PropertyValueContent(ref value) => value.to_css(),
+            PropertyValueCounterReset(ref value) => value.to_css(),
+            PropertyValueCounterIncrement(ref value) => value.to_css(),
+            PropertyValueQuotes(ref value) => value.to_css(),
+            PropertyValueListStyleType(ref value) => value.to_css(),
+            PropertyValueListStylePosition(ref value) => value.to_css(),
+            PropertyValueListStyleImage(ref value) => value.to_css(),
+            PropertyValuePageBreakBefore(ref value) => value.to_css(),
+            PropertyValuePageBreakAfter(ref value) => value.to_css(),
+            PropertyValuePageBreakInside(ref value) => value.to_css(),
+            PropertyValueOrphans(ref value) => value.to_css(),
+            PropertyValueWidows(ref value) => value.to_css(),
+            PropertyValueBackgroundColor(ref value) => value.to_css(),
+            PropertyValueColor(ref value) => value.to_css(),
+            PropertyValueFontFamily(ref value) => value.to_css(),
+            PropertyValueFontStyle(ref value) => value.to_css(),
+            PropertyValueFontWeight(ref value) => value.to_css(),
+            PropertyValueFontSize(ref value) => value.to_css(),
+            PropertyValueTextAlign(ref value) => value.to_css(),
+            PropertyValueTextDecoration(ref value) => value.to_css(),
+            PropertyValueCursor(ref value) => value.to_css(),
+            PropertyValueOutlineColor(ref value) => value.to_css()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use extra::url;
+    use color::rgba;
+    use units::*;
+    use values::*;
+    use super::{ToCss, serialize_number, serialize_string, serialize_family_name};
+
+    #[test]
+    fn test_numbers_and_strings() {
+        assert_eq!(serialize_number(1.0), ~"1");
+        assert_eq!(serialize_number(0.5), ~"0.5");
+        assert_eq!(serialize_number(1.0 / 3.0), ~"0.333333");
+        assert_eq!(serialize_number(-0.0), ~"0");
+        assert_eq!(serialize_string("a\"b\\c"), ~"\"a\\\"b\\\\c\"");
+        assert_eq!(serialize_string("a\nb"), ~"\"a\\a b\"");
+    }
+
+    #[test]
+    fn test_family_names() {
+        assert_eq!(serialize_family_name("Times New Roman"), ~"Times New Roman");
+        assert_eq!(serialize_family_name("Helvetica"), ~"Helvetica");
+        assert_eq!(serialize_family_name("serif"), ~"\"serif\"");
+        assert_eq!(serialize_family_name("Inherit"), ~"\"Inherit\"");
+        assert_eq!(serialize_family_name("Font 2000"), ~"\"Font 2000\"");
+        assert_eq!(serialize_family_name("a  b"), ~"\"a  b\"");
+        assert_eq!(serialize_family_name("-moz-fixed"), ~"-moz-fixed");
+        let families = ~[CSSFontFamilyFamilyName(~"Foo Bar"),
+                         CSSFontFamilyFamilyName(~"monospace"),
+                         CSSFontFamilyGenericFamily(Monospace)];
+        assert_eq!(families.to_css(), ~"Foo Bar, \"monospace\", monospace");
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!(Px(10.0).to_css(), ~"10px");
+        assert_eq!(Em(1.5).to_css(), ~"1.5em");
+        assert_eq!(Vmin(-2.0).to_css(), ~"-2vmin");
+        assert_eq!(Turn(0.25).to_css(), ~"0.25turn");
+        assert_eq!(Ms(200.0).to_css(), ~"200ms");
+        assert_eq!(KHz(1.0).to_css(), ~"1khz");
+        assert_eq!(Dppx(2.0).to_css(), ~"2dppx");
+        assert_eq!(DimensionPercentage(50.0).to_css(), ~"50%");
+        assert_eq!(BoxAuto.to_css(), ~"auto");
+        assert_eq!(XXSmall.to_css(), ~"xx-small");
+        assert_eq!(SansSerif.to_css(), ~"sans-serif");
+    }
+
+    #[test]
+    fn test_calc() {
+        let calc = Calc::from_percentage(50.0).add(&Calc::from_length(Px(-10.0)))
+                                             .add(&Calc::from_length(Em(2.0)));
+        assert_eq!(calc.to_css(), ~"calc(50% + 2em - 10px)");
+        let calc = Calc::from_length(Rem(-1.0)).add(&Calc::from_length(Vw(5.0)));
+        assert_eq!(calc.to_css(), ~"calc(-1rem + 5vw)");
+        assert_eq!(LPCCalc(calc).to_css(), ~"calc(-1rem + 5vw)");
+        assert_eq!(LPCPercentage(12.5).to_css(), ~"12.5%");
+    }
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(CSSDisplayTableRowGroup.to_css(), ~"table-row-group");
+        assert_eq!(CSSBackgroundRepeatNoRepeat.to_css(), ~"no-repeat");
+        assert_eq!(CSSFontWeight700.to_css(), ~"700");
+        assert_eq!(CSSCursorNeResize.to_css(), ~"ne-resize");
+        assert_eq!(CSSTextDecorationLineThrough.to_css(), ~"line-through");
+        assert_eq!(CSSBorderColorCurrentColor.to_css(), ~"currentcolor");
+        let inherit: CSSValue<CSSDisplay> = Inherit;
+        assert_eq!(inherit.to_css(), ~"inherit");
+        assert_eq!(Specified(CSSDisplayBlock).to_css(), ~"block");
+    }
+
+    #[test]
+    fn test_values() {
+        assert_eq!(CSSMarginLength(Px(0.0)).to_css(), ~"0px");
+        assert_eq!(CSSMarginPercentage(10.0).to_css(), ~"10%");
+        assert_eq!(CSSBorderColorColor(rgba(255, 0, 0, 0.5)).to_css(), ~"rgba(255, 0, 0, 0.5)");
+        assert_eq!(CSSZIndexInteger(-3).to_css(), ~"-3");
+
+        let rect = CSSClipRect {
+            top: CSSClipEdgeLength(Px(1.0)),
+            right: CSSClipEdgeAuto,
+            bottom: CSSClipEdgeLength(Px(3.0)),
+            left: CSSClipEdgeLength(Em(4.0))
+        };
+        assert_eq!(CSSClipShape(rect).to_css(), ~"rect(1px, auto, 3px, 4em)");
+
+        let url = url::from_str("http://example.com/a.png").unwrap();
+        let content = CSSContentContent(~[
+            CSSContentItemString(~"\"quoted\""),
+            CSSContentItemCounter(~"chapter", CSSListStyleTypeDecimal),
+            CSSContentItemCounters(~"section", ~".", CSSListStyleTypeUpperRoman),
+            CSSContentItemAttr(~"title"),
+            CSSContentItemOpenQuote,
+            CSSContentItemUri(url.clone())
+        ]);
+        assert_eq!(content.to_css(),
+                   ~"\"\\\"quoted\\\"\" counter(chapter) counters(section, \".\", upper-roman) \
+                     attr(title) open-quote url(\"http://example.com/a.png\")");
+
+        let reset = CSSCounterResetCounters(~[CSSCounter { name: ~"a", value: 0 },
+                                              CSSCounter { name: ~"b", value: 2 }]);
+        assert_eq!(reset.to_css(), ~"a b 2");
+        let increment = CSSCounterIncrementCounters(~[CSSCounter { name: ~"a", value: 1 },
+                                                      CSSCounter { name: ~"b", value: 0 }]);
+        assert_eq!(increment.to_css(), ~"a b 0");
+
+        let quotes = CSSQuotesStrings(~[(~"«", ~"»"), (~"'", ~"'")]);
+        assert_eq!(quotes.to_css(), ~"\"«\" \"»\" \"'\" \"'\"");
+
+        let cursor = CSSCursor { urls: ~[url], keyword: CSSCursorPointer };
+        assert_eq!(cursor.to_css(), ~"url(\"http://example.com/a.png\"), pointer");
+    }
+}
@@ -0,0 +1,199 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+/*!
+Sharing selector matching results between siblings
+
+Siblings that selectors can't tell apart get the same `SelectResults`. Two
+elements are indistinguishable when they have the same parent, local name,
+class set and link state, neither has an id or inline style, and no
+selector in the cascade looks at siblings. `SelectHandler` exposes nothing
+else that selectors can match on.
+*/
+
+use std::libc::c_void;
+use extra::sort;
+use select::SelectHandler;
+use util::VoidPtrLike;
+use selectors::{Selector, Compound, PseudoClassSelector, NegationSelector};
+
+static DEFAULT_CAPACITY: uint = 8;
+
+/**
+A small most-recently-used cache of selector matching results, used with
+`SelectCtx::select_style_shared`, or of anything derived from them alone
+and the parent's style, like `CompleteSelectResults`.
+
+The cache keeps track of how often results were shared. It must be cleared
+whenever the sheets of the `SelectCtx` or the DOM change.
+*/
+pub struct StyleSharingCache<T> {
+    entries: ~[SharingEntry<T>],
+    capacity: uint,
+    /** Lookups answered from the cache */
+    hits: uint,
+    /** Lookups for candidates that nothing in the cache matched */
+    misses: uint,
+    /** Lookups for elements that can't share their style at all */
+    ineligible: uint
+}
+
+struct SharingEntry<T> {
+    key: SharingKey,
+    results: T
+}
+
+/** Everything selectors can match on, for an element that is a sharing candidate */
+#[deriving(Eq)]
+pub struct SharingKey {
+    parent: *c_void,
+    name: ~str,
+    classes: ~[~str],
+    is_link: bool
+}
+
+impl<T: Clone> StyleSharingCache<T> {
+    pub fn new() -> StyleSharingCache<T> {
+        StyleSharingCache::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    pub fn with_capacity(capacity: uint) -> StyleSharingCache<T> {
+        StyleSharingCache {
+            entries: ~[],
+            capacity: capacity,
+            hits: 0,
+            misses: 0,
+            ineligible: 0
+        }
+    }
+
+    /** Forget every cached result, keeping the statistics */
+    pub fn clear(&mut self) {
+        self.entries = ~[];
+    }
+
+    pub fn len(&self) -> uint {
+        self.entries.len()
+    }
+
+    /** Finds the results of an equivalent element, counting a hit or a miss */
+    pub fn find(&mut self, key: &SharingKey) -> Option<T> {
+        match self.entries.iter().position(|entry| entry.key == *key) {
+            Some(i) => {
+                self.hits += 1;
+                let entry = self.entries.remove(i);
+                let results = entry.results.clone();
+                self.entries.unshift(entry);
+                Some(results)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /** Caches `results`, evicting the least recently used entry if the cache is full */
+    pub fn insert(&mut self, key: SharingKey, results: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop();
+        }
+        self.entries.unshift(SharingEntry { key: key, results: results });
+    }
+}
+
+impl SharingKey {
+    /**
+    The key of `node`, or `None` if the node can't share its style: the root,
+    and elements with an id, are unique.
+    */
+    pub fn for_node<N: VoidPtrLike, H: SelectHandler<N>>(node: &N, handler: &H)
+                                                         -> Option<SharingKey> {
+        let has_id = do handler.with_node_id(node) |id| {
+            match id {
+                Some(id) => !id.is_empty(),
+                None => false
+            }
+        };
+        if has_id {
+            return None;
+        }
+        let parent = match handler.parent_node(node) {
+            Some(parent) => parent.to_void_ptr(),
+            None => return None
+        };
+
+        let mut classes: ~[~str] = do handler.with_node_classes(node) |classes| {
+            match classes {
+                Some(classes) => {
+                    classes.split_iter(' ').filter(|c| !c.is_empty())
+                                           .map(|c| c.to_owned())
+                                           .collect()
+                }
+                None => ~[]
+            }
+        };
+        sort::quick_sort3(classes);
+
+        Some(SharingKey {
+            parent: parent,
+            name: handler.with_node_name(node, |name| name.to_owned()),
+            classes: classes,
+            is_link: handler.node_is_link(node)
+        })
+    }
+}
+
+/**
+Whether any of the selectors may match differently on siblings with the
+same name and classes, i.e. uses sibling combinators or structural
+pseudo-classes
+*/
+pub fn is_sibling_sensitive(selectors: &[Selector]) -> bool {
+    do selectors.iter().any |selector| {
+        do selector.compounds.iter().any |compound| {
+            compound.combinator.map_default(false, |combinator| combinator.is_sibling())
+                || compound_is_structural(compound)
+        }
+    }
+}
+
+fn compound_is_structural(compound: &Compound) -> bool {
+    do compound.simple_selectors.iter().any |simple_selector| {
+        match *simple_selector {
+            PseudoClassSelector(ref name, _) => {
+                ["first-", "last-", "only-", "nth-"].iter().any(|prefix| name.starts_with(*prefix))
+                    || name.as_slice() == "empty"
+            }
+            NegationSelector(ref negated) => compound_is_structural(&**negated),
+            _ => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::is_sibling_sensitive;
+    use selectors::parse_selector_list;
+
+    fn sibling_sensitive(selectors: &str) -> bool {
+        is_sibling_sensitive(parse_selector_list(selectors).unwrap())
+    }
+
+    #[test]
+    fn test_sibling_sensitive() {
+        assert!(!sibling_sensitive("div > span.a, p#b"));
+        assert!(!sibling_sensitive("a:link, a:visited"));
+        assert!(!sibling_sensitive("a[title~=\"x\"]"));
+        assert!(sibling_sensitive("h1 + p"));
+        assert!(sibling_sensitive("h1 ~ p"));
+        assert!(sibling_sensitive("li:First-Child"));
+        assert!(sibling_sensitive("li:nth-child(2n)"));
+        assert!(sibling_sensitive("p:empty"));
+        assert!(sibling_sensitive("li:not(:last-child)"));
+    }
+}
@@ -9,22 +9,121 @@ CSS stylesheets, owned types, immutable after creation
 use extra::url::Url;
 use util::DataStream;
 use netsurfcss::stylesheet::CssStylesheet;
-use parser::{parse_stylesheet, parse_style_attribute};
+use parser::{parse_stylesheet, parse_style_attribute, scan_declarations, SourceRule};
+use selectors::parse_selector_list;
+use sharing::is_sibling_sensitive;
+use invalidation::InvalidationMap;
+use color::SystemColorPalette;
 
 pub struct Stylesheet {
-    inner: CssStylesheet
+    inner: CssStylesheet,
+    // The system colors the sheet was parsed with
+    palette: SystemColorPalette,
+    // The rules as written in the source, for the parts of the cascade
+    // libcss doesn't expose
+    rules: ~[SourceRule],
+    // Whether any selector depends on an element's siblings, which rules
+    // out sharing styles between siblings
+    sibling_sensitive: bool,
+    // Where the classes, ids, attributes and pseudo-classes of the selectors
+    // appear, for incremental restyling
+    invalidation: InvalidationMap,
+    // The name of the stylesheet set the sheet belongs to, if any
+    title: Option<~str>,
+    // Whether the sheet is an alternate, as for `<link rel="alternate stylesheet">`
+    alternate: bool
 }
 
 impl Stylesheet {
+    /** A persistent stylesheet, which applies whatever stylesheet set is selected */
     pub fn new(url: Url, input: @mut DataStream) -> Stylesheet {
+        Stylesheet::new_titled(url, input, None, false)
+    }
+
+    /**
+    A persistent stylesheet taking system colors like `ButtonFace` from
+    `palette`, for a select context with the same palette, see
+    `SelectCtx::new_with_palette`
+    */
+    pub fn new_with_palette(url: Url,
+                            input: @mut DataStream,
+                            palette: &SystemColorPalette) -> Stylesheet {
+        Stylesheet::parse(url, input, None, false, palette)
+    }
+
+    /**
+    A stylesheet with a `title`, as from the `title` attribute of `<link>` or
+    `<style>`. Titled sheets apply only while their set is selected, see
+    `SelectCtx::select_sheet_set`. Alternate sheets without a title never apply.
+    */
+    pub fn new_titled(url: Url,
+                      input: @mut DataStream,
+                      title: Option<~str>,
+                      alternate: bool) -> Stylesheet {
+        Stylesheet::parse(url, input, title, alternate, &SystemColorPalette::default())
+    }
+
+    fn parse(url: Url,
+             input: @mut DataStream,
+             title: Option<~str>,
+             alternate: bool,
+             palette: &SystemColorPalette) -> Stylesheet {
+        let (inner, rules) = {
+            let net_title = match title { Some(ref title) => title.as_slice(), None => "" };
+            parse_stylesheet(url, net_title, palette, input)
+        };
+        let mut sibling_sensitive = false;
+        let mut invalidation = InvalidationMap::new();
+        // The rules for every media count, as styles may be selected for any
+        for rule in rules.iter().filter(|rule| rule.at_keyword.is_none()) {
+            match parse_selector_list(rule.prelude) {
+                Some(selectors) => {
+                    sibling_sensitive = sibling_sensitive || is_sibling_sensitive(selectors);
+                    invalidation.add_selectors(selectors);
+                }
+                // libcss may still match what we can't read, against siblings for all we know
+                None => {
+                    sibling_sensitive = true;
+                    invalidation.add_unparseable();
+                }
+            }
+        }
         Stylesheet {
-            inner: parse_stylesheet(url, input)
+            inner: inner,
+            palette: palette.clone(),
+            rules: rules,
+            sibling_sensitive: sibling_sensitive,
+            invalidation: invalidation,
+            title: title,
+            alternate: alternate
         }
     }
 
     pub fn from_attribute(url: Url, data: &str) -> Stylesheet {
+        Stylesheet::from_attribute_with_palette(url, data, &SystemColorPalette::default())
+    }
+
+    /**
+    A `style` attribute, taking system colors from `palette`. Its declarations
+    make up a single rule without a selector.
+    */
+    pub fn from_attribute_with_palette(url: Url,
+                                       data: &str,
+                                       palette: &SystemColorPalette) -> Stylesheet {
         Stylesheet {
-            inner: parse_style_attribute(url, data)
+            inner: parse_style_attribute(url, data, palette),
+            palette: palette.clone(),
+            rules: ~[SourceRule {
+                at_keyword: None,
+                prelude: ~"",
+                declarations: scan_declarations(data),
+                line: 1,
+                media: None,
+            }],
+            sibling_sensitive: false,
+            invalidation: InvalidationMap::new(),
+            title: None,
+            alternate: false
         }
     }
 }
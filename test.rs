@@ -5,16 +5,26 @@
 use extra::url::Url;
 use std::cast;
 use std::libc;
+use std::managed;
 use util::{DataStream, VoidPtrLike};
 use values::*;
 use types::*;
 use units::*;
 use select::*;
 use color;
-use color::rgb;
+use color::{Color, SystemColorPalette, SystemColorWindowText, rgb};
+use page::{PageLeft, PageRight};
+use property::*;
+use damage::{REPAINT, REFLOW, REBUILD_BOX_TREE};
 use stylesheet::Stylesheet;
 use computed::ComputedStyle;
-use complete::CompleteSelectResults;
+use complete::{CompleteSelectResults, CompleteStyle};
+use sharing::StyleSharingCache;
+use bloom::AncestorBloomFilter;
+use resolver::{StyleResolver, StyleTree};
+use units::Viewport;
+use invalidation::*;
+use extra::test::BenchHarness;
 
 fn test_url() -> Url {
     FromStr::from_str("http://foo.com").unwrap()
@@ -105,12 +115,31 @@ impl SelectHandler<TestNode> for TestHandler {
     }
     fn parent_node(&self, node: &TestNode) -> Option<TestNode> { *(**node).parent }
     fn node_has_id(&self, node: &TestNode, name: &str) -> bool { name == node.id }
-    fn named_ancestor_node(&self, _node: &TestNode, _name: &str) -> Option<TestNode> { fail!(~"TODO") }
+    fn named_ancestor_node(&self, node: &TestNode, name: &str) -> Option<TestNode> {
+        let mut current = self.parent_node(node);
+        while current.is_some() {
+            let ancestor = current.unwrap();
+            if name == (*ancestor).name {
+                return Some(ancestor);
+            }
+            current = self.parent_node(&ancestor);
+        }
+        None
+    }
     fn node_is_root(&self, node: &TestNode) -> bool { self.parent_node(node).is_none() }
     fn node_is_link(&self, node: &TestNode) -> bool { "a" == (**node).name }
     fn node_has_class(&self, _node: &TestNode, _s: &str) -> bool { true }
 }
 
+impl StyleTree<TestNode> for TestHandler {
+    fn children(&self, node: &TestNode) -> ~[TestNode] {
+        (*node).children.map(|&child| child)
+    }
+    fn with_inline_style<R>(&self, _node: &TestNode, f: &fn(Option<&Stylesheet>) -> R) -> R {
+        f(None)
+    }
+}
+
 fn single_div_test(style: &str, f: &fn(&ComputedStyle)) {
     let sheet = Stylesheet::new(test_url(), style_stream(style));
     let mut select_ctx = SelectCtx::new();
@@ -143,12 +172,30 @@ fn single_html_test(style: &str, f: &fn(&ComputedStyle)) {
     f(&computed);
 }
 
+// Nodes that are each the only child of the one before, from the root down
+fn node_chain(names_and_ids: &[(&str, &str)]) -> ~[TestNode] {
+    let mut nodes: ~[TestNode] = ~[];
+    for &(name, id) in names_and_ids.rev_iter() {
+        let node = TestNode(@NodeData {
+            name: name.to_owned(),
+            id: id.to_owned(),
+            children: if nodes.is_empty() { ~[] } else { ~[nodes[0]] },
+            parent: @mut None
+        });
+        if !nodes.is_empty() {
+            *nodes[0].parent = Some(node);
+        }
+        nodes.unshift(node);
+    }
+    nodes
+}
+
 #[test]
 fn test_background_color_simple() {
     let style = "div { background-color: #123456; }";
     do single_div_test(style) |computed| {
         let color = computed.background_color();
-        assert!(color == Specified(rgb(0x12, 0x34, 0x56)));
+        assert!(color == Specified(CSSBackgroundColorColor(rgb(0x12, 0x34, 0x56))));
     }
 }
 
@@ -267,10 +314,10 @@ fn test_border_color() {
         let right_color = computed.border_right_color();
         let bottom_color = computed.border_bottom_color();
         let left_color = computed.border_left_color();
-        assert!(top_color == Specified(rgb(255, 0, 0)));
-        assert!(right_color == Specified(rgb(0, 128, 0)));
-        assert!(bottom_color == Specified(rgb(0, 0, 255)));
-        assert!(left_color == Specified(rgb(255, 255, 0)));
+        assert!(top_color == Specified(CSSBorderColorColor(rgb(255, 0, 0))));
+        assert!(right_color == Specified(CSSBorderColorColor(rgb(0, 128, 0))));
+        assert!(bottom_color == Specified(CSSBorderColorColor(rgb(0, 0, 255))));
+        assert!(left_color == Specified(CSSBorderColorColor(rgb(255, 255, 0))));
     }
 }
 
@@ -284,11 +331,70 @@ fn test_border_color_shorthand() {
         let right_color = computed.border_right_color();
         let bottom_color = computed.border_bottom_color();
         let left_color = computed.border_left_color();
-        assert!(top_color == Specified(rgb(255, 0, 0)));
-        assert!(right_color == Specified(rgb(255, 0, 0)));
-        assert!(bottom_color == Specified(rgb(255, 0, 0)));
-        assert!(left_color == Specified(rgb(255, 0, 0)));
+        assert!(top_color == Specified(CSSBorderColorColor(rgb(255, 0, 0))));
+        assert!(right_color == Specified(CSSBorderColorColor(rgb(255, 0, 0))));
+        assert!(bottom_color == Specified(CSSBorderColorColor(rgb(255, 0, 0))));
+        assert!(left_color == Specified(CSSBorderColorColor(rgb(255, 0, 0))));
+    }
+}
+
+#[test]
+fn test_transparent() {
+    let style = "div { background-color: transparent; border-top-color: transparent; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.background_color() == Specified(CSSBackgroundColorTransparent));
+        assert!(computed.border_top_color() == Specified(CSSBorderColorTransparent));
+    }
+}
+
+#[test]
+fn test_current_color() {
+    let style = "div { color: red; border-top-color: currentColor; background-color: currentColor; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.border_top_color() == Specified(CSSBorderColorCurrentColor));
+        assert!(computed.background_color() == Specified(CSSBackgroundColorCurrentColor));
     }
+
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let handler = TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let dom = TestNode(@NodeData {
+        name: ~"div",
+        id: ~"id1",
+        children: ~[],
+        parent: @mut None
+    });
+    let results = CompleteSelectResults::new_root(select_ctx.select_style(&dom, None, &handler));
+    let complete = results.computed_style();
+    assert!(complete.border_top_color() == rgb(255, 0, 0));
+    assert!(complete.background_color() == rgb(255, 0, 0));
+}
+
+#[test]
+fn test_system_color() {
+    let style = "div { color: WindowText; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.color() == Specified(rgb(0, 0, 0)));
+    }
+
+    let mut palette = SystemColorPalette::default();
+    palette.set(SystemColorWindowText, rgb(1, 2, 3));
+    let sheet = Stylesheet::new_with_palette(test_url(), style_stream(style), &palette);
+    let mut select_ctx = SelectCtx::new_with_palette(palette.clone());
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    assert!(div_color(&select_ctx) == Specified(rgb(1, 2, 3)));
+    assert!(*select_ctx.system_color_palette() == palette);
+}
+
+#[test]
+#[should_fail]
+fn test_system_color_palette_mismatch() {
+    let mut palette = SystemColorPalette::default();
+    palette.set(SystemColorWindowText, rgb(1, 2, 3));
+    let sheet = Stylesheet::new_with_palette(test_url(), style_stream("div {}"), &palette);
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
 }
 
 #[test]
@@ -367,6 +473,66 @@ fn test_height() {
     }
 }
 
+#[test]
+fn test_property_get() {
+    let style = "div { width: 10px; color: red; z-index: 3; orphans: inherit; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.get(PropWidth) == Specified(PropertyValueWidth(CSSWidthLength(Px(10.0)))));
+        assert!(computed.get(Property::from_name("color").unwrap())
+                == Specified(PropertyValueColor(rgb(255, 0, 0))));
+        assert!(computed.get(PropZIndex) == Specified(PropertyValueZIndex(CSSZIndexInteger(3))));
+        assert!(computed.get(PropOrphans) == Inherit);
+    }
+}
+
+fn complete_div_results(style: &str) -> CompleteSelectResults {
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let handler = TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let dom = TestNode(@NodeData {
+        name: ~"div",
+        id: ~"id1",
+        children: ~[],
+        parent: @mut None
+    });
+    CompleteSelectResults::new_root(select_ctx.select_style(&dom, None, &handler))
+}
+
+#[test]
+fn test_diff() {
+    let base = complete_div_results("div { color: red; }");
+    let same = complete_div_results("div { color: red; }");
+    let recolored = complete_div_results("div { color: blue; cursor: pointer; }");
+    let resized = complete_div_results("div { color: blue; width: 10px; }");
+    let hidden = complete_div_results("div { color: red; display: none; }");
+    let pointer = complete_div_results("div { color: red; cursor: pointer; }");
+
+    let base = base.computed_style();
+    assert!(base.diff(&same.computed_style()).is_empty());
+    assert!(base.diff(&pointer.computed_style()).is_empty());
+    assert!(base.diff(&recolored.computed_style()) == REPAINT);
+    assert!(base.diff(&resized.computed_style()) == REFLOW);
+    assert!(resized.computed_style().diff(&base) == REFLOW);
+    assert!(base.diff(&hidden.computed_style()) == REBUILD_BOX_TREE);
+}
+
+#[test]
+fn test_to_css_text() {
+    let style = "div { width: 10px; color: red; orphans: inherit; \
+                 font-family: \"Times New Roman\", \"serif\", monospace; }";
+    do single_div_test(style) |computed| {
+        let text = computed.to_css_text();
+        assert!(text.starts_with("margin-top: "));
+        assert!(text.ends_with(";"));
+        assert!(text.contains("text-align: left;"));
+        assert!(text.contains("width: 10px;"));
+        assert!(text.contains("color: rgb(255, 0, 0);"));
+        assert!(text.contains("orphans: inherit;"));
+        assert!(text.contains("font-family: Times New Roman, \"serif\", monospace;"));
+    }
+}
+
 #[test]
 fn test_font_family_generic() {
     use units::Fantasy;
@@ -565,3 +731,706 @@ fn test_compose() {
 
     assert!(computed.background_color() == color::parsing::parse_color("blue").unwrap());
 }
+
+#[test]
+fn test_z_index() {
+    let style = "div { z-index: -3; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.z_index() == Specified(CSSZIndexInteger(-3)));
+    }
+    let style = "div { z-index: auto; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.z_index() == Specified(CSSZIndexAuto));
+    }
+}
+
+#[test]
+fn test_clip() {
+    let style = "div { clip: rect(5px, auto, 10px, 0px); }";
+    do single_div_test(style) |computed| {
+        assert!(computed.clip() == Specified(CSSClipShape(CSSClipRect {
+            top: CSSClipEdgeLength(Px(5.0)),
+            right: CSSClipEdgeAuto,
+            bottom: CSSClipEdgeLength(Px(10.0)),
+            left: CSSClipEdgeLength(Px(0.0))
+        })));
+    }
+}
+
+#[test]
+fn test_opacity() {
+    let style = "div { opacity: 0.5; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.opacity() == Specified(0.5));
+    }
+}
+
+#[test]
+fn test_outline() {
+    let style = "div { outline-width: 2px; outline-style: dashed; outline-color: invert; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.outline_width() == Specified(CSSBorderWidthLength(Px(2.0))));
+        assert!(computed.outline_style() == Specified(CSSBorderStyleDashed));
+        assert!(computed.outline_color() == Specified(CSSOutlineColorInvert));
+    }
+    let style = "div { outline-color: red; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.outline_color() == Specified(CSSOutlineColorColor(rgb(255, 0, 0))));
+    }
+}
+
+#[test]
+fn test_cursor() {
+    let style = "div { cursor: pointer; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.cursor() == Specified(CSSCursor {
+            urls: ~[],
+            keyword: CSSCursorPointer
+        }));
+    }
+    let style = "div { cursor: url(x.cur), pointer; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.cursor() == Specified(CSSCursor {
+            urls: ~[FromStr::from_str("http://foo.com/x.cur").unwrap()],
+            keyword: CSSCursorPointer
+        }));
+    }
+}
+
+#[test]
+fn test_page_breaks() {
+    let style = "div { page-break-before: always; page-break-after: left; page-break-inside: avoid; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.page_break_before() == Specified(CSSPageBreakBeforeAlways));
+        assert!(computed.page_break_after() == Specified(CSSPageBreakAfterLeft));
+        assert!(computed.page_break_inside() == Specified(CSSPageBreakInsideAvoid));
+    }
+}
+
+#[test]
+fn test_orphans_widows() {
+    let style = "div { orphans: 3; widows: 4; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.orphans() == Specified(3));
+        assert!(computed.widows() == Specified(4));
+    }
+}
+
+#[test]
+fn test_page_style() {
+    let style = "@page { margin: 1in 2in; }\
+                 @page :first { margin-top: 3in; }\
+                 @page :left { margin-left: auto; }\
+                 div { margin-top: 10px; }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+
+    let page = select_ctx.select_page_style(MediaTypePrint, true, PageRight);
+    assert!(page.margin_top == Some(CSSMarginLength(Px(288.0))));
+    assert!(page.margin_right == Some(CSSMarginLength(Px(192.0))));
+    assert!(page.margin_bottom == Some(CSSMarginLength(Px(96.0))));
+    assert!(page.margin_left == Some(CSSMarginLength(Px(192.0))));
+
+    let page = select_ctx.select_page_style(MediaTypePrint, false, PageLeft);
+    assert!(page.margin_top == Some(CSSMarginLength(Px(96.0))));
+    assert!(page.margin_left == Some(CSSMarginAuto));
+}
+
+#[test]
+fn test_page_style_media() {
+    let style = "@media print { @page { margin: 1in } }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+
+    let page = select_ctx.select_page_style(MediaTypePrint, false, PageRight);
+    assert!(page.margin_top == Some(CSSMarginLength(Px(96.0))));
+    assert!(page.margin_left == Some(CSSMarginLength(Px(96.0))));
+    let page = select_ctx.select_page_style(MediaTypeScreen, false, PageRight);
+    assert!(page.margin_top.is_none());
+}
+
+#[test]
+fn test_select_style_for_media() {
+    let style = "div { color: red; } @media print, tv { div { color: blue; } }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let handler = &TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let div = node_chain([("div", "")])[0];
+
+    let style = select_ctx.select_style(&div, None, handler);
+    assert!(style.computed_style().color() == Specified(rgb(255, 0, 0)));
+    let style = select_ctx.select_style_for_media(&div, MediaTypePrint, None, handler);
+    assert!(style.computed_style().color() == Specified(rgb(0, 0, 255)));
+}
+
+#[test]
+fn test_page_style_invalid() {
+    let style = "@page { margin-top: 1in; margin-top: bogus; margin: 2in bogus; }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+
+    let page = select_ctx.select_page_style(MediaTypePrint, false, PageRight);
+    assert!(page.margin_top == Some(CSSMarginLength(Px(96.0))));
+    assert!(page.margin_right.is_none());
+}
+
+#[test]
+fn test_page_style_unset() {
+    let style = "div { margin-top: 10px; }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+
+    let page = select_ctx.select_page_style(MediaTypePrint, true, PageRight);
+    assert!(page.margin_top.is_none());
+    assert!(page.margin_left.is_none());
+}
+
+#[test]
+fn test_content() {
+    let style = "div { content: \"foo\" attr(title) open-quote; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.content() == Specified(CSSContentContent(~[
+            CSSContentItemString(~"foo"),
+            CSSContentItemAttr(~"title"),
+            CSSContentItemOpenQuote
+        ])));
+    }
+    let style = "div { content: counter(item, upper-roman); }";
+    do single_div_test(style) |computed| {
+        assert!(computed.content() == Specified(CSSContentContent(~[
+            CSSContentItemCounter(~"item", CSSListStyleTypeUpperRoman)
+        ])));
+    }
+    let style = "div { content: url(a.png); }";
+    do single_div_test(style) |computed| {
+        assert!(computed.content() == Specified(CSSContentContent(~[
+            CSSContentItemUri(FromStr::from_str("http://foo.com/a.png").unwrap())
+        ])));
+    }
+}
+
+#[test]
+fn test_counters() {
+    let style = "div { counter-reset: chapter 2 section; counter-increment: chapter; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.counter_reset() == Specified(CSSCounterResetCounters(~[
+            CSSCounter { name: ~"chapter", value: 2 },
+            CSSCounter { name: ~"section", value: 0 }
+        ])));
+        assert!(computed.counter_increment() == Specified(CSSCounterIncrementCounters(~[
+            CSSCounter { name: ~"chapter", value: 1 }
+        ])));
+    }
+}
+
+#[test]
+fn test_quotes() {
+    let style = "div { quotes: \"<\" \">\" \"(\" \")\"; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.quotes() == Specified(CSSQuotesStrings(~[
+            (~"<", ~">"),
+            (~"(", ~")")
+        ])));
+    }
+}
+
+#[test]
+fn test_list_style() {
+    let style = "div { list-style-type: lower-greek; list-style-position: inside; }";
+    do single_div_test(style) |computed| {
+        assert!(computed.list_style_type() == Specified(CSSListStyleTypeLowerGreek));
+        assert!(computed.list_style_position() == Specified(CSSListStylePositionInside));
+        assert!(computed.list_style_image() == Specified(CSSListStyleImageNone));
+    }
+    let style = "div { list-style-image: url(a.png); }";
+    do single_div_test(style) |computed| {
+        let url = FromStr::from_str("http://foo.com/a.png").unwrap();
+        assert!(computed.list_style_image() == Specified(CSSListStyleImageUri(url)));
+    }
+}
+
+fn sibling_nodes(names_and_ids: &[(&str, &str)]) -> ~[TestNode] {
+    let children = do names_and_ids.map |&(name, id)| {
+        TestNode(@NodeData {
+            name: name.to_owned(),
+            id: id.to_owned(),
+            children: ~[],
+            parent: @mut None
+        })
+    };
+    let parent = TestNode(@NodeData {
+        name: ~"div",
+        id: ~"parent",
+        children: children.map(|&child| child),
+        parent: @mut None
+    });
+    for child in children.iter() {
+        *child.parent = Some(parent);
+    }
+    children
+}
+
+#[test]
+fn test_style_sharing() {
+    let sheet = Stylesheet::new(test_url(), style_stream("span { color: red; }"));
+    let mut select_ctx = SelectCtx::new();
+    let handler = TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let nodes = sibling_nodes([("span", ""), ("span", ""), ("span", "x"), ("span", ""), ("p", "")]);
+    let mut cache = StyleSharingCache::new();
+
+    let first = select_ctx.select_style_shared(&nodes[0], MediaTypeScreen, None, &handler,
+                                               &mut cache);
+    let second = select_ctx.select_style_shared(&nodes[1], MediaTypeScreen, None, &handler,
+                                                &mut cache);
+    assert!(managed::ptr_eq(first, second));
+    assert!(second.computed_style().color() == Specified(rgb(255, 0, 0)));
+
+    let with_id = select_ctx.select_style_shared(&nodes[2], MediaTypeScreen, None, &handler,
+                                                 &mut cache);
+    assert!(!managed::ptr_eq(first, with_id));
+    let inline = Stylesheet::from_attribute(test_url(), "color: blue");
+    let styled = select_ctx.select_style_shared(&nodes[3], MediaTypeScreen, Some(&inline),
+                                                &handler, &mut cache);
+    assert!(!managed::ptr_eq(first, styled));
+    assert!(styled.computed_style().color() == Specified(rgb(0, 0, 255)));
+    let other_name = select_ctx.select_style_shared(&nodes[4], MediaTypeScreen, None, &handler,
+                                                    &mut cache);
+    assert!(!managed::ptr_eq(first, other_name));
+
+    assert!(cache.hits == 1);
+    assert!(cache.misses == 2);
+    assert!(cache.ineligible == 2);
+    assert!(cache.len() == 2);
+    cache.clear();
+    assert!(cache.len() == 0);
+}
+
+#[test]
+fn test_style_sharing_sibling_selectors() {
+    // The namespaced selector is one we can't parse ourselves
+    for &style in ["span + span { color: red; }", "ns|span:first-child { color: red; }"].iter() {
+        let sheet = Stylesheet::new(test_url(), style_stream(style));
+        let mut select_ctx = SelectCtx::new();
+        let handler = TestHandler::new();
+        select_ctx.append_sheet(sheet, OriginAuthor);
+        let nodes = sibling_nodes([("span", ""), ("span", "")]);
+        let mut cache = StyleSharingCache::new();
+
+        let first = select_ctx.select_style_shared(&nodes[0], MediaTypeScreen, None, &handler,
+                                                   &mut cache);
+        let second = select_ctx.select_style_shared(&nodes[1], MediaTypeScreen, None, &handler,
+                                                    &mut cache);
+        assert!(!managed::ptr_eq(first, second));
+        assert!(cache.hits == 0);
+        assert!(cache.ineligible == 2);
+    }
+}
+
+#[test]
+fn test_style_resolver() {
+    let style = "div { font-size: 20px; } span { color: red; } div span { font-style: italic; }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let handler = TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let nodes = sibling_nodes([("span", ""), ("span", ""), ("p", "")]);
+    let root = (*nodes[0].parent).unwrap();
+
+    let mut resolver = StyleResolver::new(&select_ctx, Viewport { width: 800.0, height: 600.0 });
+    resolver.enable_bloom_filter();
+    resolver.enable_style_sharing();
+    let mut visited = ~[];
+    do resolver.resolve(&root, &handler, &handler) |node, results| {
+        let style = results.computed_style();
+        assert!(style.font_size_px() == 20.0);
+        if (*node).name == ~"span" {
+            assert!(style.color() == rgb(255, 0, 0));
+            assert!(style.font_style() == CSSFontStyleItalic);
+        }
+        visited.push((*node).name.clone());
+    }
+    assert!(visited == ~[~"div", ~"span", ~"span", ~"p"]);
+
+    let sharing = resolver.sharing.get_ref();
+    assert!(sharing.hits == 1);
+    assert!(sharing.misses == 2);
+    assert!(sharing.ineligible == 1);
+}
+
+#[test]
+fn test_invalidation() {
+    let style = ".menu > li { color: red; } li.active { color: blue; } \
+                 @media screen, print { .open + li { color: green; } } \
+                 @media print { .closed + li { color: green; } }";
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(style)), OriginAuthor);
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream("li:hover .active {}")),
+                            OriginUser);
+    select_ctx.append_sheet(Stylesheet::from_attribute(test_url(), "color: red"), OriginAuthor);
+
+    let handler = TestHandler::new();
+    let li = TestNode(@NodeData { name: ~"li", id: ~"", children: ~[], parent: @mut None });
+    let span = TestNode(@NodeData { name: ~"span", id: ~"", children: ~[], parent: @mut None });
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"menu"), &handler) == InvalidateDescendants);
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"active"), &handler) == InvalidateSelf);
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"open"), &handler) == InvalidateSiblings);
+    assert!(select_ctx.invalidate(&li, &StateChange(~"hover"), &handler) == InvalidateDescendants);
+    assert!(select_ctx.invalidate(&li, &IdChange(~"main"), &handler) == InvalidateNothing);
+    // Styles may be selected for any media
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"closed"), &handler) == InvalidateSiblings);
+    // 'li:hover' can't match a span, but '.active' can
+    assert!(select_ctx.invalidate(&span, &StateChange(~"hover"), &handler) == InvalidateNothing);
+    assert!(select_ctx.invalidate(&span, &ClassChange(~"active"), &handler) == InvalidateSelf);
+}
+
+#[test]
+fn test_invalidation_unparseable() {
+    let style = ".a { color: red; } ns|li.b { color: blue; }";
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(style)), OriginAuthor);
+
+    let handler = TestHandler::new();
+    let li = node_chain([("li", "")])[0];
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"a"), &handler) == InvalidateSubtree);
+    assert!(select_ctx.invalidate(&li, &ClassChange(~"b"), &handler) == InvalidateSubtree);
+}
+
+fn div_color(select_ctx: &SelectCtx) -> CSSValue<Color> {
+    let handler = TestHandler::new();
+    let dom = TestNode(@NodeData {
+        name: ~"div",
+        id: ~"id1",
+        children: ~[],
+        parent: @mut None
+    });
+    select_ctx.select_style(&dom, None, &handler).computed_style().color()
+}
+
+#[test]
+fn test_sheet_handles() {
+    let sheet = |style: &str| Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let red = select_ctx.append_sheet(sheet("div { color: red; }"), OriginAuthor);
+    let blue = select_ctx.append_sheet(sheet("div { color: blue; }"), OriginAuthor);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 0, 255)));
+    assert!(red != blue);
+
+    select_ctx.set_sheet_disabled(blue, true);
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+    // Disabled sheets keep their place
+    let green = select_ctx.insert_sheet_before(sheet("div { color: green; }"), OriginAuthor, blue);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 128, 0)));
+    select_ctx.set_sheet_disabled(blue, false);
+    select_ctx.set_sheet_disabled(blue, false);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 0, 255)));
+
+    let order = select_ctx.sheets().map(|status| status.handle);
+    assert!(order == ~[red, green, blue]);
+    select_ctx.set_sheet_disabled(red, true);
+    assert!(select_ctx.sheets()[0].disabled);
+    assert!(!select_ctx.sheets()[2].disabled);
+
+    let removed = select_ctx.remove_sheet(blue);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 128, 0)));
+    select_ctx.remove_sheet(green);
+    select_ctx.remove_sheet(red);
+    assert!(select_ctx.sheets().is_empty());
+
+    // A removed sheet can go back in
+    select_ctx.append_sheet(removed, OriginAuthor);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 0, 255)));
+}
+
+#[test]
+fn test_sheet_sets() {
+    let sheet = |style: &str, title: Option<~str>, alternate: bool| {
+        Stylesheet::new_titled(test_url(), style_stream(style), title, alternate)
+    };
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet("div { color: red; }", None, false), OriginAuthor);
+    let plain = select_ctx.append_sheet(sheet("div { color: green; }", Some(~"Plain"), false),
+                                        OriginAuthor);
+    select_ctx.append_sheet(sheet("div { color: blue; }", Some(~"Fancy"), true), OriginAuthor);
+    select_ctx.append_sheet(sheet("div { color: yellow; }", None, true), OriginAuthor);
+
+    assert!(select_ctx.sheet_sets() == ~[~"Plain", ~"Fancy"]);
+    assert!(select_ctx.preferred_sheet_set() == Some(~"Plain"));
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 128, 0)));
+
+    select_ctx.select_sheet_set(Some("Fancy"));
+    assert!(select_ctx.current_sheet_set() == Some(~"Fancy"));
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 0, 255)));
+    let applied = select_ctx.sheets().map(|status| status.applied);
+    assert!(applied == ~[true, false, true, false]);
+
+    // Only the persistent sheet is in a set no sheet has
+    select_ctx.select_sheet_set(Some("Missing"));
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+
+    select_ctx.select_sheet_set(None);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 128, 0)));
+
+    // Disabling a sheet and leaving its set are independent
+    select_ctx.set_sheet_disabled(plain, true);
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+    select_ctx.select_sheet_set(Some("Fancy"));
+    select_ctx.select_sheet_set(Some("Plain"));
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+    select_ctx.set_sheet_disabled(plain, false);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 128, 0)));
+
+    // Without a preferred sheet, no alternate applies
+    select_ctx.select_sheet_set(None);
+    select_ctx.remove_sheet(plain);
+    assert!(select_ctx.preferred_sheet_set() == None);
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+}
+
+#[test]
+fn test_matched_rules() {
+    let ua_style = "div { color: black; }";
+    let author_style = "body div { color: red; margin-top: 1px; }\n\
+                        #id1 { color: blue !important; margin: 0; }\n\
+                        span { color: green; }\n\
+                        div > p, html div { display: block; }\n\
+                        h1 + div, p:hover { color: pink; }\n\
+                        @media screen { div { margin: 2px; colour: red; } }\n\
+                        @media print { div { color: gray; } }\n\
+                        div::before { content: 'x'; }";
+    let mut select_ctx = SelectCtx::new();
+    let ua = select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(ua_style)),
+                                     OriginUA);
+    let author = select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(author_style)),
+                                         OriginAuthor);
+
+    let html = TestNode(@NodeData { name: ~"html", id: ~"", children: ~[], parent: @mut None });
+    let body = TestNode(@NodeData { name: ~"body", id: ~"", children: ~[], parent: @mut None });
+    let div = TestNode(@NodeData { name: ~"div", id: ~"id1", children: ~[], parent: @mut None });
+    *body.parent = Some(html);
+    *div.parent = Some(body);
+
+    let rules = select_ctx.matched_rules(&div, MediaTypeScreen, None, &TestHandler::new());
+    let selectors = rules.map(|rule| rule.selector.clone());
+    assert!(selectors == ~[~"div", ~"div", ~"body div", ~"html div", ~"h1 + div", ~"#id1"]);
+    assert!(rules.map(|rule| rule.line) == ~[1, 6, 1, 4, 5, 2]);
+    assert!(rules.map(|rule| rule.sheet) ==
+            ~[Some(ua), Some(author), Some(author), Some(author), Some(author), Some(author)]);
+    assert!(rules[0].origin == OriginUA);
+    assert!(rules[2].specificity == (0, 0, 2));
+    assert!(rules[5].specificity == (1, 0, 0));
+
+    // Siblings can't be matched here, so whether libcss matched is unknown
+    assert!(rules.map(|rule| rule.unknown) == ~[false, false, false, false, true, false]);
+    assert!(rules[4].declarations[0].overridden);
+
+    // The important color wins, from the rule that would lose otherwise
+    assert!(rules[0].declarations[0].overridden);
+    assert!(rules[2].declarations[0].name == ~"color");
+    assert!(rules[2].declarations[0].overridden);
+    assert!(rules[5].declarations[0].important);
+    assert!(!rules[5].declarations[0].overridden);
+
+    // Shorthands override longhands and the other way around
+    assert!(rules[1].declarations[0].longhands.len() == 4);
+    assert!(rules[1].declarations[0].overridden);
+    assert!(rules[2].declarations[1].overridden);
+    assert!(!rules[5].declarations[1].overridden);
+
+    // libcss drops the misspelt property
+    assert!(rules[1].declarations[1].ignored);
+    assert!(!rules[1].declarations[1].overridden);
+    assert!(div_color(&select_ctx) == Specified(rgb(0, 0, 255)));
+}
+
+#[test]
+fn test_matched_rules_values() {
+    let style = "div { color: red; margin: 1px; font: 12px serif; }\n\
+                 div { color: bogus; margin: 2px bogus; font: 14px serif; }";
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(style)), OriginAuthor);
+
+    let div = node_chain([("div", "")])[0];
+    let rules = select_ctx.matched_rules(&div, MediaTypeScreen, None, &TestHandler::new());
+    assert!(rules.len() == 2);
+    // libcss drops the invalid values, so the first rule's win
+    assert!(rules[1].declarations[0].ignored);
+    assert!(rules[1].declarations[1].ignored);
+    assert!(!rules[0].declarations[0].overridden);
+    assert!(!rules[0].declarations[1].overridden);
+    assert!(div_color(&select_ctx) == Specified(rgb(255, 0, 0)));
+    // 'font-variant' isn't computed here, so whether libcss kept the fonts is unknown
+    assert!(rules[0].declarations[2].unknown && rules[1].declarations[2].unknown);
+    assert!(!rules[0].declarations[2].overridden);
+}
+
+#[test]
+fn test_matched_rules_inline_style() {
+    let style = "#id1 { color: blue; margin-top: 1px; } div { color: red !important; }";
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(style)), OriginAuthor);
+
+    let div = node_chain([("div", "id1")])[0];
+    let inline = Stylesheet::from_attribute(test_url(), "color: green; margin-top: 2px");
+    let rules = select_ctx.matched_rules(&div, MediaTypeScreen, Some(&inline),
+                                         &TestHandler::new());
+    assert!(rules.map(|rule| rule.selector.clone()) == ~[~"div", ~"#id1", ~""]);
+    assert!(rules[2].sheet.is_none());
+    // The inline style beats the id selector, but not an important declaration
+    assert!(rules[1].declarations[1].overridden);
+    assert!(!rules[2].declarations[1].overridden);
+    assert!(rules[2].declarations[0].overridden);
+    assert!(!rules[0].declarations[0].overridden);
+}
+
+// A chain of `depth` divs with a span at the bottom, returning the root
+fn deep_tree(depth: uint) -> TestNode {
+    let mut node = TestNode(@NodeData {
+        name: ~"span",
+        id: ~"",
+        children: ~[],
+        parent: @mut None
+    });
+    for _ in range(0, depth) {
+        let parent = TestNode(@NodeData {
+            name: ~"div",
+            id: ~"",
+            children: ~[node],
+            parent: @mut None
+        });
+        *node.parent = Some(parent);
+        node = parent;
+    }
+    node
+}
+
+fn deep_tree_ctx() -> SelectCtx {
+    let style = "section span { color: red; } div span { font-style: italic; }";
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    select_ctx
+}
+
+#[test]
+fn test_bloom_filter_selection() {
+    let select_ctx = deep_tree_ctx();
+    let handler = TestHandler::new();
+    let root = deep_tree(10);
+    let mut bloom = AncestorBloomFilter::new();
+    let mut visited = 0;
+    do bloom.traverse(&root, &handler, |node| (*node).children.map(|&child| child)) |node, bloom| {
+        assert!(bloom.depth() == visited);
+        let with_bloom = select_ctx.select_style_with_bloom(node, MediaTypeScreen, None, &handler,
+                                                            bloom);
+        let without = select_ctx.select_style(node, None, &handler);
+        assert!(with_bloom.computed_style().color() == without.computed_style().color());
+        assert!(with_bloom.computed_style().font_style()
+                == without.computed_style().font_style());
+        if (*node).name == ~"span" {
+            assert!(bloom.might_have_ancestor_named("div"));
+            assert!(bloom.might_have_ancestor_named("DIV"));
+            assert!(!bloom.might_have_ancestor_named("section"));
+            assert!(with_bloom.computed_style().font_style() == Specified(CSSFontStyleItalic));
+        }
+        visited += 1;
+    }
+    assert!(visited == 11);
+    assert!(bloom.depth() == 0);
+}
+
+#[test]
+fn test_bloom_filter_matched_rules() {
+    let style = "#main div { color: red; } #other div { color: blue; } section div { margin: 0; }";
+    let mut select_ctx = SelectCtx::new();
+    select_ctx.append_sheet(Stylesheet::new(test_url(), style_stream(style)), OriginAuthor);
+    let handler = TestHandler::new();
+    let nodes = node_chain([("html", ""), ("body", "main"), ("div", "")]);
+    let mut bloom = AncestorBloomFilter::new();
+    bloom.rebuild_for(&nodes[2], &handler);
+    assert!(bloom.might_have_ancestor_with_id("main"));
+    assert!(!bloom.might_have_ancestor_with_id("other"));
+
+    let with_bloom = select_ctx.matched_rules_with_bloom(&nodes[2], MediaTypeScreen, None,
+                                                         &handler, &bloom);
+    let without = select_ctx.matched_rules(&nodes[2], MediaTypeScreen, None, &handler);
+    assert!(with_bloom.map(|rule| rule.selector.clone()) == ~[~"#main div"]);
+    assert!(without.map(|rule| rule.selector.clone()) == ~[~"#main div"]);
+}
+
+#[bench]
+fn bench_deep_tree_without_bloom(bh: &mut BenchHarness) {
+    let select_ctx = deep_tree_ctx();
+    let handler = TestHandler::new();
+    let root = deep_tree(200);
+    do bh.iter {
+        let mut node = Some(root);
+        while node.is_some() {
+            let current = node.unwrap();
+            select_ctx.select_style(&current, None, &handler);
+            node = if (*current).children.is_empty() { None } else { Some((*current).children[0]) };
+        }
+    }
+}
+
+#[bench]
+fn bench_deep_tree_with_bloom(bh: &mut BenchHarness) {
+    let select_ctx = deep_tree_ctx();
+    let handler = TestHandler::new();
+    let root = deep_tree(200);
+    let mut bloom = AncestorBloomFilter::new();
+    do bh.iter {
+        do bloom.traverse(&root, &handler, |node| (*node).children.map(|&child| child))
+                |node, bloom| {
+            select_ctx.select_style_with_bloom(node, MediaTypeScreen, None, &handler, bloom);
+        }
+    }
+}
+
+
+// Like `child_test`, with the complete styles of the parent and the child
+fn complete_child_test(style: &str, f: &fn(&CompleteStyle, &CompleteStyle)) {
+    let sheet = Stylesheet::new(test_url(), style_stream(style));
+    let mut select_ctx = SelectCtx::new();
+    let handler = &TestHandler::new();
+    select_ctx.append_sheet(sheet, OriginAuthor);
+    let child = TestNode(@NodeData {
+        name: ~"span",
+        id: ~"id1",
+        children: ~[],
+        parent: @mut None
+    });
+    let parent = TestNode(@NodeData {
+        name: ~"div",
+        id: ~"id2",
+        children: ~[child],
+        parent: @mut None
+    });
+    *child.parent = Some(parent);
+    let parent_results = select_ctx.select_style(&parent, None, handler);
+    let child_results = select_ctx.select_style(&child, None, handler);
+
+    let complete_parent_results = CompleteSelectResults::new_root(parent_results);
+    let complete_child_results = CompleteSelectResults::new_from_parent(&complete_parent_results,
+                                                                        child_results);
+    f(&complete_parent_results.computed_style(), &complete_child_results.computed_style());
+}
+
+#[test]
+fn test_relative_font_sizes() {
+    let style = "div { font-size: 3ex; } span { font-size: 1ex; }";
+    do complete_child_test(style) |parent, child| {
+        assert!(parent.font_size_px() == 24.0);
+        assert!(child.font_size_px() == 12.0);
+    }
+    let style = "div { font-size: 150%; } span { font-size: 0.5em; }";
+    do complete_child_test(style) |parent, child| {
+        assert!(parent.font_size_px() == 24.0);
+        assert!(child.font_size_px() == 12.0);
+    }
+}
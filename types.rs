@@ -4,6 +4,7 @@
 
 use n;
 
+#[deriving(Eq, Clone)]
 pub enum StylesheetOrigin {
     OriginUA,
     OriginUser,
@@ -18,4 +19,86 @@ impl StylesheetOrigin {
             OriginAuthor => n::ll::t::CSS_ORIGIN_AUTHOR
         }
     }
+
+    /**
+    CSS 2.1, Section 6.4.1: the rank of declarations from this origin in the
+    cascade, by importance. Higher ranks win.
+    */
+    pub fn cascade_level(&self, important: bool) -> uint {
+        match (*self, important) {
+            (OriginUA, _) => 0,
+            (OriginUser, false) => 1,
+            (OriginAuthor, false) => 2,
+            (OriginAuthor, true) => 3,
+            (OriginUser, true) => 4
+        }
+    }
+}
+
+/** CSS 2.1, Section 7.3: the media types styles are selected for */
+#[deriving(Eq, Clone)]
+pub enum MediaType {
+    MediaTypeAural,
+    MediaTypeBraille,
+    MediaTypeEmbossed,
+    MediaTypeHandheld,
+    MediaTypePrint,
+    MediaTypeProjection,
+    MediaTypeScreen,
+    MediaTypeSpeech,
+    MediaTypeTty,
+    MediaTypeTv
+}
+
+impl MediaType {
+    pub fn to_net(&self) -> n::ll::t::css_media_type {
+        match *self {
+            MediaTypeAural => n::ll::t::CSS_MEDIA_AURAL,
+            MediaTypeBraille => n::ll::t::CSS_MEDIA_BRAILLE,
+            MediaTypeEmbossed => n::ll::t::CSS_MEDIA_EMBOSSED,
+            MediaTypeHandheld => n::ll::t::CSS_MEDIA_HANDHELD,
+            MediaTypePrint => n::ll::t::CSS_MEDIA_PRINT,
+            MediaTypeProjection => n::ll::t::CSS_MEDIA_PROJECTION,
+            MediaTypeScreen => n::ll::t::CSS_MEDIA_SCREEN,
+            MediaTypeSpeech => n::ll::t::CSS_MEDIA_SPEECH,
+            MediaTypeTty => n::ll::t::CSS_MEDIA_TTY,
+            MediaTypeTv => n::ll::t::CSS_MEDIA_TV
+        }
+    }
+
+    /** The name of the media type in media lists, like that of `@media` */
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MediaTypeAural => "aural",
+            MediaTypeBraille => "braille",
+            MediaTypeEmbossed => "embossed",
+            MediaTypeHandheld => "handheld",
+            MediaTypePrint => "print",
+            MediaTypeProjection => "projection",
+            MediaTypeScreen => "screen",
+            MediaTypeSpeech => "speech",
+            MediaTypeTty => "tty",
+            MediaTypeTv => "tv"
+        }
+    }
+}
+
+pub enum PseudoElement {
+    PseudoElementNone,
+    PseudoElementFirstLine,
+    PseudoElementFirstLetter,
+    PseudoElementBefore,
+    PseudoElementAfter
+}
+
+impl PseudoElement {
+    pub fn to_net(&self) -> n::s::CssPseudoElement {
+        match *self {
+            PseudoElementNone => n::s::CssPseudoElementNone,
+            PseudoElementFirstLine => n::s::CssPseudoElementFirstLine,
+            PseudoElementFirstLetter => n::s::CssPseudoElementFirstLetter,
+            PseudoElementBefore => n::s::CssPseudoElementBefore,
+            PseudoElementAfter => n::s::CssPseudoElementAfter
+        }
+    }
 }
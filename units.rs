@@ -6,25 +6,516 @@
 Units used by CSS
 */
 
+use std::cmp::{Ordering, Less, Equal, Greater};
+use n::ll::t::css_fixed;
+
 #[deriving(Eq)]
 pub enum Length {
     Em(f64), // normalized to 'em'
     Px(f64), // normalized to 'px'
+    Ex(f64),
+    Rem(f64),
+    Vw(f64),
+    Vh(f64),
+    Vmin(f64),
+    Vmax(f64),
+}
+
+/** The size of the initial containing block, in px, for viewport-relative lengths */
+#[deriving(Eq, Clone)]
+pub struct Viewport {
+    width: f64,
+    height: f64
 }
 
+// The number of px in one inch, as fixed by CSS 2.1
+pub static PX_PER_INCH: f64 = 96.0;
+
 impl Length {
-    fn rel(self) -> f64 {
+    /** The value of an 'em' length */
+    pub fn rel(self) -> f64 {
         match self {
             Em(x) => x,
             _ => fail!(~"attempted to access relative unit of an absolute length")
         }
     }
-    fn abs(self) -> f64 {
+
+    /** The value of a 'px' length */
+    pub fn abs(self) -> f64 {
         match self {
-            Em(x) => x,
-            _ => fail!(~"attempted to access relative unit of an absolute length")
+            Px(x) => x,
+            _ => fail!(~"attempted to access absolute unit of a relative length")
+        }
+    }
+
+    /** The number of the length, whatever its unit */
+    pub fn value(self) -> f64 {
+        match self {
+            Em(x) | Px(x) | Ex(x) | Rem(x) | Vw(x) | Vh(x) | Vmin(x) | Vmax(x) => x
+        }
+    }
+
+    /** A length of `value` in the same unit as this one */
+    pub fn with_value(self, value: f64) -> Length {
+        match self {
+            Em(_) => Em(value),
+            Px(_) => Px(value),
+            Ex(_) => Ex(value),
+            Rem(_) => Rem(value),
+            Vw(_) => Vw(value),
+            Vh(_) => Vh(value),
+            Vmin(_) => Vmin(value),
+            Vmax(_) => Vmax(value)
+        }
+    }
+
+    pub fn is_absolute(self) -> bool {
+        match self {
+            Px(_) => true,
+            _ => false
+        }
+    }
+
+    /**
+    Resolves to px. Font-relative lengths are relative to `font_size`, or to
+    `root_font_size` for 'rem'; 'ex' is taken as half an 'em', since font
+    metrics aren't available here.
+    */
+    pub fn to_px(self, font_size: f64, root_font_size: f64, viewport: &Viewport) -> f64 {
+        match self {
+            Px(x) => x,
+            Em(x) => x * font_size,
+            Ex(x) => x * font_size / 2.0,
+            Rem(x) => x * root_font_size,
+            Vw(x) => x * viewport.width / 100.0,
+            Vh(x) => x * viewport.height / 100.0,
+            Vmin(x) => x * viewport.width.min(&viewport.height) / 100.0,
+            Vmax(x) => x * viewport.width.max(&viewport.height) / 100.0
+        }
+    }
+
+    /** Like `to_px`, in device pixels of a display with `dpi` pixels per inch */
+    pub fn to_device_px(self, font_size: f64, root_font_size: f64, viewport: &Viewport,
+                        dpi: f64) -> f64 {
+        px_to_device_px(self.to_px(font_size, root_font_size, viewport), dpi)
+    }
+
+    /** Adds two lengths in the same unit; lengths in different units can't be added without context */
+    pub fn checked_add(self, other: Length) -> Option<Length> {
+        if self.same_unit(other) {
+            Some(self.with_value(self.value() + other.value()))
+        } else {
+            None
+        }
+    }
+
+    pub fn checked_sub(self, other: Length) -> Option<Length> {
+        self.checked_add(-other)
+    }
+
+    /** Compares two lengths in the same unit */
+    pub fn partial_cmp(self, other: Length) -> Option<Ordering> {
+        if self.same_unit(other) {
+            Some(cmp_f64(self.value(), other.value()))
+        } else {
+            None
+        }
+    }
+
+    /** Compares two lengths in any units, after resolving them to px */
+    pub fn cmp_px(self, other: Length, font_size: f64, root_font_size: f64,
+                  viewport: &Viewport) -> Ordering {
+        cmp_f64(self.to_px(font_size, root_font_size, viewport),
+                other.to_px(font_size, root_font_size, viewport))
+    }
+
+    fn same_unit(self, other: Length) -> bool {
+        self.with_value(0.0) == other.with_value(0.0)
+    }
+}
+
+impl Mul<f64, Length> for Length {
+    fn mul(&self, factor: &f64) -> Length {
+        self.with_value(self.value() * *factor)
+    }
+}
+
+impl Div<f64, Length> for Length {
+    fn div(&self, divisor: &f64) -> Length {
+        self.with_value(self.value() / *divisor)
+    }
+}
+
+impl Neg<Length> for Length {
+    fn neg(&self) -> Length {
+        self.with_value(-self.value())
+    }
+}
+
+/** Converts CSS px to device pixels of a display with `dpi` pixels per inch */
+pub fn px_to_device_px(px: f64, dpi: f64) -> f64 {
+    px * dpi / PX_PER_INCH
+}
+
+fn cmp_f64(a: f64, b: f64) -> Ordering {
+    if a < b { Less } else if a > b { Greater } else { Equal }
+}
+
+/**
+A fixed-point number with 10 fractional bits, the representation libcss uses
+for its `css_fixed` values. Arithmetic on it is exact for the values layout
+deals in, and it converts to and from `css_fixed` without loss.
+*/
+#[deriving(Eq, Ord, Clone)]
+pub struct Fixed(i32);
+
+pub static FIXED_FRAC_BITS: uint = 10;
+pub static FIXED_ONE: Fixed = Fixed(1 << 10);
+
+impl Fixed {
+    pub fn from_int(value: int) -> Fixed {
+        Fixed((value << FIXED_FRAC_BITS) as i32)
+    }
+
+    /** Rounds to the nearest representable value, as libcss's FLTTOFIX does not */
+    pub fn from_f64(value: f64) -> Fixed {
+        Fixed((value * (1 << FIXED_FRAC_BITS) as f64).round() as i32)
+    }
+
+    pub fn from_css_fixed(value: css_fixed) -> Fixed {
+        Fixed(value as i32)
+    }
+
+    pub fn from_length_px(length: Length, font_size: f64, root_font_size: f64,
+                          viewport: &Viewport) -> Fixed {
+        Fixed::from_f64(length.to_px(font_size, root_font_size, viewport))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        *self as f64 / (1 << FIXED_FRAC_BITS) as f64
+    }
+
+    /** Truncates toward negative infinity */
+    pub fn to_int(self) -> int {
+        (*self >> FIXED_FRAC_BITS) as int
+    }
+
+    pub fn to_css_fixed(self) -> css_fixed {
+        *self as css_fixed
+    }
+}
+
+impl Add<Fixed, Fixed> for Fixed {
+    fn add(&self, other: &Fixed) -> Fixed {
+        Fixed(**self + **other)
+    }
+}
+
+impl Sub<Fixed, Fixed> for Fixed {
+    fn sub(&self, other: &Fixed) -> Fixed {
+        Fixed(**self - **other)
+    }
+}
+
+impl Mul<Fixed, Fixed> for Fixed {
+    fn mul(&self, other: &Fixed) -> Fixed {
+        Fixed(((**self as i64 * **other as i64) >> FIXED_FRAC_BITS) as i32)
+    }
+}
+
+impl Div<Fixed, Fixed> for Fixed {
+    fn div(&self, other: &Fixed) -> Fixed {
+        Fixed(((**self as i64 << FIXED_FRAC_BITS) / **other as i64) as i32)
+    }
+}
+
+impl Neg<Fixed> for Fixed {
+    fn neg(&self) -> Fixed {
+        Fixed(-**self)
+    }
+}
+
+static DEGREES_PER_RADIAN: f64 = 57.29577951308232;
+
+/** An angle (CSS Values 3, Section 7.1) */
+#[deriving(Eq, Clone)]
+pub enum Angle {
+    Deg(f64),
+    Grad(f64),
+    Rad(f64),
+    Turn(f64)
+}
+
+impl Angle {
+    pub fn to_degrees(self) -> f64 {
+        match self {
+            Deg(x) => x,
+            Grad(x) => x * 0.9,
+            Rad(x) => x * DEGREES_PER_RADIAN,
+            Turn(x) => x * 360.0
+        }
+    }
+
+    pub fn to_radians(self) -> f64 {
+        match self {
+            Rad(x) => x,
+            _ => self.to_degrees() / DEGREES_PER_RADIAN
+        }
+    }
+
+    pub fn to_turns(self) -> f64 {
+        match self {
+            Turn(x) => x,
+            _ => self.to_degrees() / 360.0
+        }
+    }
+
+    /** The equivalent angle in [0, 360) degrees */
+    pub fn normalized_degrees(self) -> f64 {
+        let degrees = self.to_degrees() % 360.0;
+        if degrees < 0.0 { degrees + 360.0 } else { degrees }
+    }
+}
+
+/** A duration (CSS Values 3, Section 7.2) */
+#[deriving(Eq, Clone)]
+pub enum Time {
+    S(f64),
+    Ms(f64)
+}
+
+impl Time {
+    pub fn to_seconds(self) -> f64 {
+        match self {
+            S(x) => x,
+            Ms(x) => x / 1000.0
+        }
+    }
+
+    pub fn to_ms(self) -> f64 {
+        match self {
+            S(x) => x * 1000.0,
+            Ms(x) => x
+        }
+    }
+}
+
+/** A frequency (CSS Values 3, Section 7.3) */
+#[deriving(Eq, Clone)]
+pub enum Frequency {
+    Hz(f64),
+    KHz(f64)
+}
+
+impl Frequency {
+    pub fn to_hz(self) -> f64 {
+        match self {
+            Hz(x) => x,
+            KHz(x) => x * 1000.0
+        }
+    }
+
+    pub fn to_khz(self) -> f64 {
+        self.to_hz() / 1000.0
+    }
+}
+
+/** The pixel density of an output device (CSS Values 3, Section 7.4) */
+#[deriving(Eq, Clone)]
+pub enum Resolution {
+    Dpi(f64),
+    Dpcm(f64),
+    Dppx(f64)
+}
+
+impl Resolution {
+    /** Dots per CSS px, the ratio of device pixels to px */
+    pub fn to_dppx(self) -> f64 {
+        match self {
+            Dpi(x) => x / PX_PER_INCH,
+            Dpcm(x) => x * 2.54 / PX_PER_INCH,
+            Dppx(x) => x
+        }
+    }
+
+    pub fn to_dpi(self) -> f64 {
+        match self {
+            Dpi(x) => x,
+            _ => self.to_dppx() * PX_PER_INCH
+        }
+    }
+
+    pub fn to_dpcm(self) -> f64 {
+        match self {
+            Dpcm(x) => x,
+            _ => self.to_dpi() / 2.54
+        }
+    }
+}
+
+/** A number with any unit libcss knows about */
+#[deriving(Eq, Clone)]
+pub enum Dimension {
+    DimensionLength(Length),
+    DimensionPercentage(f64),
+    DimensionAngle(Angle),
+    DimensionTime(Time),
+    DimensionFrequency(Frequency),
+    DimensionResolution(Resolution)
+}
+
+/**
+A calc() expression, simplified to a sum of at most one length per unit and
+a percentage. `calc(100% - 2em + 4px)` is `[Em(-2.0), Px(4.0)]` and `100.0`.
+*/
+#[deriving(Eq, Clone)]
+pub struct Calc {
+    // Ordered by unit, as `unit_index` orders them
+    lengths: ~[Length],
+    percentage: Option<f64>
+}
+
+impl Calc {
+    pub fn from_length(length: Length) -> Calc {
+        Calc { lengths: ~[length], percentage: None }
+    }
+
+    pub fn from_percentage(percentage: f64) -> Calc {
+        Calc { lengths: ~[], percentage: Some(percentage) }
+    }
+
+    pub fn add(&self, other: &Calc) -> Calc {
+        let mut result = self.clone();
+        for &length in other.lengths.iter() {
+            result.add_length(length);
+        }
+        result.percentage = match (self.percentage, other.percentage) {
+            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), None) | (None, Some(a)) => Some(a),
+            (None, None) => None
+        };
+        result
+    }
+
+    pub fn scale(&self, factor: f64) -> Calc {
+        Calc {
+            lengths: self.lengths.map(|&length| length * factor),
+            percentage: self.percentage.map(|&p| p * factor)
+        }
+    }
+
+    /** Resolves every length to px, leaving only the percentage unresolved */
+    pub fn simplify(&self, font_size: f64, root_font_size: f64, viewport: &Viewport) -> Calc {
+        let mut px = 0.0;
+        for &length in self.lengths.iter() {
+            px += length.to_px(font_size, root_font_size, viewport);
+        }
+        Calc { lengths: ~[Px(px)], percentage: self.percentage }
+    }
+
+    /**
+    Resolves to px against the size of the containing block, or returns `None`
+    if the expression still has relative lengths and needs simplifying first
+    */
+    pub fn resolve(&self, containing_block: f64) -> Option<f64> {
+        let mut px = 0.0;
+        for &length in self.lengths.iter() {
+            match length {
+                Px(x) => px += x,
+                _ => return None
+            }
+        }
+        Some(self.add_percentage(px, containing_block))
+    }
+
+    /** Resolves to px, relative lengths included, like `Length::to_px` */
+    pub fn to_px(&self, containing_block: f64, font_size: f64, root_font_size: f64,
+                 viewport: &Viewport) -> f64 {
+        let mut px = 0.0;
+        for &length in self.lengths.iter() {
+            px += length.to_px(font_size, root_font_size, viewport);
+        }
+        self.add_percentage(px, containing_block)
+    }
+
+    /** The plainest form of the expression, a single length or percentage if possible */
+    pub fn to_length_or_percentage_or_calc(&self) -> LengthOrPercentageOrCalc {
+        match (self.lengths.len(), self.percentage) {
+            (0, None) => LPCLength(Px(0.0)),
+            (0, Some(p)) => LPCPercentage(p),
+            (1, None) => LPCLength(self.lengths[0]),
+            _ => LPCCalc(self.clone())
+        }
+    }
+
+    fn add_percentage(&self, px: f64, containing_block: f64) -> f64 {
+        match self.percentage {
+            Some(p) => px + p * containing_block / 100.0,
+            None => px
+        }
+    }
+
+    fn add_length(&mut self, length: Length) {
+        let index = unit_index(length);
+        let mut i = 0;
+        while i < self.lengths.len() && unit_index(self.lengths[i]) < index {
+            i += 1;
+        }
+        if i < self.lengths.len() && unit_index(self.lengths[i]) == index {
+            self.lengths[i] = self.lengths[i].checked_add(length).unwrap();
+        } else {
+            self.lengths.insert(i, length);
+        }
+    }
+}
+
+fn unit_index(length: Length) -> uint {
+    match length {
+        Px(_) => 0,
+        Em(_) => 1,
+        Ex(_) => 2,
+        Rem(_) => 3,
+        Vw(_) => 4,
+        Vh(_) => 5,
+        Vmin(_) => 6,
+        Vmax(_) => 7
+    }
+}
+
+/**
+A length, a percentage, or a calc() expression mixing the two. Computed
+styles don't produce calc() yet: libcss parses sheets as CSS 2.1, and
+netsurfcss's `CssUnit` has no way to hand an expression over.
+*/
+#[deriving(Eq, Clone)]
+pub enum LengthOrPercentageOrCalc {
+    LPCLength(Length),
+    LPCPercentage(f64),
+    LPCCalc(Calc)
+}
+
+impl LengthOrPercentageOrCalc {
+    pub fn to_calc(&self) -> Calc {
+        match *self {
+            LPCLength(length) => Calc::from_length(length),
+            LPCPercentage(p) => Calc::from_percentage(p),
+            LPCCalc(ref calc) => calc.clone()
         }
     }
+
+    /**
+    Resolves an absolute length, percentage or simplified calc() to px, or
+    returns `None` for relative lengths
+    */
+    pub fn resolve(&self, containing_block: f64) -> Option<f64> {
+        self.to_calc().resolve(containing_block)
+    }
+
+    /** Resolves to px, relative lengths included, like `Length::to_px` */
+    pub fn to_px(&self, containing_block: f64, font_size: f64, root_font_size: f64,
+                 viewport: &Viewport) -> f64 {
+        self.to_calc().to_px(containing_block, font_size, root_font_size, viewport)
+    }
 }
 
 #[deriving(Eq)]
@@ -60,3 +551,529 @@ pub enum GenericFontFamily {
     Monospace,
 }
 
+pub mod parsing {
+    use std::ascii::StrAsciiExt;
+    use std::either::{Either, Left, Right};
+    use super::{Length, Px, Em, Ex, Rem, Vw, Vh, Vmin, Vmax};
+    use super::{Calc, LengthOrPercentageOrCalc, LPCLength, LPCPercentage};
+    use super::{Angle, Deg, Grad, Rad, Turn, Time, S, Ms, Frequency, Hz, KHz};
+    use super::{Resolution, Dpi, Dpcm, Dppx, Dimension, DimensionLength, DimensionPercentage};
+    use super::{DimensionAngle, DimensionTime, DimensionFrequency, DimensionResolution};
+
+    /** Splits a dimension like `12.5px` or `1e2px` into its number and lowercased unit */
+    pub fn parse_dimension(value: &str) -> Option<(f64, ~str)> {
+        let value = value.trim();
+        let unit_start = number_end(value, 0);
+        let number: Option<f64> = FromStr::from_str(value.slice_to(unit_start));
+        match number {
+            Some(number) => Some((number, value.slice_from(unit_start).to_ascii_lower())),
+            None => None
+        }
+    }
+
+    /**
+    The end of the number starting at `start` of `value`: a sign, digits with
+    an optional fraction, then an optional exponent. The 'e' of a unit like
+    'em' isn't an exponent, as no digits follow it.
+    */
+    fn number_end(value: &str, start: uint) -> uint {
+        let bytes = value.as_bytes();
+        let is_sign = |i: uint| i < bytes.len() && (bytes[i] == '+' as u8 || bytes[i] == '-' as u8);
+        let is_digit = |i: uint| i < bytes.len() && (bytes[i] as char).is_digit();
+        let mut end = start;
+        if is_sign(end) {
+            end += 1;
+        }
+        while is_digit(end) || (end < bytes.len() && bytes[end] == '.' as u8) {
+            end += 1;
+        }
+        if end < bytes.len() && (bytes[end] == 'e' as u8 || bytes[end] == 'E' as u8) {
+            let mut exponent = end + 1;
+            if is_sign(exponent) {
+                exponent += 1;
+            }
+            if is_digit(exponent) {
+                end = exponent;
+                while is_digit(end) {
+                    end += 1;
+                }
+            }
+        }
+        end
+    }
+
+    /** Parses a length, normalizing absolute units to px */
+    pub fn parse_length(value: &str) -> Option<Length> {
+        match parse_length_or_percentage(value) {
+            Some(Left(length)) => Some(length),
+            _ => None
+        }
+    }
+
+    pub fn parse_length_or_percentage(value: &str) -> Option<Either<Length, f64>> {
+        let (number, unit) = match parse_dimension(value) {
+            Some(dimension) => dimension,
+            None => return None
+        };
+        match unit.as_slice() {
+            "px" => Some(Left(Px(number))),
+            "em" => Some(Left(Em(number))),
+            "ex" => Some(Left(Ex(number))),
+            "rem" => Some(Left(Rem(number))),
+            "vw" => Some(Left(Vw(number))),
+            "vh" => Some(Left(Vh(number))),
+            "vmin" => Some(Left(Vmin(number))),
+            "vmax" => Some(Left(Vmax(number))),
+            "pt" => Some(Left(Px(number / 72.0 * 96.0))),
+            "cm" => Some(Left(Px(number / 2.54 * 96.0))),
+            "mm" => Some(Left(Px(number / 25.4 * 96.0))),
+            "in" => Some(Left(Px(number * 96.0))),
+            "pc" => Some(Left(Px(number / 6.0 * 96.0))),
+            "%" => Some(Right(number)),
+            // Unitless lengths are only valid when zero
+            "" if number == 0.0 => Some(Left(Px(0.0))),
+            _ => None
+        }
+    }
+
+    pub fn parse_angle(value: &str) -> Option<Angle> {
+        match parse_dimension(value) {
+            Some((number, unit)) => match unit.as_slice() {
+                "deg" => Some(Deg(number)),
+                "grad" => Some(Grad(number)),
+                "rad" => Some(Rad(number)),
+                "turn" => Some(Turn(number)),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    pub fn parse_time(value: &str) -> Option<Time> {
+        match parse_dimension(value) {
+            Some((number, unit)) => match unit.as_slice() {
+                "s" => Some(S(number)),
+                "ms" => Some(Ms(number)),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    pub fn parse_frequency(value: &str) -> Option<Frequency> {
+        match parse_dimension(value) {
+            Some((number, unit)) => match unit.as_slice() {
+                "hz" => Some(Hz(number)),
+                "khz" => Some(KHz(number)),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    pub fn parse_resolution(value: &str) -> Option<Resolution> {
+        match parse_dimension(value) {
+            Some((number, unit)) => match unit.as_slice() {
+                "dpi" => Some(Dpi(number)),
+                "dpcm" => Some(Dpcm(number)),
+                "dppx" | "x" => Some(Dppx(number)),
+                _ => None
+            },
+            None => None
+        }
+    }
+
+    /** Parses a number with any unit, normalizing absolute lengths to px */
+    pub fn parse_any_dimension(value: &str) -> Option<Dimension> {
+        match parse_length_or_percentage(value) {
+            Some(Left(length)) => return Some(DimensionLength(length)),
+            Some(Right(percentage)) => return Some(DimensionPercentage(percentage)),
+            None => ()
+        }
+        match parse_angle(value) {
+            Some(angle) => return Some(DimensionAngle(angle)),
+            None => ()
+        }
+        match parse_time(value) {
+            Some(time) => return Some(DimensionTime(time)),
+            None => ()
+        }
+        match parse_frequency(value) {
+            Some(frequency) => return Some(DimensionFrequency(frequency)),
+            None => ()
+        }
+        parse_resolution(value).map(|&resolution| DimensionResolution(resolution))
+    }
+
+    /** Parses a length, a percentage or a `calc()` expression over them */
+    pub fn parse_length_or_percentage_or_calc(value: &str) -> Option<LengthOrPercentageOrCalc> {
+        let value = value.trim();
+        if is_calc(value) {
+            return parse_calc(value);
+        }
+        match parse_length_or_percentage(value) {
+            Some(Left(length)) => Some(LPCLength(length)),
+            Some(Right(percentage)) => Some(LPCPercentage(percentage)),
+            None => None
+        }
+    }
+
+    /**
+    Parses a `calc()` expression, with nested parentheses and `calc()`s, `+`
+    and `-` between lengths and percentages, and `*` or `/` by numbers. The
+    result is simplified as far as it can be without knowing font sizes, the
+    viewport or the containing block.
+    */
+    pub fn parse_calc(value: &str) -> Option<LengthOrPercentageOrCalc> {
+        let value = value.trim();
+        if !is_calc(value) {
+            return None;
+        }
+        let mut parser = CalcParser { input: value, pos: 0 };
+        match parser.parse_value() {
+            Some(CalcValue(ref calc)) if parser.at_end() => {
+                Some(calc.to_length_or_percentage_or_calc())
+            }
+            _ => None
+        }
+    }
+
+    fn is_calc(value: &str) -> bool {
+        value.len() >= 5 && value.is_char_boundary(5)
+            && value.slice_to(5).eq_ignore_ascii_case("calc(")
+    }
+
+    // The operands of calc() operators: plain numbers, or lengths and percentages
+    enum CalcTerm {
+        CalcNumber(f64),
+        CalcValue(Calc)
+    }
+
+    struct CalcParser<'self> {
+        input: &'self str,
+        pos: uint
+    }
+
+    impl<'self> CalcParser<'self> {
+        // The next character if it's ASCII, as everything calc() accepts is,
+        // so that `pos` never lands inside a multibyte character
+        fn peek(&self) -> Option<char> {
+            if self.pos < self.input.len() && self.input[self.pos] < 0x80 {
+                Some(self.input[self.pos] as char)
+            } else {
+                None
+            }
+        }
+
+        fn at_end(&self) -> bool {
+            self.input.slice_from(self.pos).trim().is_empty()
+        }
+
+        /** Skips whitespace, returning whether there was any */
+        fn skip_whitespace(&mut self) -> bool {
+            let start = self.pos;
+            while self.peek().map_default(false, |&c| c.is_whitespace()) {
+                self.pos += 1;
+            }
+            self.pos != start
+        }
+
+        fn parse_sum(&mut self) -> Option<CalcTerm> {
+            let mut sum = match self.parse_product() {
+                Some(term) => term,
+                None => return None
+            };
+            loop {
+                let before = self.pos;
+                let spaced = self.skip_whitespace();
+                let sign = match self.peek() {
+                    Some('+') => 1.0,
+                    Some('-') => -1.0,
+                    _ => {
+                        self.pos = before;
+                        return Some(sum);
+                    }
+                };
+                self.pos += 1;
+                // Whitespace is required around + and -, so they aren't read as signs
+                if !spaced || !self.skip_whitespace() {
+                    return None;
+                }
+                let term = match self.parse_product() {
+                    Some(term) => term,
+                    None => return None
+                };
+                sum = match (sum, term) {
+                    (CalcNumber(a), CalcNumber(b)) => CalcNumber(a + sign * b),
+                    (CalcValue(ref a), CalcValue(ref b)) => CalcValue(a.add(&b.scale(sign))),
+                    _ => return None
+                };
+            }
+        }
+
+        fn parse_product(&mut self) -> Option<CalcTerm> {
+            let mut product = match self.parse_value() {
+                Some(term) => term,
+                None => return None
+            };
+            loop {
+                let before = self.pos;
+                self.skip_whitespace();
+                let multiply = match self.peek() {
+                    Some('*') => true,
+                    Some('/') => false,
+                    _ => {
+                        self.pos = before;
+                        return Some(product);
+                    }
+                };
+                self.pos += 1;
+                self.skip_whitespace();
+                let operand = match self.parse_value() {
+                    Some(term) => term,
+                    None => return None
+                };
+                // One side of * and the right side of / must be plain numbers
+                product = match (product, operand, multiply) {
+                    (CalcNumber(a), CalcNumber(b), true) => CalcNumber(a * b),
+                    (CalcNumber(a), CalcNumber(b), false) if b != 0.0 => CalcNumber(a / b),
+                    (CalcValue(ref a), CalcNumber(b), true) |
+                    (CalcNumber(b), CalcValue(ref a), true) => CalcValue(a.scale(b)),
+                    (CalcValue(ref a), CalcNumber(b), false) if b != 0.0 => {
+                        CalcValue(a.scale(1.0 / b))
+                    }
+                    _ => return None
+                };
+            }
+        }
+
+        fn parse_value(&mut self) -> Option<CalcTerm> {
+            if self.peek() == Some('(') {
+                self.pos += 1;
+                self.parse_nested()
+            } else if is_calc(self.input.slice_from(self.pos)) {
+                self.pos += 5;
+                self.parse_nested()
+            } else {
+                self.parse_dimension()
+            }
+        }
+
+        fn parse_nested(&mut self) -> Option<CalcTerm> {
+            self.skip_whitespace();
+            let sum = self.parse_sum();
+            self.skip_whitespace();
+            if self.peek() != Some(')') {
+                return None;
+            }
+            self.pos += 1;
+            sum
+        }
+
+        fn parse_dimension(&mut self) -> Option<CalcTerm> {
+            let start = self.pos;
+            self.pos = number_end(self.input, start);
+            let unit_start = self.pos;
+            while self.peek().map_default(false, |&c| c.is_alphabetic() || c == '%') {
+                self.pos += 1;
+            }
+
+            let token = self.input.slice(start, self.pos);
+            if unit_start == self.pos {
+                let number: Option<f64> = FromStr::from_str(token);
+                return number.map(|&n| CalcNumber(n));
+            }
+            match parse_length_or_percentage(token) {
+                Some(Left(length)) => Some(CalcValue(Calc::from_length(length))),
+                Some(Right(percentage)) => Some(CalcValue(Calc::from_percentage(percentage))),
+                None => None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cmp::{Less, Equal, Greater};
+    use super::*;
+    use super::parsing::{parse_length, parse_calc, parse_length_or_percentage_or_calc};
+    use super::parsing::{parse_angle, parse_time, parse_frequency, parse_resolution};
+    use super::parsing::parse_any_dimension;
+
+    static VIEWPORT: Viewport = Viewport { width: 800.0, height: 600.0 };
+
+    #[test]
+    fn test_accessors() {
+        assert!(Em(2.0).rel() == 2.0);
+        assert!(Px(3.0).abs() == 3.0);
+        assert!(Vh(4.0).value() == 4.0);
+        assert!(Px(1.0).is_absolute());
+        assert!(!Rem(1.0).is_absolute());
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_abs_of_relative_length() {
+        Em(1.0).abs();
+    }
+
+    #[test]
+    fn test_to_px() {
+        assert!(Px(10.0).to_px(16.0, 20.0, &VIEWPORT) == 10.0);
+        assert!(Em(2.0).to_px(16.0, 20.0, &VIEWPORT) == 32.0);
+        assert!(Ex(2.0).to_px(16.0, 20.0, &VIEWPORT) == 16.0);
+        assert!(Rem(2.0).to_px(16.0, 20.0, &VIEWPORT) == 40.0);
+        assert!(Vw(10.0).to_px(16.0, 20.0, &VIEWPORT) == 80.0);
+        assert!(Vh(10.0).to_px(16.0, 20.0, &VIEWPORT) == 60.0);
+        assert!(Vmin(50.0).to_px(16.0, 20.0, &VIEWPORT) == 300.0);
+        assert!(Vmax(50.0).to_px(16.0, 20.0, &VIEWPORT) == 400.0);
+        assert!(Px(96.0).to_device_px(16.0, 20.0, &VIEWPORT, 192.0) == 192.0);
+        assert!(px_to_device_px(10.0, 144.0) == 15.0);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert!(Em(1.5) * 2.0 == Em(3.0));
+        assert!(Px(9.0) / 3.0 == Px(3.0));
+        assert!(-Vw(5.0) == Vw(-5.0));
+        assert!(Px(1.0).checked_add(Px(2.0)) == Some(Px(3.0)));
+        assert!(Em(1.0).checked_sub(Em(0.25)) == Some(Em(0.75)));
+        assert!(Px(1.0).checked_add(Em(2.0)) == None);
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert!(Px(1.0).partial_cmp(Px(2.0)) == Some(Less));
+        assert!(Em(2.0).partial_cmp(Em(2.0)) == Some(Equal));
+        assert!(Em(1.0).partial_cmp(Px(16.0)) == None);
+        assert!(Em(1.0).cmp_px(Px(16.0), 16.0, 16.0, &VIEWPORT) == Equal);
+        assert!(Vw(1.0).cmp_px(Px(7.0), 16.0, 16.0, &VIEWPORT) == Greater);
+    }
+
+    #[test]
+    fn test_parsing_units() {
+        assert!(parse_length("2rem") == Some(Rem(2.0)));
+        assert!(parse_length("1.5ex") == Some(Ex(1.5)));
+        assert!(parse_length("50vw") == Some(Vw(50.0)));
+        assert!(parse_length("50VH") == Some(Vh(50.0)));
+        assert!(parse_length("10vmin") == Some(Vmin(10.0)));
+        assert!(parse_length("10vmax") == Some(Vmax(10.0)));
+    }
+
+    #[test]
+    fn test_angle() {
+        assert!(Deg(90.0).to_degrees() == 90.0);
+        assert!(Grad(100.0).to_degrees() == 90.0);
+        assert!(Turn(0.25).to_degrees() == 90.0);
+        assert!((Rad(1.5707963267948966).to_degrees() - 90.0).abs() < 1e-9);
+        assert!((Deg(180.0).to_radians() - 3.141592653589793).abs() < 1e-12);
+        assert!(Grad(200.0).to_turns() == 0.5);
+        assert!(Deg(-90.0).normalized_degrees() == 270.0);
+        assert!(Turn(2.5).normalized_degrees() == 180.0);
+    }
+
+    #[test]
+    fn test_time_frequency_resolution() {
+        assert!(Ms(250.0).to_seconds() == 0.25);
+        assert!(S(1.5).to_ms() == 1500.0);
+        assert!(KHz(2.0).to_hz() == 2000.0);
+        assert!(Hz(500.0).to_khz() == 0.5);
+        assert!(Dpi(192.0).to_dppx() == 2.0);
+        assert!(Dppx(1.0).to_dpi() == 96.0);
+        assert!((Dpcm(37.8).to_dpi() - 96.012).abs() < 1e-9);
+        assert!((Dpi(254.0).to_dpcm() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_parsing_dimensions() {
+        assert!(parse_angle("45DEG") == Some(Deg(45.0)));
+        assert!(parse_angle("-0.5turn") == Some(Turn(-0.5)));
+        assert!(parse_angle("10grad") == Some(Grad(10.0)));
+        assert!(parse_angle("45") == None);
+        assert!(parse_time("0.5s") == Some(S(0.5)));
+        assert!(parse_time("200ms") == Some(Ms(200.0)));
+        assert!(parse_frequency("3kHz") == Some(KHz(3.0)));
+        assert!(parse_resolution("2x") == Some(Dppx(2.0)));
+        assert!(parse_resolution("300dpi") == Some(Dpi(300.0)));
+        assert!(parse_any_dimension("1in") == Some(DimensionLength(Px(96.0))));
+        assert!(parse_any_dimension("5%") == Some(DimensionPercentage(5.0)));
+        assert!(parse_any_dimension("1rad") == Some(DimensionAngle(Rad(1.0))));
+        assert!(parse_any_dimension("1s") == Some(DimensionTime(S(1.0))));
+        assert!(parse_any_dimension("1hz") == Some(DimensionFrequency(Hz(1.0))));
+        assert!(parse_any_dimension("1dpcm") == Some(DimensionResolution(Dpcm(1.0))));
+        assert!(parse_any_dimension("1foo") == None);
+    }
+
+    #[test]
+    fn test_parsing_calc() {
+        let calc = |lengths: ~[Length], percentage: Option<f64>| {
+            Some(LPCCalc(Calc { lengths: lengths, percentage: percentage }))
+        };
+        assert!(parse_calc("calc(100% - 2em)") == calc(~[Em(-2.0)], Some(100.0)));
+        assert!(parse_calc("CALC( 1em + 2px - 3em )") == calc(~[Px(2.0), Em(-2.0)], None));
+        assert!(parse_calc("calc(1in + 4px)") == Some(LPCLength(Px(100.0))));
+        assert!(parse_calc("calc(50% * 2)") == Some(LPCPercentage(100.0)));
+        assert!(parse_calc("calc(3 * (10px + 1vw) / 2)") == calc(~[Px(15.0), Vw(1.5)], None));
+        assert!(parse_calc("calc(10% - calc(2rem - (5px + 50%)))")
+                == calc(~[Px(5.0), Rem(-2.0)], Some(60.0)));
+        assert!(parse_calc("calc(1px - 1px)") == Some(LPCLength(Px(0.0))));
+        assert!(parse_calc("calc(-1em + -2em)") == Some(LPCLength(Em(-3.0))));
+        assert!(parse_calc("calc(1e2px + 1%)") == calc(~[Px(100.0)], Some(1.0)));
+        assert!(parse_calc("calc(2.5E-1em * 4)") == Some(LPCLength(Em(1.0))));
+        assert!(parse_calc("calc(1e + 1px)") == None);
+
+        assert!(parse_calc("calc(10px)") == Some(LPCLength(Px(10.0))));
+        assert!(parse_calc("calc(2)") == None);
+        assert!(parse_calc("calc(10px + 2)") == None);
+        assert!(parse_calc("calc(10px * 2px)") == None);
+        assert!(parse_calc("calc(10px / 0)") == None);
+        assert!(parse_calc("calc(2 / 10px)") == None);
+        assert!(parse_calc("calc(10px -5px)") == None);
+        assert!(parse_calc("calc(10px+5px)") == None);
+        assert!(parse_calc("calc(10px + 5px") == None);
+        assert!(parse_calc("calc(10px) + 5px") == None);
+        assert!(parse_calc("10px") == None);
+        assert!(parse_calc("cal€(1px)") == None);
+        assert!(parse_calc("calc(1p€)") == None);
+        assert!(parse_calc("calc(1px + 2€m)") == None);
+        assert!(parse_length_or_percentage_or_calc("10px") == Some(LPCLength(Px(10.0))));
+        assert!(parse_length_or_percentage_or_calc(" 20% ") == Some(LPCPercentage(20.0)));
+    }
+
+    #[test]
+    fn test_calc_simplify_and_resolve() {
+        let calc = Calc { lengths: ~[Px(4.0), Em(2.0), Rem(1.0), Vw(10.0)], percentage: Some(50.0) };
+        let simplified = calc.simplify(10.0, 20.0, &VIEWPORT);
+        assert!(simplified == Calc { lengths: ~[Px(124.0)], percentage: Some(50.0) });
+        assert!(simplified.resolve(100.0) == Some(174.0));
+        assert!(calc.to_px(100.0, 10.0, 20.0, &VIEWPORT) == 174.0);
+        assert!(LPCPercentage(25.0).resolve(80.0) == Some(20.0));
+        assert!(LPCLength(Px(3.0)).resolve(80.0) == Some(3.0));
+        assert!(LPCLength(Em(2.0)).to_px(80.0, 10.0, 20.0, &VIEWPORT) == 20.0);
+        assert!(LPCLength(Em(1.0)).to_calc().add(&Calc::from_length(Px(1.0))).scale(2.0)
+                == Calc { lengths: ~[Px(2.0), Em(2.0)], percentage: None });
+    }
+
+    #[test]
+    fn test_resolving_unsimplified_calc() {
+        assert!(Calc::from_length(Em(1.0)).resolve(100.0) == None);
+        assert!(LPCLength(Vw(1.0)).resolve(100.0) == None);
+    }
+
+    #[test]
+    fn test_fixed() {
+        assert!(Fixed::from_int(3).to_f64() == 3.0);
+        assert!(Fixed::from_f64(1.5) == Fixed(1536));
+        assert!(Fixed::from_f64(-0.25).to_f64() == -0.25);
+        assert!(Fixed::from_f64(2.5).to_int() == 2);
+        assert!(Fixed::from_f64(-2.5).to_int() == -3);
+        assert!(Fixed::from_f64(1.5) + FIXED_ONE == Fixed::from_f64(2.5));
+        assert!(Fixed::from_f64(1.5) - FIXED_ONE == Fixed::from_f64(0.5));
+        assert!(Fixed::from_f64(1.5) * Fixed::from_f64(-2.0) == Fixed::from_f64(-3.0));
+        assert!(Fixed::from_f64(3.0) / Fixed::from_f64(4.0) == Fixed::from_f64(0.75));
+        assert!(-FIXED_ONE == Fixed::from_int(-1));
+        assert!(Fixed::from_int(1) < Fixed::from_f64(1.001));
+        assert!(Fixed::from_css_fixed(Fixed::from_f64(12.75).to_css_fixed()).to_f64() == 12.75);
+        assert!(Fixed::from_length_px(Em(2.0), 10.0, 16.0, &VIEWPORT) == Fixed::from_int(20));
+    }
+}
@@ -55,7 +55,8 @@ pub enum CSSBorderWidth {
 #[deriving(Eq)]
 pub enum CSSBorderColor {
     CSSBorderColorColor(Color),
-    CSSBorderColorTransparent
+    CSSBorderColorTransparent,
+    CSSBorderColorCurrentColor
 }
 
 #[deriving(Eq, Clone)]
@@ -104,7 +105,7 @@ pub enum CSSPosition {
 #[deriving(Eq)]
 pub enum CSSTop {
     CSSTopLength(Length),
-    CSSTopPercentage,
+    CSSTopPercentage(f64),
     CSSTopAuto
 }
 
@@ -205,10 +206,137 @@ pub enum CSSVisibility {
     CSSVisibilityCollapse
 }
 
+#[deriving(Eq)]
+pub enum CSSZIndex {
+    CSSZIndexAuto,
+    CSSZIndexInteger(int)
+}
+
+#[deriving(Eq)]
+pub enum CSSClip {
+    CSSClipAuto,
+    CSSClipShape(CSSClipRect)
+}
+
+/** The `rect(<top>, <right>, <bottom>, <left>)` shape of the 'clip' property */
+#[deriving(Eq)]
+pub struct CSSClipRect {
+    top: CSSClipEdge,
+    right: CSSClipEdge,
+    bottom: CSSClipEdge,
+    left: CSSClipEdge
+}
+
+#[deriving(Eq)]
+pub enum CSSClipEdge {
+    CSSClipEdgeLength(Length),
+    CSSClipEdgeAuto
+}
+
 // CSS 2.1, Section 12 - Generated content, automatic numbering, and lists
 
+#[deriving(Eq)]
+pub enum CSSContent {
+    CSSContentNormal,
+    CSSContentNone,
+    CSSContentContent(~[CSSContentItem])
+}
+
+#[deriving(Eq)]
+pub enum CSSContentItem {
+    CSSContentItemString(~str),
+    CSSContentItemUri(Url),
+    CSSContentItemCounter(~str, CSSListStyleType),
+    CSSContentItemCounters(~str, ~str, CSSListStyleType),
+    CSSContentItemAttr(~str),
+    CSSContentItemOpenQuote,
+    CSSContentItemCloseQuote,
+    CSSContentItemNoOpenQuote,
+    CSSContentItemNoCloseQuote
+}
+
+/** A counter name and the integer used to reset or increment it */
+#[deriving(Eq)]
+pub struct CSSCounter {
+    name: ~str,
+    value: int
+}
+
+#[deriving(Eq)]
+pub enum CSSCounterReset {
+    CSSCounterResetNone,
+    CSSCounterResetCounters(~[CSSCounter])
+}
+
+#[deriving(Eq)]
+pub enum CSSCounterIncrement {
+    CSSCounterIncrementNone,
+    CSSCounterIncrementCounters(~[CSSCounter])
+}
+
+#[deriving(Eq)]
+pub enum CSSQuotes {
+    CSSQuotesNone,
+    CSSQuotesStrings(~[(~str, ~str)])
+}
+
+#[deriving(Eq, Clone)]
+pub enum CSSListStyleType {
+    CSSListStyleTypeDisc,
+    CSSListStyleTypeCircle,
+    CSSListStyleTypeSquare,
+    CSSListStyleTypeDecimal,
+    CSSListStyleTypeDecimalLeadingZero,
+    CSSListStyleTypeLowerRoman,
+    CSSListStyleTypeUpperRoman,
+    CSSListStyleTypeLowerGreek,
+    CSSListStyleTypeLowerLatin,
+    CSSListStyleTypeUpperLatin,
+    CSSListStyleTypeArmenian,
+    CSSListStyleTypeGeorgian,
+    CSSListStyleTypeLowerAlpha,
+    CSSListStyleTypeUpperAlpha,
+    CSSListStyleTypeNone
+}
+
+#[deriving(Eq)]
+pub enum CSSListStylePosition {
+    CSSListStylePositionInside,
+    CSSListStylePositionOutside
+}
+
+#[deriving(Eq)]
+pub enum CSSListStyleImage {
+    CSSListStyleImageUri(Url),
+    CSSListStyleImageNone
+}
+
 // CSS 2.1, Section 13 - Paged media
 
+#[deriving(Eq)]
+pub enum CSSPageBreakBefore {
+    CSSPageBreakBeforeAuto,
+    CSSPageBreakBeforeAlways,
+    CSSPageBreakBeforeAvoid,
+    CSSPageBreakBeforeLeft,
+    CSSPageBreakBeforeRight
+}
+
+#[deriving(Eq)]
+pub enum CSSPageBreakAfter {
+    CSSPageBreakAfterAuto,
+    CSSPageBreakAfterAlways,
+    CSSPageBreakAfterAvoid,
+    CSSPageBreakAfterLeft,
+    CSSPageBreakAfterRight
+}
+
+#[deriving(Eq)]
+pub enum CSSPageBreakInside {
+    CSSPageBreakInsideAuto,
+    CSSPageBreakInsideAvoid
+}
+
 // CSS 2.1, Section 14 - Colors and Backgrounds
 
 #[deriving(Eq)]
@@ -219,7 +347,8 @@ pub enum CSSColor {
 #[deriving(Eq)]
 pub enum CSSBackgroundColor {
     CSSBackgroundColorColor(Color),
-    CSSBackgroundColorTransparent
+    CSSBackgroundColorTransparent,
+    CSSBackgroundColorCurrentColor
 }
 
 #[deriving(Eq)]
@@ -324,3 +453,37 @@ pub enum CSSTextTransform {
 
 // CSS 2.1, Section 18 - User interface
 
+#[deriving(Eq)]
+pub enum CSSCursorKeyword {
+    CSSCursorAuto,
+    CSSCursorCrosshair,
+    CSSCursorDefault,
+    CSSCursorPointer,
+    CSSCursorMove,
+    CSSCursorEResize,
+    CSSCursorNeResize,
+    CSSCursorNwResize,
+    CSSCursorNResize,
+    CSSCursorSeResize,
+    CSSCursorSwResize,
+    CSSCursorSResize,
+    CSSCursorWResize,
+    CSSCursorText,
+    CSSCursorWait,
+    CSSCursorHelp,
+    CSSCursorProgress
+}
+
+/** A list of cursor images to try in order, followed by the mandatory keyword fallback */
+#[deriving(Eq)]
+pub struct CSSCursor {
+    urls: ~[Url],
+    keyword: CSSCursorKeyword
+}
+
+#[deriving(Eq)]
+pub enum CSSOutlineColor {
+    CSSOutlineColorColor(Color),
+    CSSOutlineColorInvert
+}
+